@@ -86,7 +86,7 @@ fn get_child_container(container: &mut safe_nfs::rest::Container) -> Result<::sa
     let sub_containers = container.get_containers();
     let ref container_name = get_user_string("Container name");
     let info = sub_containers.iter().find(|info| *info.get_name() == *container_name);
-    let container_info = try!(info.ok_or(::safe_nfs::errors::NfsError::DirectoryNotFound));
+    let container_info = try!(info.ok_or(::safe_nfs::errors::NfsError::DirectoryNotFound(None)));
     container.get_container(container_info, None)
 }
 
@@ -0,0 +1,49 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+/// Root directory name
+pub const ROOT_DIRECTORY_NAME: &'static str = "USER_ROOT";
+/// Configuration directory Name stored in the session packet
+pub const CONFIGURATION_DIRECTORY_NAME: &'static str = "CONFIGURATION_ROOT";
+/// Name of the configuration directory holding the directories shared with the user
+pub const SHARED_DIRECTORIES_CONFIGURATION_NAME: &'static str = "SHARED_DIRECTORIES";
+/// Tag representing the Versioned Directory Listing.
+/// Directory listings are stored as StructuredData, whose tags below
+/// `safe_core::CLIENT_STRUCTURED_DATA_TAG` are reserved by the network. The tags used by safe_nfs are offset from that
+/// value so that they never collide with the reserved range.
+pub const VERSIONED_DIRECTORY_LISTING_TAG: u64 = ::safe_core::CLIENT_STRUCTURED_DATA_TAG + 100;
+/// Tag representing the Unversioned Directory Listing.
+/// Follows VERSIONED_DIRECTORY_LISTING_TAG and is likewise outside the range reserved by the network.
+pub const UNVERSIONED_DIRECTORY_LISTING_TAG: u64 = VERSIONED_DIRECTORY_LISTING_TAG + 1;
+
+/// Returns true if the tag is one under which safe_nfs stores directory listings as StructuredData
+pub fn is_directory_listing_tag(tag: u64) -> bool {
+    tag == VERSIONED_DIRECTORY_LISTING_TAG || tag == UNVERSIONED_DIRECTORY_LISTING_TAG
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn directory_listing_tags() {
+        assert!(is_directory_listing_tag(VERSIONED_DIRECTORY_LISTING_TAG));
+        assert!(is_directory_listing_tag(UNVERSIONED_DIRECTORY_LISTING_TAG));
+        assert!(!is_directory_listing_tag(::safe_core::CLIENT_STRUCTURED_DATA_TAG));
+        assert!(!is_directory_listing_tag(UNVERSIONED_DIRECTORY_LISTING_TAG + 1));
+    }
+}
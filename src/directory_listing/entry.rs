@@ -0,0 +1,115 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+/// Kind of an entry of a DirectoryListing
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum EntryKind {
+    /// The entry is a file
+    File,
+    /// The entry is a sub directory
+    Directory,
+}
+
+/// An entry of a DirectoryListing, which is either a file or a sub directory
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Entry<'a> {
+    /// A file of the directory
+    File(&'a ::file::File),
+    /// A sub directory of the directory
+    Directory(&'a ::metadata::directory_metadata::DirectoryMetadata),
+}
+
+impl<'a> Entry<'a> {
+    /// Returns the name of the entry
+    pub fn get_name(&self) -> &'a String {
+        match *self {
+            Entry::File(file)           => file.get_name(),
+            Entry::Directory(directory) => directory.get_name(),
+        }
+    }
+
+    /// Returns the kind of the entry
+    pub fn get_kind(&self) -> EntryKind {
+        match *self {
+            Entry::File(_)      => EntryKind::File,
+            Entry::Directory(_) => EntryKind::Directory,
+        }
+    }
+
+    /// Returns true if the entry is a file, else returns false
+    pub fn is_file(&self) -> bool {
+        self.get_kind() == EntryKind::File
+    }
+
+    /// Returns the metadata of the entry in the form shared by files and sub directories
+    pub fn get_entry_metadata(&self) -> EntryMetadata {
+        match *self {
+            Entry::File(file) => EntryMetadata {
+                name         : file.get_name().clone(),
+                kind         : EntryKind::File,
+                size         : Some(file.logical_size()),
+                created_time : file.get_metadata().get_created_time().clone(),
+                modified_time: file.get_metadata().get_modified_time().clone(),
+            },
+            Entry::Directory(directory) => EntryMetadata {
+                name         : directory.get_name().clone(),
+                kind         : EntryKind::Directory,
+                size         : None,
+                created_time : directory.get_created_time().clone(),
+                modified_time: directory.get_modified_time().clone(),
+            },
+        }
+    }
+}
+
+/// Metadata of an entry of a DirectoryListing, common to files and sub directories, e.g. to list the entries of a
+/// directory along with their details
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct EntryMetadata {
+    name         : String,
+    kind         : EntryKind,
+    size         : Option<u64>,
+    created_time : ::time::Tm,
+    modified_time: ::time::Tm,
+}
+
+impl EntryMetadata {
+    /// Get the name of the entry
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    /// Get the kind of the entry
+    pub fn get_kind(&self) -> EntryKind {
+        self.kind
+    }
+
+    /// Get the size of a file entry. The size of a sub directory is not known without fetching it, so it is None
+    pub fn get_size(&self) -> Option<u64> {
+        self.size
+    }
+
+    /// Get the time of creation of the entry
+    pub fn get_created_time(&self) -> &::time::Tm {
+        &self.created_time
+    }
+
+    /// Get the time of the last modification of the entry
+    pub fn get_modified_time(&self) -> &::time::Tm {
+        &self.modified_time
+    }
+}
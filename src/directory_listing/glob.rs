@@ -0,0 +1,117 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+/// Returns true if the name matches the shell-style pattern, in which `*` matches any sequence of characters, including
+/// an empty one, and `?` matches any single character. All other characters only match themselves.
+pub fn matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let mut pattern_index = 0;
+    let mut name_index = 0;
+    // Position of the last `*` seen in the pattern and of the name character it was matched up to, so that the `*` can
+    // be made to match one more character when the rest of the pattern fails to match
+    let mut backtrack: Option<(usize, usize)> = None;
+    while name_index < name.len() {
+        if pattern_index < pattern.len() && pattern[pattern_index] == '*' {
+            backtrack = Some((pattern_index, name_index));
+            pattern_index += 1;
+        } else if pattern_index < pattern.len() && (pattern[pattern_index] == '?' || pattern[pattern_index] == name[name_index]) {
+            pattern_index += 1;
+            name_index += 1;
+        } else if let Some((star_index, matched_index)) = backtrack {
+            backtrack = Some((star_index, matched_index + 1));
+            pattern_index = star_index + 1;
+            name_index = matched_index + 1;
+        } else {
+            return false;
+        }
+    }
+    pattern[pattern_index..].iter().all(|character| *character == '*')
+}
+
+/// Returns true if the path, given as its segments, matches the pattern, given as its `/` separated segments. A pattern
+/// segment of `**` matches any number of path segments, including none, and any other pattern segment matches a single
+/// path segment as by `matches`.
+pub fn matches_path(pattern: &[&str], path: &[&str]) -> bool {
+    if pattern.is_empty() {
+        path.is_empty()
+    } else if pattern[0] == "**" {
+        (0..path.len() + 1).any(|skipped| matches_path(&pattern[1..], &path[skipped..]))
+    } else {
+        !path.is_empty() && matches(pattern[0], path[0]) && matches_path(&pattern[1..], &path[1..])
+    }
+}
+
+/// Returns true if a path which extends the directory path, given as its segments, by at least one more segment can
+/// match the pattern, given as its `/` separated segments, i.e. if the directory path matches a prefix of the pattern
+/// which is followed by at least one more segment or by a `**`.
+pub fn matches_path_below(pattern: &[&str], directory_path: &[&str]) -> bool {
+    if pattern.is_empty() {
+        false
+    } else if pattern[0] == "**" {
+        true
+    } else if directory_path.is_empty() {
+        true
+    } else {
+        matches(pattern[0], directory_path[0]) && matches_path_below(&pattern[1..], &directory_path[1..])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn wildcards() {
+        assert!(matches("*.txt", "notes.txt"));
+        assert!(matches("*.txt", ".txt"));
+        assert!(!matches("*.txt", "notes.txt.bak"));
+        assert!(matches("a?c", "abc"));
+        assert!(!matches("a?c", "ac"));
+        assert!(matches("*a*b*", "xxaxxbxx"));
+        assert!(!matches("*a*b*", "xxbxxaxx"));
+        assert!(matches("*", ""));
+        assert!(matches("", ""));
+        assert!(!matches("", "a"));
+        assert!(matches("*a", "*ba"));
+        assert!(matches("ü?", "üß"));
+    }
+
+    #[test]
+    fn paths() {
+        assert!(matches_path(&["**", "*.png"], &["a.png"]));
+        assert!(matches_path(&["**", "*.png"], &["images", "2015", "a.png"]));
+        assert!(!matches_path(&["**", "*.png"], &["images", "a.jpg"]));
+        assert!(matches_path(&["images", "**", "*.png"], &["images", "a.png"]));
+        assert!(!matches_path(&["images", "**", "*.png"], &["docs", "a.png"]));
+        assert!(matches_path(&["*", "*.txt"], &["docs", "a.txt"]));
+        assert!(!matches_path(&["*", "*.txt"], &["a.txt"]));
+        assert!(matches_path(&["**"], &[]));
+    }
+
+    #[test]
+    fn paths_below() {
+        assert!(matches_path_below(&["images", "*.png"], &[]));
+        assert!(matches_path_below(&["images", "*.png"], &["images"]));
+        assert!(!matches_path_below(&["images", "*.png"], &["docs"]));
+        assert!(!matches_path_below(&["images", "*.png"], &["images", "deep"]));
+        assert!(matches_path_below(&["images", "**", "*.png"], &["images", "deep", "deeper"]));
+        assert!(!matches_path_below(&["images", "**", "*.png"], &["docs", "deep"]));
+        assert!(matches_path_below(&["im*", "*.png"], &["images"]));
+        assert!(!matches_path_below(&["*.png"], &["images"]));
+    }
+}
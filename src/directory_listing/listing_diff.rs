@@ -0,0 +1,100 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+/// Differences between two DirectoryListings, as computed by `DirectoryListing::diff`. Entries are matched by name and
+/// listed by name, in the order they appear in the listing they are taken from.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ListingDiff {
+    added_files             : Vec<String>,
+    removed_files           : Vec<String>,
+    modified_files          : Vec<String>,
+    added_sub_directories   : Vec<String>,
+    removed_sub_directories : Vec<String>,
+    modified_sub_directories: Vec<String>,
+}
+
+impl ListingDiff {
+    /// Computes the differences which turn the listing from into the listing to
+    pub fn new(from: &::directory_listing::DirectoryListing, to: &::directory_listing::DirectoryListing) -> ListingDiff {
+        let (added_files, removed_files, modified_files) = diff_entries(from.get_files(), to.get_files(), |file| file.get_name());
+        let (added_sub_directories, removed_sub_directories, modified_sub_directories) =
+            diff_entries(from.get_sub_directories(), to.get_sub_directories(), |directory| directory.get_name());
+        ListingDiff {
+            added_files             : added_files,
+            removed_files           : removed_files,
+            modified_files          : modified_files,
+            added_sub_directories   : added_sub_directories,
+            removed_sub_directories : removed_sub_directories,
+            modified_sub_directories: modified_sub_directories,
+        }
+    }
+
+    /// Get the names of the files only present in the newer listing
+    pub fn get_added_files(&self) -> &Vec<String> {
+        &self.added_files
+    }
+
+    /// Get the names of the files only present in the older listing
+    pub fn get_removed_files(&self) -> &Vec<String> {
+        &self.removed_files
+    }
+
+    /// Get the names of the files present in both listings whose content or metadata differ
+    pub fn get_modified_files(&self) -> &Vec<String> {
+        &self.modified_files
+    }
+
+    /// Get the names of the sub directories only present in the newer listing
+    pub fn get_added_sub_directories(&self) -> &Vec<String> {
+        &self.added_sub_directories
+    }
+
+    /// Get the names of the sub directories only present in the older listing
+    pub fn get_removed_sub_directories(&self) -> &Vec<String> {
+        &self.removed_sub_directories
+    }
+
+    /// Get the names of the sub directories present in both listings whose metadata differ
+    pub fn get_modified_sub_directories(&self) -> &Vec<String> {
+        &self.modified_sub_directories
+    }
+
+    /// Returns true if the listings hold the same entries
+    pub fn is_empty(&self) -> bool {
+        self.added_files.is_empty() && self.removed_files.is_empty() && self.modified_files.is_empty() &&
+        self.added_sub_directories.is_empty() && self.removed_sub_directories.is_empty() && self.modified_sub_directories.is_empty()
+    }
+}
+
+/// Returns the names of the (added, removed, modified) entries between the entries from and the entries to
+fn diff_entries<T, F>(from: &[T], to: &[T], get_name: F) -> (Vec<String>, Vec<String>, Vec<String>)
+        where T: PartialEq, F: Fn(&T) -> &String {
+    let added = to.iter()
+                  .filter(|entry| !from.iter().any(|from_entry| get_name(from_entry) == get_name(entry)))
+                  .map(|entry| get_name(entry).clone())
+                  .collect();
+    let mut removed = Vec::new();
+    let mut modified = Vec::new();
+    for entry in from {
+        match to.iter().find(|to_entry| get_name(to_entry) == get_name(entry)) {
+            Some(to_entry) if *to_entry != *entry => modified.push(get_name(entry).clone()),
+            Some(_) => (),
+            None => removed.push(get_name(entry).clone()),
+        }
+    }
+    (added, removed, modified)
+}
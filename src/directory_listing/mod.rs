@@ -15,12 +15,47 @@
 // Please review the Licences for the specific language governing permissions and limitations
 // relating to use of the SAFE Network Software.
 
+/// Entry is either a file or a sub directory of a DirectoryListing
+pub mod entry;
+/// Matching of names against shell-style wildcard patterns
+pub mod glob;
+/// ListingDiff lists the differences between two DirectoryListings
+pub mod listing_diff;
+
+/// Version of the layout in which a DirectoryListing, along with the metadata of its files and
+/// sub directories, is serialised. Must be bumped whenever a serialised field is added, so that
+/// decoding can fill in defaults for listings saved by older clients.
+/// Version 2 added the lock of a file to FileMetadata.
+/// Version 3 added the title of a directory to DirectoryMetadata.
+/// Version 4 added the hidden flag to FileMetadata and DirectoryMetadata.
+/// Version 5 added a checksum of the content of the DirectoryListing, which is verified when decoding.
+/// Version 6 added the cached size and child count of a directory to DirectoryMetadata.
+/// Version 7 holds the content of the DirectoryListing serialised separately, so that its checksum is verified over the
+/// bytes as they were stored rather than over the content encoded again.
+/// Version 8 added the nonce of a directory to DirectoryKey.
+pub const DIRECTORY_LISTING_SCHEMA_VERSION: u16 = 8;
+
+/// Characters which can not be part of the name of a file or directory, as `/` separates the names in a path
+pub const FORBIDDEN_NAME_CHARACTERS: [char; 2] = ['/', '\0'];
+/// Names which can not be given to a file or directory, as they refer to the current and parent directories in a path
+pub const RESERVED_NAMES: [&'static str; 2] = [".", ".."];
+/// Maximum size in bytes of a serialised unversioned DirectoryListing, which is saved in the data of its StructuredData
+/// and so is bounded by the size limit of a StructuredData. Versioned listings are saved as ImmutableData and have no limit
+pub const MAX_DIRECTORY_LISTING_SIZE: usize = ::routing::structured_data::MAX_STRUCTURED_DATA_SIZE_IN_BYTES;
+/// Upper bound on the serialised size in bytes of the fixed size fields of a metadata entry, i.e. the keys, timestamps,
+/// sizes and flags, along with the framing of the encoding
+const METADATA_SIZE_ESTIMATE: usize = 512;
+/// Upper bound on the serialised size in bytes of the details of one chunk in a DataMap
+const CHUNK_DETAILS_SIZE_ESTIMATE: usize = 192;
+
 /// DirectoryListing is the representation of a deserialised Directory in the network
-#[derive(Debug, RustcEncodable, RustcDecodable, PartialEq, Eq, PartialOrd, Ord, Clone)]
+/// The entries for files and sub directories are shared between clones of a DirectoryListing and are only copied when a
+/// clone modifies them, so cloning a large listing to change a single field stays cheap.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub struct DirectoryListing {
     metadata       : ::metadata::directory_metadata::DirectoryMetadata,
-    sub_directories: Vec<::metadata::directory_metadata::DirectoryMetadata>,
-    files          : Vec<::file::File>,
+    sub_directories: ::std::sync::Arc<Vec<::metadata::directory_metadata::DirectoryMetadata>>,
+    files          : ::std::sync::Arc<Vec<::file::File>>,
 }
 
 impl DirectoryListing {
@@ -39,11 +74,26 @@ impl DirectoryListing {
                                                                                     parent_dir_key));
         Ok(DirectoryListing {
             metadata       : meta_data,
-            sub_directories: Vec::new(),
-            files          : Vec::new(),
+            sub_directories: ::std::sync::Arc::new(Vec::new()),
+            files          : ::std::sync::Arc::new(Vec::new()),
         })
     }
 
+    /// Create a new DirectoryListing for a directory with a known DirectoryKey
+    pub fn new_with_key(directory_key : ::metadata::directory_key::DirectoryKey,
+                        name          : String,
+                        user_metadata : Vec<u8>,
+                        parent_dir_key: Option<::metadata::directory_key::DirectoryKey>) -> DirectoryListing {
+        DirectoryListing {
+            metadata       : ::metadata::directory_metadata::DirectoryMetadata::new_with_key(directory_key,
+                                                                                             name,
+                                                                                             user_metadata,
+                                                                                             parent_dir_key),
+            sub_directories: ::std::sync::Arc::new(Vec::new()),
+            files          : ::std::sync::Arc::new(Vec::new()),
+        }
+    }
+
     /// Returns the DirectoryKey representing the DirectoryListing
     pub fn get_key(&self) -> &::metadata::directory_key::DirectoryKey {
         &self.metadata.get_key()
@@ -66,7 +116,7 @@ impl DirectoryListing {
 
     /// Get all files in this DirectoryListing with mutability to update the listing of files
     pub fn get_mut_files(&mut self) -> &mut Vec<::file::File> {
-        &mut self.files
+        ::std::sync::Arc::make_mut(&mut self.files)
     }
 
     /// Get all subdirectories in this DirectoryListing
@@ -76,33 +126,217 @@ impl DirectoryListing {
 
     /// Get all subdirectories in this DirectoryListing with mutability to update the listing of subdirectories
     pub fn get_mut_sub_directories(&mut self) -> &mut Vec<::metadata::directory_metadata::DirectoryMetadata> {
-        &mut self.sub_directories
+        ::std::sync::Arc::make_mut(&mut self.sub_directories)
+    }
+
+    /// Get the file at the given index.
+    /// Files are kept in insertion order: a new file is appended at the end, an upsert of an
+    /// existing file keeps its position and a removal shifts the following files down by one.
+    /// The index therefore refers to the stored order and not to any sorted presentation.
+    pub fn get_file_by_index(&self, index: usize) -> Option<&::file::File> {
+        self.files.get(index)
+    }
+
+    /// Get the subdirectory at the given index.
+    /// Follows the same insertion ordering guarantee as `get_file_by_index`.
+    pub fn get_sub_directory_by_index(&self, index: usize) -> Option<&::metadata::directory_metadata::DirectoryMetadata> {
+        self.sub_directories.get(index)
     }
 
-    /// Decrypts a directory listing
-    pub fn decrypt(client      : ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
-                   directory_id: &::routing::NameType,
-                   data        : Vec<u8>) -> Result<DirectoryListing, ::errors::NfsError> {
-        let decrypted_data_map = try!(eval_result!(client.lock()).hybrid_decrypt(&data,
-                                                                                 Some(&DirectoryListing::generate_nonce(directory_id))));
-        let datamap: ::self_encryption::datamap::DataMap = try!(::safe_core::utility::deserialise(&decrypted_data_map));
+    /// Decrypts a directory listing of the directory with the given key, with the nonce of the key, see
+    /// `DirectoryKey::get_encryption_nonce`
+    pub fn decrypt(client       : ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
+                   directory_key: &::metadata::directory_key::DirectoryKey,
+                   data         : Vec<u8>) -> Result<DirectoryListing, ::errors::NfsError> {
+        let datamap = try!(DirectoryListing::decrypt_datamap(client.clone(), directory_key, &data));
         let mut se = ::self_encryption::SelfEncryptor::new(::safe_core::SelfEncryptionStorage::new(client.clone()), datamap);
         let length = se.len();
         debug!("Reading encrypted storage of length {:?} ...", length);
         let serialised_directory_listing = se.read(0, length);
-        Ok(try!(::safe_core::utility::deserialise(&serialised_directory_listing)))
+        DirectoryListing::deserialise(&serialised_directory_listing)
+    }
+
+    /// Decodes a directory listing from the bytes held by a version of the directory, e.g. as kept in a backup, without
+    /// fetching the directory from the network. The bytes are decoded as `DirectoryHelper::get_by_version` decodes a
+    /// version, according to the access level of the directory_key, and the decoded listing must belong to the
+    /// directory_key. A Shared listing needs the shared key and has to be decoded through `decrypt_with_shared_key`.
+    /// Returns NfsError::MetaDataMissingOrCorrupted if the bytes do not hold a listing of the directory
+    pub fn from_bytes(client       : ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
+                      directory_key: &::metadata::directory_key::DirectoryKey,
+                      bytes        : &[u8]) -> Result<DirectoryListing, ::errors::NfsError> {
+        let decoded = match *directory_key.get_access_level() {
+            ::AccessLevel::Private => DirectoryListing::decrypt(client, directory_key, bytes.to_vec()),
+            ::AccessLevel::Public  => DirectoryListing::deserialise(bytes),
+            ::AccessLevel::Shared  => return Err(::errors::NfsError::MissingSharedKey),
+        };
+        match decoded {
+            Ok(ref directory_listing) if directory_listing.get_key() == directory_key => Ok(directory_listing.clone()),
+            Ok(_) => {
+                debug!("Decoded directory listing belongs to another directory ...");
+                Err(::errors::NfsError::MetaDataMissingOrCorrupted)
+            },
+            Err(error) => {
+                debug!("Failed to decode directory listing from bytes: {:?} ...", error);
+                Err(::errors::NfsError::MetaDataMissingOrCorrupted)
+            },
+        }
+    }
+
+    /// Decrypts the data-map through which the content of an encrypted directory listing of the directory with the given
+    /// key is self-encrypted, with the nonce of the key
+    pub fn decrypt_datamap(client       : ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
+                           directory_key: &::metadata::directory_key::DirectoryKey,
+                           data         : &[u8]) -> Result<::self_encryption::datamap::DataMap, ::errors::NfsError> {
+        let nonce = directory_key.get_encryption_nonce();
+        let decrypted_data_map = try!(eval_result!(client.lock()).hybrid_decrypt(data, Some(&nonce)));
+        Ok(try!(::safe_core::utility::deserialise(&decrypted_data_map)))
+    }
+
+    /// Decrypts a directory listing which was encrypted with a shared key through `encrypt_with_shared_key`
+    pub fn decrypt_with_shared_key(client    : ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
+                                   shared_key: &::sodiumoxide::crypto::secretbox::Key,
+                                   data      : &[u8]) -> Result<DirectoryListing, ::errors::NfsError> {
+        let datamap = try!(DirectoryListing::decrypt_datamap_with_shared_key(shared_key, data));
+        let mut se = ::self_encryption::SelfEncryptor::new(::safe_core::SelfEncryptionStorage::new(client.clone()), datamap);
+        let length = se.len();
+        debug!("Reading storage encrypted with shared key of length {:?} ...", length);
+        let serialised_directory_listing = se.read(0, length);
+        DirectoryListing::deserialise(&serialised_directory_listing)
+    }
+
+    /// Decrypts the data-map through which the content of a directory listing encrypted with a shared key is self-encrypted
+    pub fn decrypt_datamap_with_shared_key(shared_key: &::sodiumoxide::crypto::secretbox::Key,
+                                           data      : &[u8]) -> Result<::self_encryption::datamap::DataMap, ::errors::NfsError> {
+        let decrypted_data_map = try!(DirectoryListing::open_with_shared_key(shared_key, data));
+        Ok(try!(::safe_core::utility::deserialise(&decrypted_data_map)))
+    }
+
+    /// Encrypts data with a shared key. A random nonce is generated for every call and prefixed to the cipher text,
+    /// so that successive versions of a directory never reuse a nonce with the same key
+    pub fn seal_with_shared_key(shared_key: &::sodiumoxide::crypto::secretbox::Key,
+                                data      : &[u8]) -> Vec<u8> {
+        let nonce = ::sodiumoxide::crypto::secretbox::gen_nonce();
+        let mut sealed_data = nonce.0.to_vec();
+        sealed_data.extend(::sodiumoxide::crypto::secretbox::seal(data, &nonce, shared_key));
+        sealed_data
     }
 
-    /// Encrypts the directory listing
+    /// Decrypts data which was encrypted through `seal_with_shared_key`
+    pub fn open_with_shared_key(shared_key: &::sodiumoxide::crypto::secretbox::Key,
+                                data      : &[u8]) -> Result<Vec<u8>, ::errors::NfsError> {
+        if data.len() < ::sodiumoxide::crypto::secretbox::NONCEBYTES {
+            return Err(::errors::NfsError::from("Data encrypted with shared key is too short"));
+        }
+        let (nonce, cipher_text) = data.split_at(::sodiumoxide::crypto::secretbox::NONCEBYTES);
+        let nonce = try!(::sodiumoxide::crypto::secretbox::Nonce::from_slice(nonce)
+                         .ok_or(::errors::NfsError::from("Invalid nonce for shared key")));
+        ::sodiumoxide::crypto::secretbox::open(cipher_text, &nonce, shared_key)
+            .map_err(|()| ::errors::NfsError::from("Failed to decrypt with shared key"))
+    }
+
+    /// Serialises the DirectoryListing in the layout of the current schema version, along with the checksum of its
+    /// serialised content.
+    /// Returns NfsError::SerialisationError if the DirectoryListing can not be serialised
+    pub fn serialise(&self) -> Result<Vec<u8>, ::errors::NfsError> {
+        let content = try!(::errors::serialise(&ListingContent(self)));
+        let checksum = ::sodiumoxide::crypto::hash::sha512::hash(&content).0.to_vec();
+        ::errors::serialise(&StoredDirectoryListing {
+            content : content,
+            checksum: checksum,
+        })
+    }
+
+    /// Deserialises a DirectoryListing.
+    /// Listings saved before the schema version or the checksum were introduced are also accepted.
+    /// Returns NfsError::MetaDataMissingOrCorrupted if the data can not be decoded or does not match its checksum
+    pub fn deserialise(data: &[u8]) -> Result<DirectoryListing, ::errors::NfsError> {
+        if let Ok(directory_listing) = ::safe_core::utility::deserialise::<DirectoryListing>(data) {
+            return Ok(directory_listing);
+        }
+        debug!("Falling back to decode directory listing with checksum over its encoded layout ...");
+        if let Ok(ChecksummedDirectoryListing(directory_listing)) = ::safe_core::utility::deserialise::<ChecksummedDirectoryListing>(data) {
+            return Ok(directory_listing);
+        }
+        debug!("Falling back to decode directory listing without checksum ...");
+        if let Ok(UncheckedDirectoryListing(directory_listing)) = ::safe_core::utility::deserialise::<UncheckedDirectoryListing>(data) {
+            return Ok(directory_listing);
+        }
+        debug!("Falling back to decode directory listing without schema version ...");
+        match ::safe_core::utility::deserialise::<UnversionedDirectoryListing>(data) {
+            Ok(UnversionedDirectoryListing(directory_listing)) => Ok(directory_listing),
+            Err(error) => {
+                debug!("Failed to decode directory listing: {:?} ...", error);
+                Err(::errors::NfsError::MetaDataMissingOrCorrupted)
+            },
+        }
+    }
+
+    /// Encrypts the directory listing with the nonce of its key, see `DirectoryKey::get_encryption_nonce`
     pub fn encrypt(&self,
                    client: ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>) -> Result<Vec<u8>, ::errors::NfsError> {
-        let serialised_data = try!(::safe_core::utility::serialise(&self));
+        let nonce = self.get_key().get_encryption_nonce();
+        let serialised_data = try!(self.serialise());
+        let mut se = ::self_encryption::SelfEncryptor::new(::safe_core::SelfEncryptionStorage::new(client.clone()), ::self_encryption::datamap::DataMap::None);
+        debug!("Writing to storage using self encryption ...");
+        se.write(&serialised_data, 0);
+        let datamap = se.close();
+        let serialised_data_map = try!(::errors::serialise(&datamap));
+        Ok(try!(eval_result!(client.lock()).hybrid_encrypt(&serialised_data_map, Some(&nonce))))
+    }
+
+    /// Encrypts the directory listing with a shared key. The listing is self-encrypted and the resulting data-map is
+    /// encrypted with the shared key
+    pub fn encrypt_with_shared_key(&self,
+                                   client    : ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
+                                   shared_key: &::sodiumoxide::crypto::secretbox::Key) -> Result<Vec<u8>, ::errors::NfsError> {
+        let serialised_data = try!(self.serialise());
         let mut se = ::self_encryption::SelfEncryptor::new(::safe_core::SelfEncryptionStorage::new(client.clone()), ::self_encryption::datamap::DataMap::None);
         debug!("Writing to storage using self encryption ...");
         se.write(&serialised_data, 0);
         let datamap = se.close();
-        let serialised_data_map = try!(::safe_core::utility::serialise(&datamap));
-        Ok(try!(eval_result!(client.lock()).hybrid_encrypt(&serialised_data_map, Some(&DirectoryListing::generate_nonce(&self.get_key().get_id())))))
+        let serialised_data_map = try!(::errors::serialise(&datamap));
+        Ok(DirectoryListing::seal_with_shared_key(shared_key, &serialised_data_map))
+    }
+
+    /// Returns the files and sub_directories of the DirectoryListing whose names match the shell-style pattern, in which
+    /// `*` matches any sequence of characters and `?` any single character. The files are returned before the
+    /// sub_directories. Only the immediate entries of the directory are matched, sub_directories are not searched.
+    pub fn glob(&self, pattern: &str) -> Vec<::directory_listing::entry::Entry> {
+        let files = self.files.iter().map(|file| ::directory_listing::entry::Entry::File(file));
+        let sub_directories = self.sub_directories.iter().map(|directory| ::directory_listing::entry::Entry::Directory(directory));
+        files.chain(sub_directories).filter(|entry| ::directory_listing::glob::matches(pattern, entry.get_name())).collect()
+    }
+
+    /// Returns the files and sub_directories of the DirectoryListing which are not hidden, files first, each in the order
+    /// in which they are stored
+    pub fn visible_entries(&self) -> Vec<::directory_listing::entry::Entry> {
+        let files = self.files.iter()
+                              .filter(|file| !file.get_metadata().is_hidden())
+                              .map(|file| ::directory_listing::entry::Entry::File(file));
+        let sub_directories = self.sub_directories.iter()
+                                                  .filter(|directory| !directory.is_hidden())
+                                                  .map(|directory| ::directory_listing::entry::Entry::Directory(directory));
+        files.chain(sub_directories).collect()
+    }
+
+    /// Estimates the size in bytes of the DirectoryListing once serialised, without serialising it. The estimate is
+    /// conservative, i.e. it is never less than the actual size, and grows with each entry added to the listing
+    pub fn estimated_serialized_size(&self) -> usize {
+        METADATA_SIZE_ESTIMATE +
+        estimate_directory_metadata_size(&self.metadata) +
+        self.sub_directories.iter().map(estimate_directory_metadata_size).fold(0, |total, size| total + size) +
+        self.files.iter().map(estimate_file_size).fold(0, |total, size| total + size)
+    }
+
+    /// Returns the number of bytes by which the serialised DirectoryListing can grow before reaching
+    /// MAX_DIRECTORY_LISTING_SIZE, so that a directory can be split before it gets too large to be saved. The listing is
+    /// serialised to be measured; the overhead of its encryption and of the StructuredData is not counted.
+    /// Returns None for a versioned DirectoryListing, whose size is not limited
+    pub fn remaining_capacity(&self) -> Result<Option<usize>, ::errors::NfsError> {
+        if self.get_key().is_versioned() {
+            return Ok(None);
+        }
+        Ok(Some(MAX_DIRECTORY_LISTING_SIZE.saturating_sub(try!(self.serialise()).len())))
     }
 
     /// Get DirectoryInfo of sub_directory within a DirectoryListing.
@@ -112,6 +346,12 @@ impl DirectoryListing {
         self.get_files().iter().find(|file| *file.get_name() == *file_name)
     }
 
+    /// Get the index of a file within the DirectoryListing along with the file, in a single pass.
+    /// Returns None if no file with the name exists in the DirectoryListing
+    pub fn locate_file(&self, file_name: &str) -> Option<(usize, &::file::File)> {
+        self.files.iter().enumerate().find(|&(_, file)| *file.get_name() == file_name)
+    }
+
     /// Get DirectoryInfo of sub_directory within a DirectoryListing.
     /// Returns the Option<DirectoryInfo> for the directory_name from the DirectoryListing
     pub fn find_file_by_id(&self,
@@ -119,6 +359,52 @@ impl DirectoryListing {
         self.get_files().iter().find(|file| *file.get_id() == *id)
     }
 
+    /// Returns the files and sub directories added, removed or modified in other compared to the DirectoryListing, matching
+    /// the entries by name. Only the listings passed are compared, without any access to the network.
+    pub fn diff(&self, other: &DirectoryListing) -> ::directory_listing::listing_diff::ListingDiff {
+        ::directory_listing::listing_diff::ListingDiff::new(self, other)
+    }
+
+    /// Returns the name and the size of each file of the DirectoryListing, the size being the logical size of the file
+    /// as seen by readers. No file content is read.
+    pub fn file_sizes(&self) -> Vec<(String, u64)> {
+        self.files.iter().map(|file| (file.get_name().clone(), file.logical_size())).collect()
+    }
+
+    /// Returns the metadata of the DirectoryListing with its size and child count computed from the entries of the
+    /// DirectoryListing, to be cached in the entry of the directory in its parent. Both only cover the entries held
+    /// directly by the DirectoryListing: the size is that of its own files, not counting the content of its sub
+    /// directories, as the entries further up the tree are not refreshed when the DirectoryListing changes.
+    pub fn aggregated_metadata(&self) -> ::metadata::directory_metadata::DirectoryMetadata {
+        let mut metadata = self.metadata.clone();
+        metadata.set_size(self.files.iter().fold(0, |size, file| size + file.logical_size()));
+        metadata.set_child_count((self.files.len() + self.sub_directories.len()) as u64);
+        metadata
+    }
+
+    /// Returns base_name if the DirectoryListing has no file or sub directory with it, else base_name followed by the
+    /// first of ` (1)`, ` (2)`, etc. which makes the name free
+    pub fn unique_name(&self, base_name: &str) -> String {
+        let is_taken = |name: &String| self.find_file(name).is_some() || self.find_sub_directory(name).is_some();
+        let mut name = base_name.to_string();
+        let mut index = 0;
+        while is_taken(&name) {
+            index += 1;
+            name = format!("{} ({})", base_name, index);
+        }
+        name
+    }
+
+    /// Get the metadata of the file or sub directory with the name passed, from the DirectoryListing alone.
+    /// Returns None if the DirectoryListing has no entry with the name
+    pub fn entry_metadata(&self, name: &str) -> Option<::directory_listing::entry::EntryMetadata> {
+        let name = name.to_string();
+        self.find_file(&name)
+            .map(|file| ::directory_listing::entry::Entry::File(file))
+            .or_else(|| self.find_sub_directory(&name).map(|directory| ::directory_listing::entry::Entry::Directory(directory)))
+            .map(|entry| entry.get_entry_metadata())
+    }
+
     /// Get DirectoryInfo of sub_directory within a DirectoryListing.
     /// Returns the Option<DirectoryInfo> for the directory_name from the DirectoryListing
     pub fn find_sub_directory(&self,
@@ -141,11 +427,11 @@ impl DirectoryListing {
         // *existing_file = file;
         if let Some(index) = self.files.iter().position(|entry| *entry.get_id() == *file.get_id()) {
             debug!("Replacing file in directory listing ...");
-            let mut existing = eval_option!(self.files.get_mut(index), "Programming Error - Report this as a Bug.");
+            let mut existing = eval_option!(self.get_mut_files().get_mut(index), "Programming Error - Report this as a Bug.");
             *existing = file;
         } else {
             debug!("Adding file to directory listing ...");
-            self.files.push(file);
+            self.get_mut_files().push(file);
         }
         self.get_mut_metadata().set_modified_time(modified_time)
     }
@@ -155,31 +441,217 @@ impl DirectoryListing {
         let modified_time = directory_metadata.get_modified_time().clone();
         if let Some(index) = self.sub_directories.iter().position(|entry| *entry.get_key().get_id() == *directory_metadata.get_key().get_id()) {
             debug!("Replacing directory listing metadata ...");
-            let mut existing = eval_option!(self.sub_directories.get_mut(index), "Programming Error - Report this as a Bug.");
+            let mut existing = eval_option!(self.get_mut_sub_directories().get_mut(index), "Programming Error - Report this as a Bug.");
             *existing = directory_metadata;
         } else {
             debug!("Adding metadata to directory listing ...");
-            self.sub_directories.push(directory_metadata);
+            self.get_mut_sub_directories().push(directory_metadata);
         }
         self.get_mut_metadata().set_modified_time(modified_time);
     }
 
+    /// Replaces or inserts the DirectoryMetadata in the same way as `upsert_sub_directory`, except that a sub directory
+    /// with the same name but a different key is not replaced silently, as that would orphan the existing sub directory.
+    /// NfsError::SubDirectoryKeyConflict is returned instead, see `check_sub_directory_conflict`, unless
+    /// replace_conflicting is set in which case the conflicting entry is dropped.
+    pub fn upsert_sub_directory_checked(&mut self,
+                                        directory_metadata : ::metadata::directory_metadata::DirectoryMetadata,
+                                        replace_conflicting: bool) -> Result<(), ::errors::NfsError> {
+        if let Err(error) = self.check_sub_directory_conflict(&directory_metadata) {
+            if !replace_conflicting {
+                return Err(error);
+            }
+            debug!("Removing conflicting sub directory ...");
+            let _ = self.take_sub_directory(directory_metadata.get_name());
+        }
+        self.upsert_sub_directory(directory_metadata);
+        Ok(())
+    }
+
+    /// Checks that the DirectoryListing has no sub directory with the name of the DirectoryMetadata under a different
+    /// key, e.g. before the directory is saved in the network so that a conflict does not leave it orphaned.
+    /// Returns NfsError::SubDirectoryKeyConflict if it has one
+    pub fn check_sub_directory_conflict(&self, directory_metadata: &::metadata::directory_metadata::DirectoryMetadata) -> Result<(), ::errors::NfsError> {
+        if self.sub_directories.iter().any(|entry| *entry.get_name() == *directory_metadata.get_name() &&
+                                                   *entry.get_key().get_id() != *directory_metadata.get_key().get_id()) {
+            return Err(::errors::NfsError::SubDirectoryKeyConflict);
+        }
+        Ok(())
+    }
+
     /// Remove a sub_directory
     pub fn remove_sub_directory(&mut self, directory_name: &String) -> Result<(), ::errors::NfsError> {
-        let index = try!(self.get_sub_directories().iter().position(|dir_info| *dir_info.get_name() == *directory_name).ok_or(::errors::NfsError::DirectoryNotFound));
-        debug!("Removing sub directory at index {:?} ...", index);
-        let _ = self.get_mut_sub_directories().remove(index);
+        let _ = try!(self.take_sub_directory(directory_name).ok_or(::errors::NfsError::DirectoryNotFound(None)));
         Ok(())
     }
 
     /// Remove a file
     pub fn remove_file(&mut self, file_name: &String) -> Result<(), ::errors::NfsError> {
-        let index = try!(self.get_files().iter().position(|file| *file.get_name() == *file_name).ok_or(::errors::NfsError::FileNotFound));
-        debug!("Removing file at index {:?} ...", index);
-        let _ = self.get_mut_files().remove(index);
+        let _ = try!(self.take_file(file_name).ok_or(::errors::NfsError::FileNotFound));
+        Ok(())
+    }
+
+    /// Remove a sub_directory and return its metadata.
+    /// Returns None if no sub_directory with the name exists in the DirectoryListing
+    pub fn take_sub_directory(&mut self, directory_name: &str) -> Option<::metadata::directory_metadata::DirectoryMetadata> {
+        let position = self.sub_directories.iter().position(|dir_info| *dir_info.get_name() == directory_name);
+        position.map(|index| {
+            debug!("Removing sub directory at index {:?} ...", index);
+            self.get_mut_sub_directories().remove(index)
+        })
+    }
+
+    /// Remove a file and return it.
+    /// Returns None if no file with the name exists in the DirectoryListing
+    pub fn take_file(&mut self, file_name: &str) -> Option<::file::File> {
+        let position = self.files.iter().position(|file| *file.get_name() == file_name);
+        position.map(|index| {
+            debug!("Removing file at index {:?} ...", index);
+            self.get_mut_files().remove(index)
+        })
+    }
+
+    /// Removes all the files of the DirectoryListing
+    pub fn clear_files(&mut self) {
+        debug!("Removing all files from directory listing ...");
+        self.files = ::std::sync::Arc::new(Vec::new());
+    }
+
+    /// Removes all the sub_directories of the DirectoryListing
+    pub fn clear_sub_directories(&mut self) {
+        debug!("Removing all sub directories from directory listing ...");
+        self.sub_directories = ::std::sync::Arc::new(Vec::new());
+    }
+
+    /// Removes all the files and sub_directories of the DirectoryListing
+    pub fn clear(&mut self) {
+        self.clear_files();
+        self.clear_sub_directories();
+    }
+
+    /// Renames a file in place, keeping its id and DataMap, so the content and history of the file are left untouched.
+    /// Returns NfsError::NameIsEmpty or NfsError::InvalidName if new_name is not a valid name,
+    /// NfsError::FileAlreadyExistsWithSameName or NfsError::NameConflictsWithExistingEntry if new_name is taken by
+    /// another file or by a sub_directory and NfsError::FileNotFound if no file is named file_name.
+    /// Names are compared case-sensitively, so a rename which only changes the case of the name is not a conflict
+    pub fn rename_file(&mut self, file_name: &str, new_name: String) -> Result<(), ::errors::NfsError> {
+        try!(DirectoryListing::validate_name(&new_name));
+        if self.find_sub_directory(&new_name).is_some() {
+            return Err(::errors::NfsError::NameConflictsWithExistingEntry);
+        }
+        let index = try!(self.locate_file(file_name).map(|(index, _)| index).ok_or(::errors::NfsError::FileNotFound));
+        if file_name != new_name && self.find_file(&new_name).is_some() {
+            return Err(::errors::NfsError::FileAlreadyExistsWithSameName);
+        }
+        debug!("Renaming file at index {:?} ...", index);
+        self.get_mut_files()[index].get_mut_metadata().set_name(new_name);
         Ok(())
     }
 
+    /// Exchanges the names of two files in place, keeping the id and DataMap of each file.
+    /// Returns NfsError::FileNotFound if either file is not in the DirectoryListing
+    pub fn swap_file_names(&mut self, first_name: &str, second_name: &str) -> Result<(), ::errors::NfsError> {
+        let first_index = try!(self.locate_file(first_name).map(|(index, _)| index).ok_or(::errors::NfsError::FileNotFound));
+        let second_index = try!(self.locate_file(second_name).map(|(index, _)| index).ok_or(::errors::NfsError::FileNotFound));
+        if first_index != second_index {
+            debug!("Swapping names of files at index {:?} and {:?} ...", first_index, second_index);
+            let files = self.get_mut_files();
+            files[first_index].get_mut_metadata().set_name(second_name.to_string());
+            files[second_index].get_mut_metadata().set_name(first_name.to_string());
+        }
+        Ok(())
+    }
+
+    /// Checks the invariants of the entries of the DirectoryListing, so that a listing which would later break path
+    /// resolution is not saved.
+    /// Names are only checked against `validate_name` where they are given, as listings saved before the names were
+    /// restricted may hold entries named otherwise, and those listings must still be saved for the entries to be renamed.
+    /// Returns NfsError::NameIsEmpty if a file or sub_directory has an empty name, NfsError::FileAlreadyExistsWithSameName
+    /// or NfsError::DirectoryAlreadyExistsWithSameName if two files or two sub_directories share a name and
+    /// NfsError::NameConflictsWithExistingEntry if a sub_directory has the same name as a file
+    pub fn validate(&self) -> Result<(), ::errors::NfsError> {
+        let file_names: Vec<&String> = self.files.iter().map(|file| file.get_name()).collect();
+        let directory_names: Vec<&String> = self.sub_directories.iter().map(|directory| directory.get_name()).collect();
+        if try!(has_duplicate_name(&file_names)) {
+            return Err(::errors::NfsError::FileAlreadyExistsWithSameName);
+        }
+        if try!(has_duplicate_name(&directory_names)) {
+            return Err(::errors::NfsError::DirectoryAlreadyExistsWithSameName);
+        }
+        if directory_names.iter().any(|name| file_names.contains(name)) {
+            return Err(::errors::NfsError::NameConflictsWithExistingEntry);
+        }
+        Ok(())
+    }
+
+    /// Checks that the name of each file and sub_directory is valid, as checked by `validate_name`, e.g. before a
+    /// listing assembled offline is saved.
+    /// Returns NfsError::NameIsEmpty or NfsError::InvalidName for the first entry which does not have a valid name
+    pub fn validate_names(&self) -> Result<(), ::errors::NfsError> {
+        for name in self.files.iter().map(|file| file.get_name()).chain(self.sub_directories.iter().map(|directory| directory.get_name())) {
+            try!(DirectoryListing::validate_name(name));
+        }
+        Ok(())
+    }
+
+    /// Checks that a name can be given to a file or directory and addressed by path.
+    /// Returns NfsError::NameIsEmpty if the name is empty and NfsError::InvalidName if it holds any of
+    /// FORBIDDEN_NAME_CHARACTERS or is one of RESERVED_NAMES
+    pub fn validate_name(name: &str) -> Result<(), ::errors::NfsError> {
+        if name.is_empty() {
+            return Err(::errors::NfsError::NameIsEmpty);
+        }
+        if name.contains(&FORBIDDEN_NAME_CHARACTERS[..]) || RESERVED_NAMES.iter().any(|reserved_name| *reserved_name == name) {
+            return Err(::errors::NfsError::InvalidName);
+        }
+        Ok(())
+    }
+
+    /// Computes a digest of the content of the DirectoryListing: the names, sizes and content hashes of the files and the
+    /// names and keys of the sub_directories. Timestamps and the order of the entries are left out, so two listings with
+    /// the same meaningful content have the same fingerprint, however and whenever they were saved.
+    pub fn fingerprint(&self) -> Vec<u8> {
+        let mut files: Vec<&::file::File> = self.files.iter().collect();
+        files.sort_by(|lhs, rhs| lhs.get_name().cmp(rhs.get_name()));
+        let mut sub_directories: Vec<&::metadata::directory_metadata::DirectoryMetadata> = self.sub_directories.iter().collect();
+        sub_directories.sort_by(|lhs, rhs| lhs.get_name().cmp(rhs.get_name()));
+
+        let mut data = Vec::new();
+        push_field(&mut data, &files.len().to_string().into_bytes());
+        for file in files {
+            push_field(&mut data, file.get_name().as_bytes());
+            push_field(&mut data, &file.logical_size().to_string().into_bytes());
+            match *file.get_datamap() {
+                ::self_encryption::datamap::DataMap::Chunks(ref chunks) => {
+                    let mut chunks: Vec<&::self_encryption::datamap::ChunkDetails> = chunks.iter().collect();
+                    chunks.sort_by(|lhs, rhs| lhs.chunk_num.cmp(&rhs.chunk_num));
+                    push_field(&mut data, b"chunks");
+                    for chunk in chunks {
+                        push_field(&mut data, &chunk.hash);
+                    }
+                },
+                ::self_encryption::datamap::DataMap::Content(ref content) => {
+                    push_field(&mut data, b"content");
+                    push_field(&mut data, &::sodiumoxide::crypto::hash::sha512::hash(content).0);
+                },
+                ::self_encryption::datamap::DataMap::None => push_field(&mut data, b"none"),
+            }
+        }
+        for sub_directory in sub_directories {
+            let key = sub_directory.get_key();
+            push_field(&mut data, sub_directory.get_name().as_bytes());
+            push_field(&mut data, &key.get_id().0);
+            push_field(&mut data, &key.get_type_tag().to_string().into_bytes());
+            push_field(&mut data, if key.is_versioned() { &b"versioned"[..] } else { &b"unversioned"[..] });
+            push_field(&mut data, match *key.get_access_level() {
+                ::AccessLevel::Private => &b"private"[..],
+                ::AccessLevel::Public  => &b"public"[..],
+                ::AccessLevel::Shared  => &b"shared"[..],
+            });
+        }
+        ::sodiumoxide::crypto::hash::sha512::hash(&data).0.to_vec()
+    }
+
     /// Generates a nonce based on the directory_id
     pub fn generate_nonce(directory_id: &::routing::NameType) -> ::sodiumoxide::crypto::box_::Nonce {
         let mut nonce = [0u8; ::sodiumoxide::crypto::box_::NONCEBYTES];
@@ -191,6 +663,240 @@ impl DirectoryListing {
     }
 }
 
+/// DirectoryListing as stored from schema version 7: its content, serialised as ListingContent, along with the checksum
+/// of those bytes. Built by `DirectoryListing::serialise`, so that a failure to serialise the content is reported
+/// rather than stored.
+struct StoredDirectoryListing {
+    content : Vec<u8>,
+    checksum: Vec<u8>,
+}
+
+impl ::rustc_serialize::Encodable for StoredDirectoryListing {
+    fn encode<E: ::rustc_serialize::Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+        e.emit_struct("DirectoryListing", 3, |e| {
+            try!(e.emit_struct_field("schema_version", 0, |e| DIRECTORY_LISTING_SCHEMA_VERSION.encode(e)));
+            try!(e.emit_struct_field("content",        1, |e| self.content.encode(e)));
+            try!(e.emit_struct_field("checksum",       2, |e| self.checksum.encode(e)));
+
+            Ok(())
+        })
+    }
+}
+
+impl ::rustc_serialize::Decodable for DirectoryListing {
+    fn decode<D: ::rustc_serialize::Decoder>(d: &mut D) -> Result<DirectoryListing, D::Error> {
+        d.read_struct("DirectoryListing", 3, |d| {
+            let schema_version: u16 = try!(d.read_struct_field("schema_version", 0, |d| ::rustc_serialize::Decodable::decode(d)));
+            if schema_version > DIRECTORY_LISTING_SCHEMA_VERSION || schema_version < 7 {
+                return Err(d.error(&format!("Unsupported DirectoryListing schema version {}", schema_version)));
+            }
+            let content: Vec<u8> = try!(d.read_struct_field("content", 1, |d| ::rustc_serialize::Decodable::decode(d)));
+            let checksum: Vec<u8> = try!(d.read_struct_field("checksum", 2, |d| ::rustc_serialize::Decodable::decode(d)));
+            if checksum != ::sodiumoxide::crypto::hash::sha512::hash(&content).0.to_vec() {
+                return Err(d.error("DirectoryListing does not match its checksum"));
+            }
+            match ::safe_core::utility::deserialise::<DecodedListingContent>(&content) {
+                Ok(DecodedListingContent(directory_listing)) => Ok(directory_listing),
+                Err(error) => Err(d.error(&format!("Failed to decode DirectoryListing content: {:?}", error))),
+            }
+        })
+    }
+}
+
+/// Metadata, sub directories and files of a DirectoryListing, serialised along with the schema version of their layout.
+/// From schema version 7 a DirectoryListing holds its content serialised this way, so that the checksum is verified
+/// over the bytes as they were stored.
+struct ListingContent<'a>(&'a DirectoryListing);
+
+impl<'a> ::rustc_serialize::Encodable for ListingContent<'a> {
+    fn encode<E: ::rustc_serialize::Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+        e.emit_struct("DirectoryListingContent", 4, |e| {
+            try!(e.emit_struct_field("schema_version",  0, |e| DIRECTORY_LISTING_SCHEMA_VERSION.encode(e)));
+            try!(e.emit_struct_field("metadata",        1, |e| self.0.metadata.encode(e)));
+            try!(e.emit_struct_field("sub_directories", 2, |e| self.0.get_sub_directories().encode(e)));
+            try!(e.emit_struct_field("files",           3, |e| self.0.get_files().encode(e)));
+
+            Ok(())
+        })
+    }
+}
+
+/// Content of a DirectoryListing decoded from the layout written through ListingContent
+struct DecodedListingContent(DirectoryListing);
+
+impl ::rustc_serialize::Decodable for DecodedListingContent {
+    fn decode<D: ::rustc_serialize::Decoder>(d: &mut D) -> Result<DecodedListingContent, D::Error> {
+        d.read_struct("DirectoryListingContent", 4, |d| {
+            let schema_version: u16 = try!(d.read_struct_field("schema_version", 0, |d| ::rustc_serialize::Decodable::decode(d)));
+            if schema_version > DIRECTORY_LISTING_SCHEMA_VERSION {
+                return Err(d.error(&format!("Unsupported DirectoryListing schema version {}", schema_version)));
+            }
+            Ok(DecodedListingContent(try!(decode_directory_listing_fields(d, 1, schema_version))))
+        })
+    }
+}
+
+/// DirectoryListing as serialised in schema versions 5 and 6, with a checksum computed over the layout of the schema
+/// version rather than over the bytes stored
+struct ChecksummedDirectoryListing(DirectoryListing);
+
+impl ::rustc_serialize::Decodable for ChecksummedDirectoryListing {
+    fn decode<D: ::rustc_serialize::Decoder>(d: &mut D) -> Result<ChecksummedDirectoryListing, D::Error> {
+        d.read_struct("DirectoryListing", 5, |d| {
+            let schema_version: u16 = try!(d.read_struct_field("schema_version", 0, |d| ::rustc_serialize::Decodable::decode(d)));
+            if schema_version < 5 || schema_version > 6 {
+                return Err(d.error(&format!("DirectoryListing of schema version {} has no checksummed fields", schema_version)));
+            }
+            let directory_listing = try!(decode_directory_listing_fields(d, 1, schema_version));
+            let checksum: Vec<u8> = try!(d.read_struct_field("checksum", 4, |d| ::rustc_serialize::Decodable::decode(d)));
+            let expected_checksum = try!(compute_checksum(&directory_listing, schema_version).map_err(|error| {
+                d.error(&format!("Failed to compute checksum of DirectoryListing: {:?}", error))
+            }));
+            if checksum != expected_checksum {
+                return Err(d.error("DirectoryListing does not match its checksum"));
+            }
+            Ok(ChecksummedDirectoryListing(directory_listing))
+        })
+    }
+}
+
+/// DirectoryListing as serialised before the checksum was introduced in schema version 5
+struct UncheckedDirectoryListing(DirectoryListing);
+
+impl ::rustc_serialize::Decodable for UncheckedDirectoryListing {
+    fn decode<D: ::rustc_serialize::Decoder>(d: &mut D) -> Result<UncheckedDirectoryListing, D::Error> {
+        d.read_struct("DirectoryListing", 4, |d| {
+            let schema_version: u16 = try!(d.read_struct_field("schema_version", 0, |d| ::rustc_serialize::Decodable::decode(d)));
+            if schema_version >= 5 {
+                return Err(d.error(&format!("DirectoryListing of schema version {} must have a checksum", schema_version)));
+            }
+            Ok(UncheckedDirectoryListing(try!(decode_directory_listing_fields(d, 1, schema_version))))
+        })
+    }
+}
+
+/// DirectoryListing as serialised before the schema version was introduced
+struct UnversionedDirectoryListing(DirectoryListing);
+
+impl ::rustc_serialize::Decodable for UnversionedDirectoryListing {
+    fn decode<D: ::rustc_serialize::Decoder>(d: &mut D) -> Result<UnversionedDirectoryListing, D::Error> {
+        d.read_struct("DirectoryListing", 3, |d| {
+            Ok(UnversionedDirectoryListing(try!(decode_directory_listing_fields(d, 0, 0))))
+        })
+    }
+}
+
+fn decode_directory_listing_fields<D: ::rustc_serialize::Decoder>(d             : &mut D,
+                                                                  first_field   : usize,
+                                                                  schema_version: u16) -> Result<DirectoryListing, D::Error> {
+    Ok(DirectoryListing {
+        metadata       : try!(d.read_struct_field("metadata",        first_field,     |d| {
+                             ::metadata::directory_metadata::DirectoryMetadata::decode_with_schema(d, schema_version)
+                         })),
+        sub_directories: ::std::sync::Arc::new(try!(d.read_struct_field("sub_directories", first_field + 1, |d| decode_sub_directories(d, schema_version)))),
+        files          : ::std::sync::Arc::new(try!(d.read_struct_field("files",           first_field + 2, |d| decode_files(d, schema_version)))),
+    })
+}
+
+fn decode_sub_directories<D: ::rustc_serialize::Decoder>(d             : &mut D,
+                                                         schema_version: u16) -> Result<Vec<::metadata::directory_metadata::DirectoryMetadata>, D::Error> {
+    d.read_seq(|d, length| {
+        let mut sub_directories = Vec::with_capacity(length);
+        for index in 0..length {
+            sub_directories.push(try!(d.read_seq_elt(index, |d| ::metadata::directory_metadata::DirectoryMetadata::decode_with_schema(d, schema_version))));
+        }
+        Ok(sub_directories)
+    })
+}
+
+fn decode_files<D: ::rustc_serialize::Decoder>(d: &mut D, schema_version: u16) -> Result<Vec<::file::File>, D::Error> {
+    d.read_seq(|d, length| {
+        let mut files = Vec::with_capacity(length);
+        for index in 0..length {
+            files.push(try!(d.read_seq_elt(index, |d| ::file::File::decode_with_schema(d, schema_version))));
+        }
+        Ok(files)
+    })
+}
+
+/// Computes the checksum of a DirectoryListing saved in schema version 5 or 6, which is the SHA-512 hash of its metadata,
+/// sub directories and files serialised in the layout of the given schema version
+fn compute_checksum(directory_listing: &DirectoryListing, schema_version: u16) -> Result<Vec<u8>, ::errors::NfsError> {
+    let content = ChecksumContent {
+        directory_listing: directory_listing,
+        schema_version   : schema_version,
+    };
+    Ok(::sodiumoxide::crypto::hash::sha512::hash(&try!(::errors::serialise(&content))).0.to_vec())
+}
+
+/// Content of a DirectoryListing over which its checksum is computed, serialised as the tuple of its metadata, sub
+/// directories and files in the layout of the schema version
+struct ChecksumContent<'a> {
+    directory_listing: &'a DirectoryListing,
+    schema_version   : u16,
+}
+
+impl<'a> ::rustc_serialize::Encodable for ChecksumContent<'a> {
+    fn encode<E: ::rustc_serialize::Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+        let schema_version = self.schema_version;
+        let sub_directories = self.directory_listing.get_sub_directories();
+        e.emit_tuple(3, |e| {
+            try!(e.emit_tuple_arg(0, |e| self.directory_listing.get_metadata().encode_with_schema(e, schema_version)));
+            try!(e.emit_tuple_arg(1, |e| e.emit_seq(sub_directories.len(), |e| {
+                for (index, sub_directory) in sub_directories.iter().enumerate() {
+                    try!(e.emit_seq_elt(index, |e| sub_directory.encode_with_schema(e, schema_version)));
+                }
+                Ok(())
+            })));
+            e.emit_tuple_arg(2, |e| self.directory_listing.get_files().encode(e))
+        })
+    }
+}
+
+/// Estimates the serialised size in bytes of the metadata of a directory, erring on the larger side
+fn estimate_directory_metadata_size(metadata: &::metadata::directory_metadata::DirectoryMetadata) -> usize {
+    METADATA_SIZE_ESTIMATE +
+    metadata.get_name().len() +
+    metadata.get_title().map_or(0, |title| title.len()) +
+    metadata.get_user_metadata().len()
+}
+
+/// Checks that none of the names is empty.
+/// Returns true if any of the names is repeated
+fn has_duplicate_name(names: &[&String]) -> Result<bool, ::errors::NfsError> {
+    for (index, name) in names.iter().enumerate() {
+        if name.is_empty() {
+            return Err(::errors::NfsError::NameIsEmpty);
+        }
+        if names[index + 1..].contains(name) {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Estimates the serialised size in bytes of a file entry, erring on the larger side
+fn estimate_file_size(file: &::file::File) -> usize {
+    let metadata = file.get_metadata();
+    let datamap_size = match *file.get_datamap() {
+        ::self_encryption::datamap::DataMap::Chunks(ref chunks) => chunks.len() * CHUNK_DETAILS_SIZE_ESTIMATE,
+        ::self_encryption::datamap::DataMap::Content(ref content) => content.len(),
+        ::self_encryption::datamap::DataMap::None => 0,
+    };
+    METADATA_SIZE_ESTIMATE +
+    metadata.get_name().len() +
+    metadata.get_user_metadata().len() +
+    metadata.get_lock().map_or(0, |lock| lock.get_owner().len()) +
+    datamap_size
+}
+
+/// Appends a length prefixed field to the data being fingerprinted, so that the boundaries between fields are unambiguous
+fn push_field(data: &mut Vec<u8>, field: &[u8]) {
+    data.extend(field.len().to_string().into_bytes());
+    data.push(b':');
+    data.extend(field.iter().cloned());
+}
+
 #[cfg(test)]
 mod test {
     use super::DirectoryListing;
@@ -204,11 +910,235 @@ mod test {
                                                             ::AccessLevel::Private,
                                                             None));
 
-        let serialised_data = eval_result!(::safe_core::utility::serialise(&obj_before));
-        let obj_after = eval_result!(::safe_core::utility::deserialise(&serialised_data));
+        let serialised_data = eval_result!(obj_before.serialise());
+        let obj_after = eval_result!(DirectoryListing::deserialise(&serialised_data));
         assert_eq!(obj_before, obj_after);
     }
 
+    #[test]
+    fn deserialise_directory_listing_without_schema_version() {
+        // Layout of DirectoryKey before the nonce was added in schema version 8
+        #[derive(RustcEncodable)]
+        struct LegacyDirectoryKey {
+            id          : ::routing::NameType,
+            type_tag    : u64,
+            versioned   : bool,
+            access_level: ::AccessLevel,
+        }
+
+        // Layout of DirectoryMetadata before the title was added in schema version 3
+        #[derive(RustcEncodable)]
+        struct LegacyDirectoryMetadata {
+            key               : LegacyDirectoryKey,
+            name              : String,
+            created_time_sec  : i64,
+            created_time_nsec : i32,
+            modified_time_sec : i64,
+            modified_time_nsec: i32,
+            user_metadata     : Vec<u8>,
+            parent_dir_key    : Option<LegacyDirectoryKey>,
+        }
+
+        // Layout of File before the lock was added in schema version 2
+        #[derive(RustcEncodable)]
+        struct LegacyFileMetadata {
+            name              : String,
+            size              : u64,
+            created_time_sec  : i64,
+            created_time_nsec : i32,
+            modified_time_sec : i64,
+            modified_time_nsec: i32,
+            user_metadata     : Vec<u8>,
+        }
+
+        #[derive(RustcEncodable)]
+        struct LegacyFile {
+            id      : ::routing::NameType,
+            metadata: LegacyFileMetadata,
+            datamap : ::self_encryption::datamap::DataMap,
+        }
+
+        #[derive(RustcEncodable)]
+        struct UnversionedDirectoryListing {
+            metadata       : LegacyDirectoryMetadata,
+            sub_directories: Vec<LegacyDirectoryMetadata>,
+            files          : Vec<LegacyFile>,
+        }
+
+        // Layout of DirectoryMetadata before the size and child count were added in schema version 6
+        #[derive(RustcEncodable)]
+        struct VersionFourDirectoryMetadata {
+            key               : LegacyDirectoryKey,
+            name              : String,
+            created_time_sec  : i64,
+            created_time_nsec : i32,
+            modified_time_sec : i64,
+            modified_time_nsec: i32,
+            user_metadata     : Vec<u8>,
+            parent_dir_key    : Option<LegacyDirectoryKey>,
+            title             : Option<String>,
+            hidden            : bool,
+        }
+
+        #[derive(RustcEncodable)]
+        struct VersionFourDirectoryListing {
+            schema_version : u16,
+            metadata       : VersionFourDirectoryMetadata,
+            sub_directories: Vec<VersionFourDirectoryMetadata>,
+            files          : Vec<::file::File>,
+        }
+
+        #[derive(RustcEncodable)]
+        struct VersionFiveDirectoryListing {
+            schema_version : u16,
+            metadata       : VersionFourDirectoryMetadata,
+            sub_directories: Vec<VersionFourDirectoryMetadata>,
+            files          : Vec<::file::File>,
+            checksum       : Vec<u8>,
+        }
+
+        #[derive(RustcEncodable)]
+        struct VersionOneDirectoryListing {
+            schema_version : u16,
+            metadata       : LegacyDirectoryMetadata,
+            sub_directories: Vec<LegacyDirectoryMetadata>,
+            files          : Vec<LegacyFile>,
+        }
+
+        let mut directory_listing = eval_result!(DirectoryListing::new("Home".to_string(),
+                                                                       10,
+                                                                       Vec::new(),
+                                                                       true,
+                                                                       ::AccessLevel::Private,
+                                                                       None));
+        directory_listing.upsert_file(eval_result!(::file::File::new(::metadata::file_metadata::FileMetadata::new("index.html".to_string(), Vec::new()),
+                                                                     ::self_encryption::datamap::DataMap::None)));
+        // Entries saved before schema version 4 are hidden if their name starts with `.`
+        directory_listing.upsert_file(eval_result!(::file::File::new(::metadata::file_metadata::FileMetadata::new(".profile".to_string(), Vec::new()),
+                                                                     ::self_encryption::datamap::DataMap::None)));
+        let mut sub_directory = eval_result!(::metadata::directory_metadata::DirectoryMetadata::new("Child".to_string(),
+                                                                                                    10,
+                                                                                                    true,
+                                                                                                    ::AccessLevel::Private,
+                                                                                                    Vec::new(),
+                                                                                                    None));
+        // Directories saved before schema version 3 get their name as title
+        sub_directory.set_title(Some("Child".to_string()));
+        directory_listing.upsert_sub_directory(sub_directory);
+        directory_listing.get_mut_metadata().set_title(Some("Home".to_string()));
+        let legacy_key = |key: &::metadata::directory_key::DirectoryKey| LegacyDirectoryKey {
+            id          : key.get_id().clone(),
+            type_tag    : key.get_type_tag(),
+            versioned   : key.is_versioned(),
+            access_level: key.get_access_level().clone(),
+        };
+        let legacy_metadata = |metadata: &::metadata::directory_metadata::DirectoryMetadata| LegacyDirectoryMetadata {
+            key               : legacy_key(metadata.get_key()),
+            name              : metadata.get_name().clone(),
+            created_time_sec  : metadata.get_created_time().to_timespec().sec,
+            created_time_nsec : metadata.get_created_time().to_timespec().nsec,
+            modified_time_sec : metadata.get_modified_time().to_timespec().sec,
+            modified_time_nsec: metadata.get_modified_time().to_timespec().nsec,
+            user_metadata     : metadata.get_user_metadata().clone(),
+            parent_dir_key    : metadata.get_parent_dir_key().map(&legacy_key),
+        };
+        let version_four_metadata = |metadata: &::metadata::directory_metadata::DirectoryMetadata| VersionFourDirectoryMetadata {
+            key               : legacy_key(metadata.get_key()),
+            name              : metadata.get_name().clone(),
+            created_time_sec  : metadata.get_created_time().to_timespec().sec,
+            created_time_nsec : metadata.get_created_time().to_timespec().nsec,
+            modified_time_sec : metadata.get_modified_time().to_timespec().sec,
+            modified_time_nsec: metadata.get_modified_time().to_timespec().nsec,
+            user_metadata     : metadata.get_user_metadata().clone(),
+            parent_dir_key    : metadata.get_parent_dir_key().map(&legacy_key),
+            title             : metadata.get_title().cloned(),
+            hidden            : metadata.is_hidden(),
+        };
+        let legacy_files = || directory_listing.get_files().iter().map(|file| {
+            let metadata = file.get_metadata();
+            LegacyFile {
+                id      : file.get_id().clone(),
+                metadata: LegacyFileMetadata {
+                    name              : metadata.get_name().clone(),
+                    size              : metadata.get_size(),
+                    created_time_sec  : metadata.get_created_time().to_timespec().sec,
+                    created_time_nsec : metadata.get_created_time().to_timespec().nsec,
+                    modified_time_sec : metadata.get_modified_time().to_timespec().sec,
+                    modified_time_nsec: metadata.get_modified_time().to_timespec().nsec,
+                    user_metadata     : metadata.get_user_metadata().clone(),
+                },
+                datamap : file.get_datamap().clone(),
+            }
+        }).collect::<Vec<LegacyFile>>();
+
+        let unversioned = UnversionedDirectoryListing {
+            metadata       : legacy_metadata(directory_listing.get_metadata()),
+            sub_directories: directory_listing.get_sub_directories().iter().map(&legacy_metadata).collect(),
+            files          : legacy_files(),
+        };
+        let serialised_data = eval_result!(::safe_core::utility::serialise(&unversioned));
+        assert_eq!(eval_result!(DirectoryListing::deserialise(&serialised_data)), directory_listing);
+
+        let version_one = VersionOneDirectoryListing {
+            schema_version : 1,
+            metadata       : legacy_metadata(directory_listing.get_metadata()),
+            sub_directories: directory_listing.get_sub_directories().iter().map(&legacy_metadata).collect(),
+            files          : legacy_files(),
+        };
+        let serialised_data = eval_result!(::safe_core::utility::serialise(&version_one));
+        assert_eq!(eval_result!(DirectoryListing::deserialise(&serialised_data)), directory_listing);
+
+        // Listings saved before schema version 5 have no checksum
+        let version_four = VersionFourDirectoryListing {
+            schema_version : 4,
+            metadata       : version_four_metadata(directory_listing.get_metadata()),
+            sub_directories: directory_listing.get_sub_directories().iter().map(&version_four_metadata).collect(),
+            files          : directory_listing.get_files().clone(),
+        };
+        let serialised_data = eval_result!(::safe_core::utility::serialise(&version_four));
+        assert_eq!(eval_result!(DirectoryListing::deserialise(&serialised_data)), directory_listing);
+
+        // Listings saved in schema version 5 have a checksum over the layout of that version
+        let content = (version_four_metadata(directory_listing.get_metadata()),
+                       directory_listing.get_sub_directories().iter().map(&version_four_metadata).collect::<Vec<_>>(),
+                       directory_listing.get_files().clone());
+        let version_five = VersionFiveDirectoryListing {
+            schema_version : 5,
+            checksum       : ::sodiumoxide::crypto::hash::sha512::hash(&eval_result!(::safe_core::utility::serialise(&content))).0.to_vec(),
+            metadata       : content.0,
+            sub_directories: content.1,
+            files          : content.2,
+        };
+        let serialised_data = eval_result!(::safe_core::utility::serialise(&version_five));
+        assert_eq!(eval_result!(DirectoryListing::deserialise(&serialised_data)), directory_listing);
+
+        let serialised_data = eval_result!(directory_listing.serialise());
+        assert_eq!(eval_result!(DirectoryListing::deserialise(&serialised_data)), directory_listing);
+    }
+
+    #[test]
+    fn reject_listing_not_matching_checksum() {
+        let mut directory_listing = eval_result!(DirectoryListing::new("Home".to_string(),
+                                                                       10,
+                                                                       Vec::new(),
+                                                                       true,
+                                                                       ::AccessLevel::Private,
+                                                                       None));
+        directory_listing.upsert_file(eval_result!(::file::File::new(::metadata::file_metadata::FileMetadata::new("index.html".to_string(), Vec::new()),
+                                                                     ::self_encryption::datamap::DataMap::None)));
+        let mut serialised_data = eval_result!(directory_listing.serialise());
+        assert_eq!(eval_result!(DirectoryListing::deserialise(&serialised_data)), directory_listing);
+
+        // Corrupt the name of the file, which still decodes
+        let name = "index.html".as_bytes();
+        let position = eval_option!(serialised_data.windows(name.len()).position(|window| window == name), "Name not found");
+        serialised_data[position] = b'x';
+        match DirectoryListing::deserialise(&serialised_data) {
+            Err(::errors::NfsError::MetaDataMissingOrCorrupted) => (),
+            _ => panic!("Expected MetaDataMissingOrCorrupted"),
+        }
+    }
+
     #[test]
     fn encrypt_and_decrypt_directory_listing() {
         let test_client = eval_result!(::safe_core::utility::test_utils::get_client());
@@ -221,9 +1151,22 @@ mod test {
                                                                    None));
         let encrypted_data = eval_result!(directory_listing.encrypt(client.clone()));
         let decrypted_listing = eval_result!(DirectoryListing::decrypt(client.clone(),
-                                                                       directory_listing.get_key().get_id(),
+                                                                       directory_listing.get_key(),
                                                                        encrypted_data));
         assert_eq!(directory_listing, decrypted_listing);
+
+        // A listing whose key has a nonce is encrypted and decrypted with that nonce, not the one generated from its id
+        let nonce = ::sodiumoxide::crypto::box_::gen_nonce();
+        let key = directory_listing.get_key().clone().with_nonce(nonce);
+        let directory_listing = DirectoryListing::new_with_key(key.clone(), "Home".to_string(), Vec::new(), None);
+        let encrypted_data = eval_result!(directory_listing.encrypt(client.clone()));
+        assert_eq!(eval_result!(DirectoryListing::decrypt(client.clone(), &key, encrypted_data.clone())), directory_listing);
+        assert_eq!(eval_result!(DirectoryListing::from_bytes(client.clone(), &key, &encrypted_data)), directory_listing);
+        let key_without_nonce = ::metadata::directory_key::DirectoryKey::new(key.get_id().clone(),
+                                                                            key.get_type_tag(),
+                                                                            key.is_versioned(),
+                                                                            key.get_access_level().clone());
+        assert!(DirectoryListing::decrypt(client.clone(), &key_without_nonce, encrypted_data).is_err());
     }
 
     #[test]
@@ -250,6 +1193,8 @@ mod test {
 
         let _ = eval_option!(directory_listing.find_file(file.get_name()), "File not found");
         let _ = eval_option!(directory_listing.find_file(file2.get_name()), "File not found");
+        assert_eq!(eval_option!(directory_listing.locate_file(file2.get_name()), "File not found").0, 1);
+        assert!(directory_listing.locate_file("missing.html").is_none());
 
         let _ = eval_result!(directory_listing.remove_file(file.get_metadata().get_name()));
         assert!(directory_listing.find_file(file.get_name()).is_none());
@@ -260,6 +1205,33 @@ mod test {
         assert_eq!(directory_listing.get_files().len(), 0);
     }
 
+    #[test]
+    fn take_file() {
+        let mut directory_listing = eval_result!(DirectoryListing::new("Home".to_string(),
+                                                                       10,
+                                                                       Vec::new(),
+                                                                       true,
+                                                                       ::AccessLevel::Private,
+                                                                       None));
+        let file = eval_result!(::file::File::new(::metadata::file_metadata::FileMetadata::new("index.html".to_string(), Vec::new()),
+                                                  ::self_encryption::datamap::DataMap::None));
+        let file2 = eval_result!(::file::File::new(::metadata::file_metadata::FileMetadata::new("demo.html".to_string(), Vec::new()),
+                                                   ::self_encryption::datamap::DataMap::None));
+        directory_listing.upsert_file(file.clone());
+        directory_listing.upsert_file(file2.clone());
+
+        let taken_file = eval_option!(directory_listing.take_file(file2.get_name()), "File not found");
+        assert_eq!(taken_file, file2);
+        assert_eq!(directory_listing.get_files().len(), 1);
+        assert!(directory_listing.find_file(file.get_name()).is_some());
+        assert!(directory_listing.take_file(file2.get_name()).is_none());
+
+        match directory_listing.remove_file(file2.get_metadata().get_name()) {
+            Err(::errors::NfsError::FileNotFound) => (),
+            _ => panic!("Expected FileNotFound"),
+        }
+    }
+
     #[test]
     fn find_upsert_remove_directory() {
         let mut directory_listing = eval_result!(DirectoryListing::new("Home".to_string(),
@@ -302,4 +1274,545 @@ mod test {
         assert_eq!(directory_listing.get_sub_directories().len(), 0);
     }
 
+    #[test]
+    fn take_sub_directory() {
+        let mut directory_listing = eval_result!(DirectoryListing::new("Home".to_string(),
+                                                                       10,
+                                                                       Vec::new(),
+                                                                       true,
+                                                                       ::AccessLevel::Private,
+                                                                       None));
+        let sub_directory = eval_result!(DirectoryListing::new("Child one".to_string(),
+                                                               10,
+                                                               Vec::new(),
+                                                               true,
+                                                               ::AccessLevel::Private,
+                                                               None));
+        let sub_directory_two = eval_result!(DirectoryListing::new("Child Two".to_string(),
+                                                                   10,
+                                                                   Vec::new(),
+                                                                   true,
+                                                                   ::AccessLevel::Private,
+                                                                   None));
+        directory_listing.upsert_sub_directory(sub_directory.get_metadata().clone());
+        directory_listing.upsert_sub_directory(sub_directory_two.get_metadata().clone());
+
+        let taken_directory = eval_option!(directory_listing.take_sub_directory(sub_directory_two.get_metadata().get_name()),
+                                           "Directory not found");
+        assert_eq!(taken_directory, *sub_directory_two.get_metadata());
+        assert_eq!(directory_listing.get_sub_directories().len(), 1);
+        assert!(directory_listing.find_sub_directory(sub_directory.get_metadata().get_name()).is_some());
+        assert!(directory_listing.take_sub_directory(sub_directory_two.get_metadata().get_name()).is_none());
+
+        match directory_listing.remove_sub_directory(sub_directory_two.get_metadata().get_name()) {
+            Err(::errors::NfsError::DirectoryNotFound(None)) => (),
+            _ => panic!("Expected DirectoryNotFound"),
+        }
+    }
+
+    #[test]
+    fn get_entries_by_index() {
+        let mut directory_listing = eval_result!(DirectoryListing::new("Home".to_string(),
+                                                                       10,
+                                                                       Vec::new(),
+                                                                       true,
+                                                                       ::AccessLevel::Private,
+                                                                       None));
+        let file_one = eval_result!(::file::File::new(::metadata::file_metadata::FileMetadata::new("b.html".to_string(), Vec::new()),
+                                                      ::self_encryption::datamap::DataMap::None));
+        let file_two = eval_result!(::file::File::new(::metadata::file_metadata::FileMetadata::new("a.html".to_string(), Vec::new()),
+                                                      ::self_encryption::datamap::DataMap::None));
+        directory_listing.upsert_file(file_one.clone());
+        directory_listing.upsert_file(file_two.clone());
+        assert_eq!(*eval_option!(directory_listing.get_file_by_index(0), "File not found"), file_one);
+        assert_eq!(*eval_option!(directory_listing.get_file_by_index(1), "File not found"), file_two);
+        assert!(directory_listing.get_file_by_index(2).is_none());
+
+        let sub_directory = eval_result!(DirectoryListing::new("Child".to_string(),
+                                                               10,
+                                                               Vec::new(),
+                                                               true,
+                                                               ::AccessLevel::Private,
+                                                               None));
+        assert!(directory_listing.get_sub_directory_by_index(0).is_none());
+        directory_listing.upsert_sub_directory(sub_directory.get_metadata().clone());
+        assert_eq!(*eval_option!(directory_listing.get_sub_directory_by_index(0), "Directory not found"),
+                   *sub_directory.get_metadata());
+    }
+
+    #[test]
+    fn upsert_sub_directory_with_conflicting_key() {
+        let mut directory_listing = eval_result!(DirectoryListing::new("Home".to_string(),
+                                                                       10,
+                                                                       Vec::new(),
+                                                                       true,
+                                                                       ::AccessLevel::Private,
+                                                                       None));
+        let mut sub_directory = eval_result!(DirectoryListing::new("Child".to_string(),
+                                                                   10,
+                                                                   Vec::new(),
+                                                                   true,
+                                                                   ::AccessLevel::Private,
+                                                                   None));
+        directory_listing.upsert_sub_directory(sub_directory.get_metadata().clone());
+        // Same key is a true update
+        sub_directory.get_mut_metadata().set_user_metadata(vec![1u8; 10]);
+        eval_result!(directory_listing.upsert_sub_directory_checked(sub_directory.get_metadata().clone(), false));
+        assert_eq!(directory_listing.get_sub_directories().len(), 1);
+        assert_eq!(*eval_option!(directory_listing.find_sub_directory(&"Child".to_string()), "Directory not found").get_user_metadata(),
+                   vec![1u8; 10]);
+
+        // Same name with a different key is a conflict
+        let same_named_directory = eval_result!(DirectoryListing::new("Child".to_string(),
+                                                                      10,
+                                                                      Vec::new(),
+                                                                      true,
+                                                                      ::AccessLevel::Private,
+                                                                      None));
+        assert!(directory_listing.check_sub_directory_conflict(sub_directory.get_metadata()).is_ok());
+        match directory_listing.check_sub_directory_conflict(same_named_directory.get_metadata()) {
+            Err(::errors::NfsError::SubDirectoryKeyConflict) => (),
+            _ => panic!("Expected SubDirectoryKeyConflict"),
+        }
+        match directory_listing.upsert_sub_directory_checked(same_named_directory.get_metadata().clone(), false) {
+            Err(::errors::NfsError::SubDirectoryKeyConflict) => (),
+            _ => panic!("Expected SubDirectoryKeyConflict"),
+        }
+        assert_eq!(*eval_option!(directory_listing.find_sub_directory(&"Child".to_string()), "Directory not found").get_key(),
+                   *sub_directory.get_key());
+
+        eval_result!(directory_listing.upsert_sub_directory_checked(same_named_directory.get_metadata().clone(), true));
+        assert_eq!(directory_listing.get_sub_directories().len(), 1);
+        assert_eq!(*eval_option!(directory_listing.find_sub_directory(&"Child".to_string()), "Directory not found").get_key(),
+                   *same_named_directory.get_key());
+    }
+
+    #[test]
+    fn clone_shares_entries() {
+        let mut directory_listing = eval_result!(DirectoryListing::new("Home".to_string(),
+                                                                       10,
+                                                                       Vec::new(),
+                                                                       true,
+                                                                       ::AccessLevel::Private,
+                                                                       None));
+        for i in 0..1000 {
+            directory_listing.upsert_file(eval_result!(::file::File::new(::metadata::file_metadata::FileMetadata::new(format!("{}.html", i), Vec::new()),
+                                                                         ::self_encryption::datamap::DataMap::None)));
+        }
+
+        // Cloning shares the entries instead of copying them
+        let mut clone = directory_listing.clone();
+        assert!(directory_listing.get_files() as *const Vec<::file::File> == clone.get_files() as *const Vec<::file::File>);
+
+        // Modifying the metadata does not copy the entries
+        clone.get_mut_metadata().set_name("Renamed".to_string());
+        assert!(directory_listing.get_files() as *const Vec<::file::File> == clone.get_files() as *const Vec<::file::File>);
+
+        // Modifying the entries copies them, leaving the original untouched
+        let _ = eval_result!(clone.remove_file(&"0.html".to_string()));
+        assert!(directory_listing.get_files() as *const Vec<::file::File> != clone.get_files() as *const Vec<::file::File>);
+        assert_eq!(directory_listing.get_files().len(), 1000);
+        assert_eq!(clone.get_files().len(), 999);
+        assert_eq!(directory_listing.get_metadata().get_name(), "Home");
+    }
+
+    #[test]
+    fn validate() {
+        let mut directory_listing = eval_result!(DirectoryListing::new("Home".to_string(),
+                                                                       10,
+                                                                       Vec::new(),
+                                                                       true,
+                                                                       ::AccessLevel::Private,
+                                                                       None));
+        let new_file = |name: &str| eval_result!(::file::File::new(::metadata::file_metadata::FileMetadata::new(name.to_string(), Vec::new()),
+                                                                  ::self_encryption::datamap::DataMap::None));
+        directory_listing.upsert_file(new_file("index.html"));
+        eval_result!(directory_listing.validate());
+
+        let mut listing_with_empty_name = directory_listing.clone();
+        listing_with_empty_name.upsert_file(new_file(""));
+        match listing_with_empty_name.validate() {
+            Err(::errors::NfsError::NameIsEmpty) => (),
+            _ => panic!("Expected NameIsEmpty"),
+        }
+
+        for name in ["a/b", "a\0b", ".", ".."].iter() {
+            let mut listing_with_invalid_name = directory_listing.clone();
+            listing_with_invalid_name.upsert_file(new_file(name));
+            eval_result!(listing_with_invalid_name.validate());
+            match listing_with_invalid_name.validate_names() {
+                Err(::errors::NfsError::InvalidName) => (),
+                _ => panic!("Expected InvalidName"),
+            }
+
+            let serialised = eval_result!(listing_with_invalid_name.serialise());
+            let deserialised = eval_result!(DirectoryListing::deserialise(&serialised));
+            assert_eq!(deserialised, listing_with_invalid_name);
+            let mut renamed_listing = deserialised.clone();
+            eval_result!(renamed_listing.rename_file(name, "renamed.html".to_string()));
+            eval_result!(renamed_listing.validate_names());
+        }
+        eval_result!(directory_listing.validate_names());
+        eval_result!(DirectoryListing::validate_name("..a"));
+
+        let mut listing_with_duplicate = directory_listing.clone();
+        listing_with_duplicate.get_mut_files().push(new_file("index.html"));
+        match listing_with_duplicate.validate() {
+            Err(::errors::NfsError::FileAlreadyExistsWithSameName) => (),
+            _ => panic!("Expected FileAlreadyExistsWithSameName"),
+        }
+
+        let child = eval_result!(DirectoryListing::new("Child".to_string(),
+                                                       10,
+                                                       Vec::new(),
+                                                       true,
+                                                       ::AccessLevel::Private,
+                                                       None));
+        let mut listing_with_duplicate_directory = directory_listing.clone();
+        listing_with_duplicate_directory.get_mut_sub_directories().push(child.get_metadata().clone());
+        listing_with_duplicate_directory.get_mut_sub_directories().push(child.get_metadata().clone());
+        match listing_with_duplicate_directory.validate() {
+            Err(::errors::NfsError::DirectoryAlreadyExistsWithSameName) => (),
+            _ => panic!("Expected DirectoryAlreadyExistsWithSameName"),
+        }
+
+        let sub_directory = eval_result!(DirectoryListing::new("index.html".to_string(),
+                                                               10,
+                                                               Vec::new(),
+                                                               true,
+                                                               ::AccessLevel::Private,
+                                                               None));
+        directory_listing.upsert_sub_directory(sub_directory.get_metadata().clone());
+        match directory_listing.validate() {
+            Err(::errors::NfsError::NameConflictsWithExistingEntry) => (),
+            _ => panic!("Expected NameConflictsWithExistingEntry"),
+        }
+    }
+
+    #[test]
+    fn fingerprint() {
+        let mut directory_listing = eval_result!(DirectoryListing::new("Home".to_string(),
+                                                                       10,
+                                                                       Vec::new(),
+                                                                       true,
+                                                                       ::AccessLevel::Private,
+                                                                       None));
+        let file_one = eval_result!(::file::File::new(::metadata::file_metadata::FileMetadata::new("a.html".to_string(), Vec::new()),
+                                                      ::self_encryption::datamap::DataMap::Content(vec![1u8; 10])));
+        let file_two = eval_result!(::file::File::new(::metadata::file_metadata::FileMetadata::new("b.html".to_string(), Vec::new()),
+                                                      ::self_encryption::datamap::DataMap::None));
+        directory_listing.upsert_file(file_one.clone());
+        directory_listing.upsert_file(file_two.clone());
+        let fingerprint = directory_listing.fingerprint();
+
+        // Timestamps and order of the entries do not matter
+        let mut same_listing = directory_listing.clone();
+        same_listing.get_mut_files().reverse();
+        same_listing.get_mut_metadata().set_modified_time(::time::at_utc(::time::Timespec::new(0, 0)));
+        eval_option!(same_listing.get_mut_files().get_mut(0), "File not found").get_mut_metadata()
+                                                                          .set_modified_time(::time::at_utc(::time::Timespec::new(0, 0)));
+        assert_eq!(same_listing.fingerprint(), fingerprint);
+        let serialised_data = eval_result!(directory_listing.serialise());
+        assert_eq!(eval_result!(DirectoryListing::deserialise(&serialised_data)).fingerprint(), fingerprint);
+
+        // Content, names and sub directories do matter
+        let mut changed_listing = directory_listing.clone();
+        let mut changed_file = file_one.clone();
+        changed_file.set_datamap(::self_encryption::datamap::DataMap::Content(vec![2u8; 10]));
+        changed_listing.upsert_file(changed_file);
+        assert!(changed_listing.fingerprint() != fingerprint);
+
+        let mut renamed_listing = directory_listing.clone();
+        let mut renamed_file = file_two.clone();
+        renamed_file.get_mut_metadata().set_name("c.html".to_string());
+        renamed_listing.upsert_file(renamed_file);
+        assert!(renamed_listing.fingerprint() != fingerprint);
+
+        let sub_directory = eval_result!(DirectoryListing::new("Child".to_string(),
+                                                               10,
+                                                               Vec::new(),
+                                                               true,
+                                                               ::AccessLevel::Private,
+                                                               None));
+        directory_listing.upsert_sub_directory(sub_directory.get_metadata().clone());
+        assert!(directory_listing.fingerprint() != fingerprint);
+    }
+
+    #[test]
+    fn glob() {
+        let mut directory_listing = eval_result!(DirectoryListing::new("Home".to_string(),
+                                                                       10,
+                                                                       Vec::new(),
+                                                                       true,
+                                                                       ::AccessLevel::Private,
+                                                                       None));
+        for name in ["notes.txt", "todo.txt", "image.png"].iter() {
+            directory_listing.upsert_file(eval_result!(::file::File::new(::metadata::file_metadata::FileMetadata::new(name.to_string(), Vec::new()),
+                                                                         ::self_encryption::datamap::DataMap::None)));
+        }
+        let sub_directory = eval_result!(DirectoryListing::new("archive.txt".to_string(),
+                                                               10,
+                                                               Vec::new(),
+                                                               true,
+                                                               ::AccessLevel::Private,
+                                                               None));
+        directory_listing.upsert_sub_directory(sub_directory.get_metadata().clone());
+
+        let names = |pattern: &str| directory_listing.glob(pattern).iter().map(|entry| entry.get_name().clone()).collect::<Vec<String>>();
+        assert_eq!(names("*.txt"), vec!["notes.txt".to_string(), "todo.txt".to_string(), "archive.txt".to_string()]);
+        assert_eq!(names("????.txt"), vec!["todo.txt".to_string()]);
+        assert!(names("*.jpg").is_empty());
+        let entries = directory_listing.glob("archive*");
+        assert_eq!(entries.len(), 1);
+        assert!(!entries[0].is_file());
+    }
+
+    #[test]
+    fn from_bytes() {
+        let client = ::std::sync::Arc::new(::std::sync::Mutex::new(eval_result!(::safe_core::utility::test_utils::get_client())));
+        let mut private_directory = eval_result!(DirectoryListing::new("Private".to_string(),
+                                                                       ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                       Vec::new(),
+                                                                       true,
+                                                                       ::AccessLevel::Private,
+                                                                       None));
+        private_directory.upsert_file(eval_result!(::file::File::new(::metadata::file_metadata::FileMetadata::new("file.txt".to_string(), Vec::new()),
+                                                                     ::self_encryption::datamap::DataMap::None)));
+        let bytes = eval_result!(private_directory.encrypt(client.clone()));
+        let decoded = eval_result!(DirectoryListing::from_bytes(client.clone(), private_directory.get_key(), &bytes));
+        assert_eq!(decoded, private_directory);
+
+        let public_directory = eval_result!(DirectoryListing::new("Public".to_string(),
+                                                                  ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                  Vec::new(),
+                                                                  true,
+                                                                  ::AccessLevel::Public,
+                                                                  None));
+        let bytes = eval_result!(public_directory.serialise());
+        let decoded = eval_result!(DirectoryListing::from_bytes(client.clone(), public_directory.get_key(), &bytes));
+        assert_eq!(decoded, public_directory);
+
+        // Bytes of another directory are rejected
+        match DirectoryListing::from_bytes(client.clone(), private_directory.get_key(), &bytes) {
+            Err(::errors::NfsError::MetaDataMissingOrCorrupted) => (),
+            _ => panic!("Expected MetaDataMissingOrCorrupted"),
+        }
+        match DirectoryListing::from_bytes(client.clone(), public_directory.get_key(), &[1u8, 2, 3]) {
+            Err(::errors::NfsError::MetaDataMissingOrCorrupted) => (),
+            _ => panic!("Expected MetaDataMissingOrCorrupted"),
+        }
+    }
+
+    #[test]
+    fn visible_entries() {
+        let mut directory_listing = eval_result!(DirectoryListing::new("Home".to_string(),
+                                                                       10,
+                                                                       Vec::new(),
+                                                                       true,
+                                                                       ::AccessLevel::Private,
+                                                                       None));
+        let mut system_metadata = ::metadata::file_metadata::FileMetadata::new("system.dat".to_string(), Vec::new());
+        system_metadata.set_hidden(true);
+        let mut dot_metadata = ::metadata::file_metadata::FileMetadata::new(".shown".to_string(), Vec::new());
+        assert!(dot_metadata.is_hidden());
+        dot_metadata.set_hidden(false);
+        for metadata in vec![::metadata::file_metadata::FileMetadata::new("notes.txt".to_string(), Vec::new()),
+                             ::metadata::file_metadata::FileMetadata::new(".bashrc".to_string(), Vec::new()),
+                             system_metadata,
+                             dot_metadata] {
+            directory_listing.upsert_file(eval_result!(::file::File::new(metadata, ::self_encryption::datamap::DataMap::None)));
+        }
+        for name in vec![".config", "Documents"] {
+            let sub_directory = eval_result!(::metadata::directory_metadata::DirectoryMetadata::new(name.to_string(),
+                                                                                                    10,
+                                                                                                    true,
+                                                                                                    ::AccessLevel::Private,
+                                                                                                    Vec::new(),
+                                                                                                    None));
+            directory_listing.upsert_sub_directory(sub_directory);
+        }
+
+        let names: Vec<&String> = directory_listing.visible_entries().iter().map(|entry| entry.get_name()).collect();
+        assert_eq!(names, vec!["notes.txt", ".shown", "Documents"]);
+
+        let serialised_data = eval_result!(directory_listing.serialise());
+        let deserialised: DirectoryListing = eval_result!(::safe_core::utility::deserialise(&serialised_data));
+        assert_eq!(deserialised.visible_entries().len(), 3);
+    }
+
+    #[test]
+    fn remaining_capacity() {
+        let versioned_listing = eval_result!(DirectoryListing::new("Versioned".to_string(),
+                                                                   10,
+                                                                   Vec::new(),
+                                                                   true,
+                                                                   ::AccessLevel::Private,
+                                                                   None));
+        assert_eq!(eval_result!(versioned_listing.remaining_capacity()), None);
+
+        let mut directory_listing = eval_result!(DirectoryListing::new("Home".to_string(),
+                                                                       10,
+                                                                       vec![1u8; 100],
+                                                                       false,
+                                                                       ::AccessLevel::Private,
+                                                                       None));
+        let mut remaining_capacity = eval_option!(eval_result!(directory_listing.remaining_capacity()), "Capacity not limited");
+        assert_eq!(remaining_capacity, super::MAX_DIRECTORY_LISTING_SIZE - eval_result!(directory_listing.serialise()).len());
+        for index in 0..10usize {
+            let chunks = (0..5).map(|chunk_num| ::self_encryption::datamap::ChunkDetails {
+                chunk_num  : chunk_num,
+                hash       : vec![chunk_num as u8; 64],
+                pre_hash   : vec![chunk_num as u8; 64],
+                source_size: 1024 * 1024,
+            }).collect();
+            let metadata = ::metadata::file_metadata::FileMetadata::new(format!("file-{}.txt", index), vec![index as u8; index * 10]);
+            directory_listing.upsert_file(eval_result!(::file::File::new(metadata, ::self_encryption::datamap::DataMap::Chunks(chunks))));
+            let sub_directory = eval_result!(::metadata::directory_metadata::DirectoryMetadata::new(format!("dir-{}", index),
+                                                                                                    10,
+                                                                                                    true,
+                                                                                                    ::AccessLevel::Private,
+                                                                                                    Vec::new(),
+                                                                                                    None));
+            directory_listing.upsert_sub_directory(sub_directory);
+
+            let serialised_data = eval_result!(directory_listing.serialise());
+            assert!(directory_listing.estimated_serialized_size() >= serialised_data.len());
+            let capacity = eval_option!(eval_result!(directory_listing.remaining_capacity()), "Capacity not limited");
+            assert_eq!(capacity, super::MAX_DIRECTORY_LISTING_SIZE - serialised_data.len());
+            assert!(capacity < remaining_capacity);
+            remaining_capacity = capacity;
+        }
+    }
+
+    #[test]
+    fn entry_metadata() {
+        let mut directory_listing = eval_result!(DirectoryListing::new("Home".to_string(),
+                                                                       10,
+                                                                       Vec::new(),
+                                                                       true,
+                                                                       ::AccessLevel::Private,
+                                                                       None));
+        let mut file_metadata = ::metadata::file_metadata::FileMetadata::new("notes.txt".to_string(), Vec::new());
+        file_metadata.set_size(42);
+        let file = eval_result!(::file::File::new(file_metadata, ::self_encryption::datamap::DataMap::None));
+        directory_listing.upsert_file(file.clone());
+        let sub_directory = eval_result!(::metadata::directory_metadata::DirectoryMetadata::new("Documents".to_string(),
+                                                                                                10,
+                                                                                                true,
+                                                                                                ::AccessLevel::Private,
+                                                                                                Vec::new(),
+                                                                                                None));
+        directory_listing.upsert_sub_directory(sub_directory.clone());
+
+        let metadata = eval_option!(directory_listing.entry_metadata("notes.txt"), "Entry not found");
+        assert_eq!(metadata.get_name(), "notes.txt");
+        assert_eq!(metadata.get_kind(), ::directory_listing::entry::EntryKind::File);
+        assert_eq!(metadata.get_size(), Some(42));
+        assert_eq!(metadata.get_created_time(), file.get_metadata().get_created_time());
+        assert_eq!(metadata.get_modified_time(), file.get_metadata().get_modified_time());
+
+        let metadata = eval_option!(directory_listing.entry_metadata("Documents"), "Entry not found");
+        assert_eq!(metadata.get_kind(), ::directory_listing::entry::EntryKind::Directory);
+        assert_eq!(metadata.get_size(), None);
+        assert_eq!(metadata.get_modified_time(), sub_directory.get_modified_time());
+
+        assert!(directory_listing.entry_metadata("missing").is_none());
+    }
+
+    #[test]
+    fn unique_name() {
+        let mut directory_listing = eval_result!(DirectoryListing::new("Home".to_string(),
+                                                                       10,
+                                                                       Vec::new(),
+                                                                       true,
+                                                                       ::AccessLevel::Private,
+                                                                       None));
+        assert_eq!(directory_listing.unique_name("Notes"), "Notes");
+        for name in vec!["Notes", "Notes (1)"] {
+            let metadata = ::metadata::file_metadata::FileMetadata::new(name.to_string(), Vec::new());
+            directory_listing.upsert_file(eval_result!(::file::File::new(metadata, ::self_encryption::datamap::DataMap::None)));
+        }
+        let sub_directory = eval_result!(::metadata::directory_metadata::DirectoryMetadata::new("Notes (2)".to_string(),
+                                                                                                10,
+                                                                                                true,
+                                                                                                ::AccessLevel::Private,
+                                                                                                Vec::new(),
+                                                                                                None));
+        directory_listing.upsert_sub_directory(sub_directory);
+        assert_eq!(directory_listing.unique_name("Notes"), "Notes (3)");
+        assert_eq!(directory_listing.unique_name("Notes (1)"), "Notes (1) (1)");
+    }
+
+    #[test]
+    fn file_sizes() {
+        let mut directory_listing = eval_result!(DirectoryListing::new("Home".to_string(),
+                                                                       10,
+                                                                       Vec::new(),
+                                                                       true,
+                                                                       ::AccessLevel::Private,
+                                                                       None));
+        assert!(directory_listing.file_sizes().is_empty());
+        let mut metadata = ::metadata::file_metadata::FileMetadata::new("sparse.dat".to_string(), Vec::new());
+        metadata.set_size(100);
+        directory_listing.upsert_file(eval_result!(::file::File::new(metadata, ::self_encryption::datamap::DataMap::None)));
+        let metadata = ::metadata::file_metadata::FileMetadata::new("inline.txt".to_string(), Vec::new());
+        directory_listing.upsert_file(eval_result!(::file::File::new(metadata, ::self_encryption::datamap::DataMap::Content(vec![1u8; 12]))));
+        assert_eq!(directory_listing.file_sizes(), vec![("sparse.dat".to_string(), 100), ("inline.txt".to_string(), 12)]);
+    }
+
+    #[test]
+    fn diff() {
+        let mut old_listing = eval_result!(DirectoryListing::new("Home".to_string(),
+                                                                 10,
+                                                                 Vec::new(),
+                                                                 true,
+                                                                 ::AccessLevel::Private,
+                                                                 None));
+        for name in vec!["kept.txt", "removed.txt", "modified.txt"] {
+            let metadata = ::metadata::file_metadata::FileMetadata::new(name.to_string(), Vec::new());
+            old_listing.upsert_file(eval_result!(::file::File::new(metadata, ::self_encryption::datamap::DataMap::None)));
+        }
+        for name in vec!["Kept", "Removed", "Modified"] {
+            let sub_directory = eval_result!(::metadata::directory_metadata::DirectoryMetadata::new(name.to_string(),
+                                                                                                    10,
+                                                                                                    true,
+                                                                                                    ::AccessLevel::Private,
+                                                                                                    Vec::new(),
+                                                                                                    None));
+            old_listing.upsert_sub_directory(sub_directory);
+        }
+        assert!(old_listing.diff(&old_listing).is_empty());
+
+        let mut new_listing = old_listing.clone();
+        eval_result!(new_listing.remove_file(&"removed.txt".to_string()));
+        let mut modified_file = eval_option!(new_listing.find_file(&"modified.txt".to_string()), "File not found").clone();
+        modified_file.set_datamap(::self_encryption::datamap::DataMap::Content(vec![1u8; 10]));
+        new_listing.upsert_file(modified_file);
+        let metadata = ::metadata::file_metadata::FileMetadata::new("added.txt".to_string(), Vec::new());
+        new_listing.upsert_file(eval_result!(::file::File::new(metadata, ::self_encryption::datamap::DataMap::None)));
+        eval_result!(new_listing.remove_sub_directory(&"Removed".to_string()));
+        let mut modified_directory = eval_option!(new_listing.find_sub_directory(&"Modified".to_string()), "Directory not found").clone();
+        modified_directory.set_user_metadata(vec![1u8; 4]);
+        new_listing.upsert_sub_directory(modified_directory);
+        let sub_directory = eval_result!(::metadata::directory_metadata::DirectoryMetadata::new("Added".to_string(),
+                                                                                                10,
+                                                                                                true,
+                                                                                                ::AccessLevel::Private,
+                                                                                                Vec::new(),
+                                                                                                None));
+        new_listing.upsert_sub_directory(sub_directory);
+
+        let diff = old_listing.diff(&new_listing);
+        assert!(!diff.is_empty());
+        assert_eq!(*diff.get_added_files(), vec!["added.txt".to_string()]);
+        assert_eq!(*diff.get_removed_files(), vec!["removed.txt".to_string()]);
+        assert_eq!(*diff.get_modified_files(), vec!["modified.txt".to_string()]);
+        assert_eq!(*diff.get_added_sub_directories(), vec!["Added".to_string()]);
+        assert_eq!(*diff.get_removed_sub_directories(), vec!["Removed".to_string()]);
+        assert_eq!(*diff.get_modified_sub_directories(), vec!["Modified".to_string()]);
+
+        let reverse_diff = new_listing.diff(&old_listing);
+        assert_eq!(*reverse_diff.get_added_files(), vec!["removed.txt".to_string()]);
+        assert_eq!(*reverse_diff.get_removed_sub_directories(), vec!["Added".to_string()]);
+    }
 }
@@ -28,8 +28,9 @@ pub enum NfsError {
     DirectoryAlreadyExistsWithSameName,
     /// Destination is Same as the Source
     DestinationAndSourceAreSame,
-    /// Directory not found
-    DirectoryNotFound,
+    /// Directory not found. Holds the key of the directory, when it is known, so that it can be told
+    /// whether a versioned or an unversioned directory was involved
+    DirectoryNotFound(Option<::metadata::directory_key::DirectoryKey>),
     /// File Already exists with the same name in a directory
     FileAlreadyExistsWithSameName,
     /// File does not match with the existing file in the directory listing
@@ -40,6 +41,54 @@ pub enum NfsError {
     InvalidRangeSpecified,
     /// Validation error - if the field passed as parameter is not valid
     ParameterIsNotValid,
+    /// A sub directory with the same name but a different key already exists in the directory
+    SubDirectoryKeyConflict,
+    /// The content read is not valid UTF-8
+    InvalidUtf8,
+    /// The File no longer matches the state of the file stored in the network
+    StaleFileReference,
+    /// Error while reading from or writing to a local source or sink
+    LocalIo(::std::io::Error),
+    /// The Writer has already been closed
+    WriterAlreadyClosed,
+    /// A file or directory has an empty name
+    NameIsEmpty,
+    /// A directory has the same name as a file in the same directory
+    NameConflictsWithExistingEntry,
+    /// The File is locked by another owner
+    FileLocked {
+        /// Owner of the lock
+        owner: String,
+    },
+    /// The requested entry was not found
+    NotFound,
+    /// A Shared directory was accessed without the shared key it is encrypted with
+    MissingSharedKey,
+    /// A file or directory name holds a forbidden character or is a reserved name, so that it could not be addressed
+    /// by path
+    InvalidName,
+    /// Data could not be serialised, as opposed to being rejected by the network
+    SerialisationError(String),
+    /// Data expected to hold a directory listing could not be decrypted or decoded
+    MetaDataMissingOrCorrupted,
+    /// The network did not respond within the time allowed
+    Timeout,
+    /// A key or token does not have the expected format
+    InvalidKeyFormat,
+    /// The access level of a directory is not allowed for the access level of its parent
+    AccessLevelMismatch,
+    /// A write was attempted through a helper in read-only mode
+    ReadOnly,
+    /// The content buffered for a file would grow beyond the limit for buffered files
+    BufferedFileTooLarge,
+    /// A tree of directories could not be created and the directories already created for it were removed from their
+    /// parents
+    TreeCreationRolledBack {
+        /// Error which stopped the creation of the tree
+        error               : Box<NfsError>,
+        /// Keys of the directories removed from their parents, whose StructuredData is left in the network
+        unlinked_directories: Vec<::metadata::directory_key::DirectoryKey>,
+    },
     /// Unexpected error
     Unexpected(String),
 }
@@ -50,6 +99,97 @@ impl From<::safe_core::errors::CoreError> for NfsError {
     }
 }
 
+impl From<::std::io::Error> for NfsError {
+    fn from(error: ::std::io::Error) -> NfsError {
+        NfsError::LocalIo(error)
+    }
+}
+
+impl NfsError {
+    /// Returns a copy of the error. A LocalIo error is copied as an error of the same kind and description.
+    /// Returns None for a CoreError, which can not be copied
+    pub fn try_clone(&self) -> Option<NfsError> {
+        match *self {
+            NfsError::DirectoryAlreadyExistsWithSameName    => Some(NfsError::DirectoryAlreadyExistsWithSameName),
+            NfsError::DestinationAndSourceAreSame           => Some(NfsError::DestinationAndSourceAreSame),
+            NfsError::FileAlreadyExistsWithSameName         => Some(NfsError::FileAlreadyExistsWithSameName),
+            NfsError::FileDoesNotMatch                      => Some(NfsError::FileDoesNotMatch),
+            NfsError::FileNotFound                          => Some(NfsError::FileNotFound),
+            NfsError::InvalidRangeSpecified                 => Some(NfsError::InvalidRangeSpecified),
+            NfsError::ParameterIsNotValid                   => Some(NfsError::ParameterIsNotValid),
+            NfsError::SubDirectoryKeyConflict               => Some(NfsError::SubDirectoryKeyConflict),
+            NfsError::InvalidUtf8                           => Some(NfsError::InvalidUtf8),
+            NfsError::StaleFileReference                    => Some(NfsError::StaleFileReference),
+            NfsError::WriterAlreadyClosed                   => Some(NfsError::WriterAlreadyClosed),
+            NfsError::NameIsEmpty                           => Some(NfsError::NameIsEmpty),
+            NfsError::NameConflictsWithExistingEntry        => Some(NfsError::NameConflictsWithExistingEntry),
+            NfsError::NotFound                              => Some(NfsError::NotFound),
+            NfsError::MissingSharedKey                      => Some(NfsError::MissingSharedKey),
+            NfsError::InvalidName                           => Some(NfsError::InvalidName),
+            NfsError::MetaDataMissingOrCorrupted            => Some(NfsError::MetaDataMissingOrCorrupted),
+            NfsError::Timeout                               => Some(NfsError::Timeout),
+            NfsError::InvalidKeyFormat                      => Some(NfsError::InvalidKeyFormat),
+            NfsError::AccessLevelMismatch                   => Some(NfsError::AccessLevelMismatch),
+            NfsError::ReadOnly                              => Some(NfsError::ReadOnly),
+            NfsError::BufferedFileTooLarge                  => Some(NfsError::BufferedFileTooLarge),
+            NfsError::CoreError(_)                          => None,
+            NfsError::DirectoryNotFound(ref key)            => Some(NfsError::DirectoryNotFound(key.clone())),
+            NfsError::LocalIo(ref error)                    => Some(NfsError::LocalIo(::std::io::Error::new(error.kind(), format!("{}", error)))),
+            NfsError::FileLocked { ref owner }              => Some(NfsError::FileLocked { owner: owner.clone() }),
+            NfsError::SerialisationError(ref error)         => Some(NfsError::SerialisationError(error.clone())),
+            NfsError::Unexpected(ref error)                 => Some(NfsError::Unexpected(error.clone())),
+            NfsError::TreeCreationRolledBack { ref error, ref unlinked_directories } => error.try_clone().map(|error| {
+                NfsError::TreeCreationRolledBack {
+                    error               : Box::new(error),
+                    unlinked_directories: unlinked_directories.clone(),
+                }
+            }),
+        }
+    }
+}
+
+/// Errors compare equal if they are the same variant holding equal data. LocalIo errors compare by their kind and
+/// CoreErrors by their debug representation
+impl PartialEq for NfsError {
+    fn eq(&self, other: &NfsError) -> bool {
+        match (self, other) {
+            (&NfsError::CoreError(ref lhs), &NfsError::CoreError(ref rhs))                   => format!("{:?}", lhs) == format!("{:?}", rhs),
+            (&NfsError::DirectoryNotFound(ref lhs), &NfsError::DirectoryNotFound(ref rhs))   => lhs == rhs,
+            (&NfsError::LocalIo(ref lhs), &NfsError::LocalIo(ref rhs))                       => lhs.kind() == rhs.kind(),
+            (&NfsError::FileLocked { owner: ref lhs }, &NfsError::FileLocked { owner: ref rhs }) => lhs == rhs,
+            (&NfsError::SerialisationError(ref lhs), &NfsError::SerialisationError(ref rhs)) => lhs == rhs,
+            (&NfsError::Unexpected(ref lhs), &NfsError::Unexpected(ref rhs))                 => lhs == rhs,
+            (&NfsError::TreeCreationRolledBack { error: ref lhs_error, unlinked_directories: ref lhs_directories },
+             &NfsError::TreeCreationRolledBack { error: ref rhs_error, unlinked_directories: ref rhs_directories }) => {
+                lhs_error == rhs_error && lhs_directories == rhs_directories
+            },
+            (&NfsError::DirectoryAlreadyExistsWithSameName, &NfsError::DirectoryAlreadyExistsWithSameName) |
+            (&NfsError::DestinationAndSourceAreSame, &NfsError::DestinationAndSourceAreSame)               |
+            (&NfsError::FileAlreadyExistsWithSameName, &NfsError::FileAlreadyExistsWithSameName)           |
+            (&NfsError::FileDoesNotMatch, &NfsError::FileDoesNotMatch)                                     |
+            (&NfsError::FileNotFound, &NfsError::FileNotFound)                                             |
+            (&NfsError::InvalidRangeSpecified, &NfsError::InvalidRangeSpecified)                           |
+            (&NfsError::ParameterIsNotValid, &NfsError::ParameterIsNotValid)                               |
+            (&NfsError::SubDirectoryKeyConflict, &NfsError::SubDirectoryKeyConflict)                       |
+            (&NfsError::InvalidUtf8, &NfsError::InvalidUtf8)                                               |
+            (&NfsError::StaleFileReference, &NfsError::StaleFileReference)                                 |
+            (&NfsError::WriterAlreadyClosed, &NfsError::WriterAlreadyClosed)                               |
+            (&NfsError::NameIsEmpty, &NfsError::NameIsEmpty)                                               |
+            (&NfsError::NameConflictsWithExistingEntry, &NfsError::NameConflictsWithExistingEntry)         |
+            (&NfsError::NotFound, &NfsError::NotFound)                                                     |
+            (&NfsError::MissingSharedKey, &NfsError::MissingSharedKey)                                     |
+            (&NfsError::InvalidName, &NfsError::InvalidName)                                               |
+            (&NfsError::MetaDataMissingOrCorrupted, &NfsError::MetaDataMissingOrCorrupted)                 |
+            (&NfsError::Timeout, &NfsError::Timeout)                                                       |
+            (&NfsError::InvalidKeyFormat, &NfsError::InvalidKeyFormat)                                     |
+            (&NfsError::AccessLevelMismatch, &NfsError::AccessLevelMismatch)                               |
+            (&NfsError::ReadOnly, &NfsError::ReadOnly)                                                     |
+            (&NfsError::BufferedFileTooLarge, &NfsError::BufferedFileTooLarge)                             => true,
+            _ => false,
+        }
+    }
+}
+
 impl<'a> From<&'a str> for NfsError {
     fn from(error: &'a str) -> NfsError {
         NfsError::Unexpected(error.to_string())
@@ -62,13 +202,32 @@ impl Into<i32> for NfsError {
             NfsError::CoreError(error)                    => error.into(),
             NfsError::DirectoryAlreadyExistsWithSameName    => NFS_ERROR_START_RANGE - 1,
             NfsError::DestinationAndSourceAreSame           => NFS_ERROR_START_RANGE - 2,
-            NfsError::DirectoryNotFound                     => NFS_ERROR_START_RANGE - 3,
+            NfsError::DirectoryNotFound(_)                  => NFS_ERROR_START_RANGE - 3,
             NfsError::FileAlreadyExistsWithSameName         => NFS_ERROR_START_RANGE - 4,
             NfsError::FileDoesNotMatch                      => NFS_ERROR_START_RANGE - 5,
             NfsError::FileNotFound                          => NFS_ERROR_START_RANGE - 6,
             NfsError::InvalidRangeSpecified                 => NFS_ERROR_START_RANGE - 7,
             NfsError::ParameterIsNotValid                   => NFS_ERROR_START_RANGE - 8,
             NfsError::Unexpected(_)                         => NFS_ERROR_START_RANGE - 9,
+            NfsError::SubDirectoryKeyConflict               => NFS_ERROR_START_RANGE - 10,
+            NfsError::InvalidUtf8                           => NFS_ERROR_START_RANGE - 11,
+            NfsError::StaleFileReference                    => NFS_ERROR_START_RANGE - 12,
+            NfsError::LocalIo(_)                            => NFS_ERROR_START_RANGE - 13,
+            NfsError::WriterAlreadyClosed                   => NFS_ERROR_START_RANGE - 14,
+            NfsError::NameIsEmpty                           => NFS_ERROR_START_RANGE - 15,
+            NfsError::NameConflictsWithExistingEntry        => NFS_ERROR_START_RANGE - 17,
+            NfsError::FileLocked { .. }                     => NFS_ERROR_START_RANGE - 18,
+            NfsError::NotFound                              => NFS_ERROR_START_RANGE - 19,
+            NfsError::MissingSharedKey                      => NFS_ERROR_START_RANGE - 20,
+            NfsError::InvalidName                           => NFS_ERROR_START_RANGE - 21,
+            NfsError::SerialisationError(_)                 => NFS_ERROR_START_RANGE - 22,
+            NfsError::MetaDataMissingOrCorrupted            => NFS_ERROR_START_RANGE - 23,
+            NfsError::Timeout                               => NFS_ERROR_START_RANGE - 24,
+            NfsError::InvalidKeyFormat                      => NFS_ERROR_START_RANGE - 25,
+            NfsError::AccessLevelMismatch                   => NFS_ERROR_START_RANGE - 26,
+            NfsError::ReadOnly                              => NFS_ERROR_START_RANGE - 27,
+            NfsError::BufferedFileTooLarge                  => NFS_ERROR_START_RANGE - 28,
+            NfsError::TreeCreationRolledBack { .. }         => NFS_ERROR_START_RANGE - 29,
         }
     }
 }
@@ -79,13 +238,90 @@ impl ::std::fmt::Debug for NfsError {
             NfsError::CoreError(ref error)                => write!(f, "NfsError::CoreError -> {:?}", error),
             NfsError::DirectoryAlreadyExistsWithSameName    => write!(f, "NfsError::DirectoryAlreadyExistsWithSameName"),
             NfsError::DestinationAndSourceAreSame           => write!(f, "NfsError::DestinationAndSourceAreSame"),
-            NfsError::DirectoryNotFound                     => write!(f, "NfsError::DirectoryNotFound"),
+            NfsError::DirectoryNotFound(None)               => write!(f, "NfsError::DirectoryNotFound"),
+            NfsError::DirectoryNotFound(Some(ref key))      => write!(f,
+                                                                          "NfsError::DirectoryNotFound -> {} directory with tag {:?} and id {:?}",
+                                                                          if key.is_versioned() { "versioned" } else { "unversioned" },
+                                                                          key.get_type_tag(),
+                                                                          key.get_id()),
             NfsError::FileAlreadyExistsWithSameName         => write!(f, "NfsError::FileAlreadyExistsWithSameName"),
             NfsError::FileDoesNotMatch                      => write!(f, "NfsError::FileDoesNotMatch"),            
             NfsError::FileNotFound                          => write!(f, "NfsError::FileNotFound"),
             NfsError::InvalidRangeSpecified                 => write!(f, "NfsError::InvalidRangeSpecified"),
             NfsError::ParameterIsNotValid                   => write!(f, "NfsError::ParameterIsNotValid"),
             NfsError::Unexpected(ref error)                 => write!(f, "NfsError::Unexpected -> {:?}", error),
+            NfsError::SubDirectoryKeyConflict               => write!(f, "NfsError::SubDirectoryKeyConflict"),
+            NfsError::InvalidUtf8                           => write!(f, "NfsError::InvalidUtf8"),
+            NfsError::StaleFileReference                    => write!(f, "NfsError::StaleFileReference"),
+            NfsError::LocalIo(ref error)                    => write!(f, "NfsError::LocalIo -> {:?}", error),
+            NfsError::WriterAlreadyClosed                   => write!(f, "NfsError::WriterAlreadyClosed"),
+            NfsError::NameIsEmpty                           => write!(f, "NfsError::NameIsEmpty"),
+            NfsError::NameConflictsWithExistingEntry        => write!(f, "NfsError::NameConflictsWithExistingEntry"),
+            NfsError::FileLocked { ref owner }              => write!(f, "NfsError::FileLocked -> {:?}", owner),
+            NfsError::NotFound                              => write!(f, "NfsError::NotFound"),
+            NfsError::MissingSharedKey                      => write!(f, "NfsError::MissingSharedKey"),
+            NfsError::InvalidName                           => write!(f, "NfsError::InvalidName"),
+            NfsError::SerialisationError(ref error)         => write!(f, "NfsError::SerialisationError -> {:?}", error),
+            NfsError::MetaDataMissingOrCorrupted            => write!(f, "NfsError::MetaDataMissingOrCorrupted"),
+            NfsError::Timeout                               => write!(f, "NfsError::Timeout"),
+            NfsError::InvalidKeyFormat                      => write!(f, "NfsError::InvalidKeyFormat"),
+            NfsError::AccessLevelMismatch                   => write!(f, "NfsError::AccessLevelMismatch"),
+            NfsError::ReadOnly                              => write!(f, "NfsError::ReadOnly"),
+            NfsError::BufferedFileTooLarge                  => write!(f, "NfsError::BufferedFileTooLarge"),
+            NfsError::TreeCreationRolledBack { ref error, ref unlinked_directories } => write!(f,
+                                                                                               "NfsError::TreeCreationRolledBack -> {:?} with {:?} directories unlinked",
+                                                                                               error,
+                                                                                               unlinked_directories.len()),
+        }
+    }
+}
+
+/// Serialises the data through `safe_core::utility::serialise`, reporting a failure to encode it as
+/// NfsError::SerialisationError instead of as a CoreError
+pub fn serialise<T: ::rustc_serialize::Encodable>(data: &T) -> Result<Vec<u8>, NfsError> {
+    ::safe_core::utility::serialise(data).map_err(serialisation_error)
+}
+
+/// Reports a failure of safe_core to serialise data as NfsError::SerialisationError
+fn serialisation_error(error: ::safe_core::errors::CoreError) -> NfsError {
+    NfsError::SerialisationError(format!("{:?}", error))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn compare_and_clone() {
+        assert_eq!(NfsError::FileNotFound, NfsError::FileNotFound);
+        assert!(NfsError::FileNotFound != NfsError::NotFound);
+        assert!(NfsError::FileNotFound != NfsError::Unexpected("NfsError::FileNotFound".to_string()));
+        assert_eq!(NfsError::FileLocked { owner: "owner".to_string() }, NfsError::FileLocked { owner: "owner".to_string() });
+        assert!(NfsError::FileLocked { owner: "owner".to_string() } != NfsError::FileLocked { owner: "other".to_string() });
+        assert_eq!(NfsError::LocalIo(::std::io::Error::new(::std::io::ErrorKind::NotFound, "missing")),
+                   NfsError::LocalIo(::std::io::Error::new(::std::io::ErrorKind::NotFound, "gone")));
+
+        for error in vec![NfsError::Timeout,
+                          NfsError::BufferedFileTooLarge,
+                          NfsError::DirectoryNotFound(None),
+                          NfsError::SerialisationError("error".to_string()),
+                          NfsError::LocalIo(::std::io::Error::new(::std::io::ErrorKind::Other, "error"))] {
+            assert_eq!(eval_option!(error.try_clone(), "Expected a copy"), error);
+        }
+        assert!(NfsError::CoreError(::safe_core::errors::CoreError::ReceivedUnexpectedData).try_clone().is_none());
+    }
+
+    #[test]
+    fn serialisation_error() {
+        match super::serialisation_error(::safe_core::errors::CoreError::ReceivedUnexpectedData) {
+            NfsError::SerialisationError(_) => (),
+            _ => panic!("Expected SerialisationError"),
         }
+
+        let data = vec![1u64, 2, 3];
+        let serialised_data = eval_result!(serialise(&data));
+        assert_eq!(serialised_data, eval_result!(::safe_core::utility::serialise(&data)));
+        let deserialised_data: Vec<u64> = eval_result!(::safe_core::utility::deserialise(&serialised_data));
+        assert_eq!(deserialised_data, data);
     }
 }
@@ -17,7 +17,7 @@
 
 /// Representation of a File to be put into the network. Could be text, music, video etc any kind
 /// of file
-#[derive(RustcEncodable, RustcDecodable, PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[derive(RustcEncodable, PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub struct File {
     id      : ::routing::NameType,
     metadata: ::metadata::file_metadata::FileMetadata,
@@ -61,10 +61,50 @@ impl File {
         &self.datamap
     }
 
+    /// Get the logical size of the File, which is the size seen by readers. This can be larger than
+    /// the size of the content held by the data-map when the File ends in a hole, in which case
+    /// the hole reads as zeros
+    pub fn logical_size(&self) -> u64 {
+        ::std::cmp::max(self.metadata.get_size(), self.datamap.len())
+    }
+
+    /// Returns true if the content of the File is held inline in its data-map rather than in chunks
+    pub fn is_inline(&self) -> bool {
+        match self.datamap {
+            ::self_encryption::datamap::DataMap::Content(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns true if the File has no content stored, i.e. its data-map is empty
+    pub fn is_empty(&self) -> bool {
+        match self.datamap {
+            ::self_encryption::datamap::DataMap::None => true,
+            _ => false,
+        }
+    }
+
     /// Set a data-map to be associated with the File
     pub fn set_datamap(&mut self, datamap: ::self_encryption::datamap::DataMap) {
         self.datamap = datamap;
     }
+
+    /// Decodes a File serialised in the layout of the given schema version of DirectoryListing
+    pub fn decode_with_schema<D: ::rustc_serialize::Decoder>(d: &mut D, schema_version: u16) -> Result<File, D::Error> {
+        d.read_struct("File", 3, |d| {
+            Ok(File {
+                id      : try!(d.read_struct_field("id",       0, |d| ::rustc_serialize::Decodable::decode(d))),
+                metadata: try!(d.read_struct_field("metadata", 1, |d| ::metadata::file_metadata::FileMetadata::decode_with_schema(d, schema_version))),
+                datamap : try!(d.read_struct_field("datamap",  2, |d| ::rustc_serialize::Decodable::decode(d))),
+            })
+        })
+    }
+}
+
+impl ::rustc_serialize::Decodable for File {
+    fn decode<D: ::rustc_serialize::Decoder>(d: &mut D) -> Result<File, D::Error> {
+        File::decode_with_schema(d, ::directory_listing::DIRECTORY_LISTING_SCHEMA_VERSION)
+    }
 }
 
 impl ::std::fmt::Debug for File {
@@ -86,4 +126,29 @@ mod test {
         let obj_after = eval_result!(::safe_core::utility::deserialise(&serialised_data));
         assert_eq!(obj_before, obj_after);
     }
+
+    #[test]
+    fn logical_size() {
+        let mut file = eval_result!(File::new(::metadata::file_metadata::FileMetadata::new("sparse.txt".to_string(), Vec::new()),
+                                              ::self_encryption::datamap::DataMap::Content(vec![1u8; 10])));
+        assert_eq!(file.logical_size(), 10);
+        file.get_mut_metadata().set_size(100);
+        assert_eq!(file.logical_size(), 100);
+    }
+
+    #[test]
+    fn storage_layout() {
+        let mut file = eval_result!(File::new(::metadata::file_metadata::FileMetadata::new("layout.txt".to_string(), Vec::new()),
+                                              ::self_encryption::datamap::DataMap::None));
+        assert!(file.is_empty());
+        assert!(!file.is_inline());
+
+        file.set_datamap(::self_encryption::datamap::DataMap::Content(vec![1u8; 10]));
+        assert!(!file.is_empty());
+        assert!(file.is_inline());
+
+        file.set_datamap(::self_encryption::datamap::DataMap::Chunks(Vec::new()));
+        assert!(!file.is_empty());
+        assert!(!file.is_inline());
+    }
 }
@@ -0,0 +1,139 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+/// Maximum number of bytes of content a BufferedFile can hold
+pub const MAX_BUFFERED_FILE_SIZE: usize = 1024 * 1024;
+
+/// BufferedFile holds the content of a small file in memory until it is committed to a directory, so that many small
+/// files can be prepared without keeping a Writer, and its self-encryptor, for each of them. The content is only
+/// self-encrypted and saved in the network by `commit`.
+pub struct BufferedFile {
+    client       : ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
+    shared_key   : Option<::sodiumoxide::crypto::secretbox::Key>,
+    storage      : ::std::sync::Arc<::helper::chunk_storage::ChunkStorage>,
+    read_only    : bool,
+    name         : String,
+    user_metadata: Vec<u8>,
+    content      : Vec<u8>,
+}
+
+impl BufferedFile {
+    /// Create a new, empty, BufferedFile
+    pub fn new(client       : ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
+               name         : String,
+               user_metadata: Vec<u8>) -> BufferedFile {
+        BufferedFile {
+            client       : client.clone(),
+            shared_key   : None,
+            storage      : ::std::sync::Arc::new(::helper::chunk_storage::NetworkChunkStorage::new(client)),
+            read_only    : false,
+            name         : name,
+            user_metadata: user_metadata,
+            content      : Vec::new(),
+        }
+    }
+
+    /// Sets the shared key used to update the directory the file is committed to when it is a Shared directory
+    pub fn set_shared_key(&mut self, shared_key: Option<::sodiumoxide::crypto::secretbox::Key>) {
+        self.shared_key = shared_key;
+    }
+
+    /// Sets the storage the chunks of the content are saved in when the file is committed, see `FileHelper::with_storage`
+    pub fn set_storage(&mut self, storage: ::std::sync::Arc<::helper::chunk_storage::ChunkStorage>) {
+        self.storage = storage;
+    }
+
+    /// Sets whether the file is committed through a helper in read-only mode, in which case `commit` returns
+    /// NfsError::ReadOnly
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    /// Returns the name of the file
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    /// Returns the content buffered so far
+    pub fn get_content(&self) -> &Vec<u8> {
+        &self.content
+    }
+
+    /// Appends data to the content of the file.
+    /// Returns NfsError::BufferedFileTooLarge, leaving the content unchanged, if the content would grow beyond
+    /// MAX_BUFFERED_FILE_SIZE
+    pub fn write(&mut self, data: &[u8]) -> Result<(), ::errors::NfsError> {
+        if self.content.len() + data.len() > MAX_BUFFERED_FILE_SIZE {
+            return Err(::errors::NfsError::BufferedFileTooLarge);
+        }
+        self.content.extend(data.iter().cloned());
+        Ok(())
+    }
+
+    /// Self-encrypts the content and saves the file in the directory_listing, which is updated in the network once.
+    /// Returns NfsError::FileAlreadyExistsWithSameName if the directory_listing already has a file with the name.
+    /// Returns the updated DirectoryListing
+    pub fn commit(self,
+                  directory_listing: ::directory_listing::DirectoryListing) -> Result<::directory_listing::DirectoryListing, ::errors::NfsError> {
+        let file_helper = ::helper::file_helper::FileHelper::from_parts(self.client.clone(), self.shared_key, self.read_only)
+            .with_chunk_storage(self.storage);
+        debug!("Committing buffered file {:?} of {:?} bytes ...", self.name, self.content.len());
+        let mut writer = try!(file_helper.create(self.name, self.user_metadata, directory_listing));
+        writer.write(&self.content, 0);
+        let (updated_directory_listing, _) = try!(writer.close());
+        Ok(updated_directory_listing)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn write_and_commit() {
+        let test_client = eval_result!(::safe_core::utility::test_utils::get_client());
+        let client = ::std::sync::Arc::new(::std::sync::Mutex::new(test_client));
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let mut buffered_file = BufferedFile::new(client.clone(), "small.txt".to_string(), Vec::new());
+        eval_result!(buffered_file.write(b"Hello "));
+        eval_result!(buffered_file.write(b"World!"));
+        match buffered_file.write(&vec![0u8; MAX_BUFFERED_FILE_SIZE]) {
+            Err(::errors::NfsError::BufferedFileTooLarge) => (),
+            _ => panic!("Expected BufferedFileTooLarge"),
+        }
+        assert_eq!(*buffered_file.get_content(), b"Hello World!".to_vec());
+
+        let directory = eval_result!(buffered_file.commit(directory));
+        assert_eq!(eval_result!(dir_helper.get(directory.get_key())), directory);
+        let file = eval_option!(directory.find_file(&"small.txt".to_string()), "File not found");
+        let mut reader = ::helper::reader::Reader::new(client.clone(), file);
+        let size = reader.size();
+        assert_eq!(eval_result!(reader.read(0, size)), b"Hello World!".to_vec());
+
+        let buffered_file = BufferedFile::new(client.clone(), "small.txt".to_string(), Vec::new());
+        match buffered_file.commit(directory) {
+            Err(::errors::NfsError::FileAlreadyExistsWithSameName) => (),
+            _ => panic!("Expected FileAlreadyExistsWithSameName"),
+        }
+    }
+}
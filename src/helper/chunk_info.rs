@@ -0,0 +1,66 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+/// Location and size of a chunk holding a part of the content of a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkInfo {
+    index : usize,
+    name  : ::routing::NameType,
+    size  : u64,
+    offset: u64,
+}
+
+impl ChunkInfo {
+    /// Create a new instance of ChunkInfo
+    pub fn new(index: usize, name: ::routing::NameType, size: u64, offset: u64) -> ChunkInfo {
+        ChunkInfo {
+            index : index,
+            name  : name,
+            size  : size,
+            offset: offset,
+        }
+    }
+
+    /// Get the position of the chunk among the chunks of the file
+    pub fn get_index(&self) -> usize {
+        self.index
+    }
+
+    /// Get the name of the ImmutableData holding the chunk
+    pub fn get_name(&self) -> &::routing::NameType {
+        &self.name
+    }
+
+    /// Get the number of bytes of the file content held by the chunk
+    pub fn get_size(&self) -> u64 {
+        self.size
+    }
+
+    /// Get the offset of the chunk in the file content
+    pub fn get_offset(&self) -> u64 {
+        self.offset
+    }
+}
+
+/// Returns the name of the ImmutableData holding a self-encrypted chunk
+pub fn chunk_name(hash: &[u8]) -> ::routing::NameType {
+    let mut name = [0u8; 64];
+    for (dst, src) in name.iter_mut().zip(hash) {
+        *dst = *src;
+    }
+    ::routing::NameType::new(name)
+}
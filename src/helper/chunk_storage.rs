@@ -0,0 +1,154 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+/// Storage of the self-encrypted chunks of file contents. The chunks of the files read and written through a FileHelper
+/// are kept in its ChunkStorage, which is the network by default, see `FileHelper::with_storage`
+pub trait ChunkStorage: Send + Sync {
+    /// Returns the content of the chunk with the given name, or an empty content if the chunk is not held
+    fn get(&self, name: Vec<u8>) -> Vec<u8>;
+    /// Returns the content of the chunk with the given name in the same way as `get`, waiting for it for up to the
+    /// timeout. Storages which never wait for a chunk read it through `get`.
+    /// Returns NfsError::Timeout if the chunk could not be read within the timeout
+    fn get_with_timeout(&self, name: Vec<u8>, _timeout: ::std::time::Duration) -> Result<Vec<u8>, ::errors::NfsError> {
+        Ok(self.get(name))
+    }
+    /// Stores the content of a chunk under the given name
+    fn put(&self, name: Vec<u8>, data: Vec<u8>);
+    /// Releases one reference to the chunk with the given name, as taken by storing it through `put`. The chunk is only
+    /// removed once no reference to it is left; a storage which can not tell that keeps the chunk. Releasing a chunk
+    /// which is not held does nothing
+    fn delete(&self, name: Vec<u8>);
+}
+
+/// ChunkStorage keeping the chunks in the network through a client
+pub struct NetworkChunkStorage {
+    client : ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
+    storage: ::std::sync::Arc<::safe_core::SelfEncryptionStorage>,
+}
+
+impl NetworkChunkStorage {
+    /// Create a new instance of NetworkChunkStorage
+    pub fn new(client: ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>) -> NetworkChunkStorage {
+        NetworkChunkStorage {
+            client : client.clone(),
+            storage: ::safe_core::SelfEncryptionStorage::new(client),
+        }
+    }
+}
+
+impl ChunkStorage for NetworkChunkStorage {
+    fn get(&self, name: Vec<u8>) -> Vec<u8> {
+        ::self_encryption::Storage::get(&*self.storage, name)
+    }
+
+    fn get_with_timeout(&self, name: Vec<u8>, timeout: ::std::time::Duration) -> Result<Vec<u8>, ::errors::NfsError> {
+        let request = ::routing::data::DataRequest::ImmutableData(::helper::chunk_info::chunk_name(&name),
+                                                                  ::routing::immutable_data::ImmutableDataType::Normal);
+        debug!("Getting chunk from the network ...");
+        let response_getter = eval_result!(self.client.lock()).get(request, None);
+        match try!(::helper::timeout::get_response(response_getter, Some(timeout))) {
+            ::routing::data::Data::ImmutableData(immutable_data) => Ok(immutable_data.value().clone()),
+            _ => Err(::errors::NfsError::from(::safe_core::errors::CoreError::ReceivedUnexpectedData)),
+        }
+    }
+
+    fn put(&self, name: Vec<u8>, data: Vec<u8>) {
+        ::self_encryption::Storage::put(&*self.storage, name, data)
+    }
+
+    fn delete(&self, _name: Vec<u8>) {
+        // Chunks are convergently encrypted, so the same chunk may back files of other directories, older versions of
+        // directories or the data of other users, none of which can be seen from here. The chunk is always kept
+        debug!("Keeping network chunk, which may still be referenced elsewhere ...");
+    }
+}
+
+/// Storage for a self-encryptor which keeps the chunks in a ChunkStorage
+pub struct SelfEncryptionChunkStorage {
+    storage: ::std::sync::Arc<ChunkStorage>,
+}
+
+impl SelfEncryptionChunkStorage {
+    /// Create a new instance of SelfEncryptionChunkStorage keeping the chunks in the storage passed
+    pub fn new(storage: ::std::sync::Arc<ChunkStorage>) -> ::std::sync::Arc<SelfEncryptionChunkStorage> {
+        ::std::sync::Arc::new(SelfEncryptionChunkStorage {
+            storage: storage,
+        })
+    }
+}
+
+impl ::self_encryption::Storage for SelfEncryptionChunkStorage {
+    fn get(&self, name: Vec<u8>) -> Vec<u8> {
+        self.storage.get(name)
+    }
+
+    fn put(&self, name: Vec<u8>, data: Vec<u8>) {
+        self.storage.put(name, data)
+    }
+}
+
+/// ChunkStorage keeping the chunks in memory, e.g. for tests or as a local cache. Each chunk is held along with the
+/// number of times it was stored, and is removed once it has been deleted as many times
+pub struct MemoryChunkStorage {
+    chunks: ::std::sync::Mutex<::std::collections::HashMap<Vec<u8>, (usize, Vec<u8>)>>,
+}
+
+impl MemoryChunkStorage {
+    /// Create a new instance of MemoryChunkStorage holding no chunks
+    pub fn new() -> MemoryChunkStorage {
+        MemoryChunkStorage {
+            chunks: ::std::sync::Mutex::new(::std::collections::HashMap::new()),
+        }
+    }
+
+    /// Returns the number of chunks held
+    pub fn len(&self) -> usize {
+        eval_result!(self.chunks.lock()).len()
+    }
+
+    /// Returns true if no chunks are held
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl ChunkStorage for MemoryChunkStorage {
+    fn get(&self, name: Vec<u8>) -> Vec<u8> {
+        eval_result!(self.chunks.lock()).get(&name).map(|&(_, ref data)| data.clone()).unwrap_or(Vec::new())
+    }
+
+    fn put(&self, name: Vec<u8>, data: Vec<u8>) {
+        let mut chunks = eval_result!(self.chunks.lock());
+        let entry = chunks.entry(name).or_insert((0, Vec::new()));
+        entry.0 += 1;
+        entry.1 = data;
+    }
+
+    fn delete(&self, name: Vec<u8>) {
+        let mut chunks = eval_result!(self.chunks.lock());
+        let released = match chunks.get_mut(&name) {
+            Some(&mut (ref mut references, _)) => {
+                *references -= 1;
+                *references == 0
+            },
+            None => false,
+        };
+        if released {
+            let _ = chunks.remove(&name);
+        }
+    }
+}
@@ -15,22 +15,110 @@
 // Please review the Licences for the specific language governing permissions and limitations
 // relating to use of the SAFE Network Software.
 
+/// Number of bytes of file content read from the network at a time while streaming
+const STREAM_BUFFER_SIZE: u64 = 1024 * 1024;
+/// Maximum number of files whose content is uploaded concurrently by `create_with_files`
+pub const MAX_PARALLEL_UPLOADS: usize = 8;
+/// Maximum number of sub directories fetched concurrently by `get_with_children`
+pub const MAX_PARALLEL_FETCHES: usize = 8;
+/// Number of seconds by which the time of creation passed to `create_with_time` may be ahead of the current time, to
+/// allow for clock skew between clients
+pub const MAX_CREATED_TIME_AHEAD_SECONDS: i64 = 24 * 60 * 60;
+
 /// DirectoryHelper provides helper functions to perform Operations on Directory
+#[derive(Clone)]
 pub struct DirectoryHelper {
-    client: ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
+    client    : ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
+    shared_key: Option<::sodiumoxide::crypto::secretbox::Key>,
+    storage   : ::std::sync::Arc<::helper::chunk_storage::ChunkStorage>,
+    read_only : bool,
+    timeout   : Option<::std::time::Duration>,
+}
+
+/// Options of the directory created by `DirectoryHelper::create_with_files`
+#[derive(Debug, Clone)]
+pub struct CreateWithFilesOptions {
+    tag_type      : u64,
+    versioned     : bool,
+    access_level  : ::AccessLevel,
+    all_or_nothing: bool,
+}
+
+impl CreateWithFilesOptions {
+    /// Create a new instance of CreateWithFilesOptions for a directory with the tag type, versioning and access level
+    /// passed. The files which fail to be uploaded are reported and the directory is created with the others
+    pub fn new(tag_type: u64, versioned: bool, access_level: ::AccessLevel) -> CreateWithFilesOptions {
+        CreateWithFilesOptions {
+            tag_type      : tag_type,
+            versioned     : versioned,
+            access_level  : access_level,
+            all_or_nothing: false,
+        }
+    }
+
+    /// Sets whether the directory is not created when any file fails, in which case the error of the first failing file
+    /// is returned instead
+    pub fn with_all_or_nothing(mut self, all_or_nothing: bool) -> CreateWithFilesOptions {
+        self.all_or_nothing = all_or_nothing;
+        self
+    }
+}
+
+/// A directory shared with the user, as saved in the registry kept by `add_shared_directory`
+#[derive(RustcEncodable, RustcDecodable)]
+struct SharedDirectory {
+    name: String,
+    key : ::metadata::directory_key::DirectoryKey,
 }
 
 impl DirectoryHelper {
     /// Create a new DirectoryHelper instance
     pub fn new(client: ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>) -> DirectoryHelper {
+        DirectoryHelper::from_parts(client, None, false)
+    }
+
+    /// Create a new DirectoryHelper instance which reads and writes Shared directories with the shared_key passed
+    pub fn with_shared_key(client    : ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
+                           shared_key: ::sodiumoxide::crypto::secretbox::Key) -> DirectoryHelper {
+        DirectoryHelper::from_parts(client, Some(shared_key), false)
+    }
+
+    /// Create a new DirectoryHelper instance with the shared key, if any, and the read-only mode passed, e.g. to carry
+    /// those of another helper, see `with_shared_key` and `with_read_only`
+    pub fn from_parts(client    : ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
+                      shared_key: Option<::sodiumoxide::crypto::secretbox::Key>,
+                      read_only : bool) -> DirectoryHelper {
         DirectoryHelper {
-            client: client,
+            client    : client.clone(),
+            shared_key: shared_key,
+            storage   : ::std::sync::Arc::new(::helper::chunk_storage::NetworkChunkStorage::new(client)),
+            read_only : read_only,
+            timeout   : None,
         }
     }
 
+    /// Sets the storage through which the chunks of file contents are read and written by the DirectoryHelper, e.g. when
+    /// files are uploaded by `create_with_files` or exported by `export_tar`, instead of the network, see
+    /// `FileHelper::with_storage`. Directories are still saved in the network through the client.
+    pub fn with_chunk_storage(mut self, storage: ::std::sync::Arc<::helper::chunk_storage::ChunkStorage>) -> DirectoryHelper {
+        self.storage = storage;
+        self
+    }
+
+    /// Sets whether the DirectoryHelper is in read-only mode, e.g. for an anonymous session or a directory shared for
+    /// reading only. In read-only mode every operation which would write to the network, including the creation of the
+    /// root and configuration directories on their first access, returns NfsError::ReadOnly without writing anything.
+    pub fn with_read_only(mut self, read_only: bool) -> DirectoryHelper {
+        self.read_only = read_only;
+        self
+    }
+
     /// Creates a Directory in the network.
     /// When a directory is created and parent_directory is passed as a parameter. Then the parent directory is updated.
+    /// The parent_directory passed is updated in place to hold the created directory, so it reflects the saved state of the
+    /// parent without having to fetch it again from the network.
     /// If the parent_directory passed has a parent, then the parent_directory's parent is also updated and the same is returned
+    /// Returns NfsError::NameIsEmpty or NfsError::InvalidName if the directory_name can not be addressed by path.
     /// Returns (created_directory, Option<parent_directory's parent>)
     pub fn create(&self,
                   directory_name  : String,
@@ -40,29 +128,271 @@ impl DirectoryHelper {
                   access_level    : ::AccessLevel,
                   parent_directory: Option<&mut ::directory_listing::DirectoryListing>) -> Result<(::directory_listing::DirectoryListing,
                                                                                                    Option<::directory_listing::DirectoryListing>), ::errors::NfsError> {
+        try!(self.check_writable());
+        self.create_with_time(directory_name, tag_type, user_metadata, versioned, access_level, parent_directory, ::time::now_utc())
+    }
+
+    /// Creates a Directory in the network under the parent_directory, in the same way as `create`, named base_name or,
+    /// if the name is taken by an entry of the parent_directory, base_name followed by the first of ` (1)`, ` (2)`, etc.
+    /// which makes the name free. The parent_directory is updated in place.
+    /// Returns the created DirectoryListing
+    pub fn create_unique(&self,
+                         base_name       : &str,
+                         tag_type        : u64,
+                         user_metadata   : Vec<u8>,
+                         versioned       : bool,
+                         access_level    : ::AccessLevel,
+                         parent_directory: &mut ::directory_listing::DirectoryListing) -> Result<::directory_listing::DirectoryListing, ::errors::NfsError> {
+        try!(self.check_writable());
+        let directory_name = parent_directory.unique_name(base_name);
+        debug!("Creating directory {:?} ...", directory_name);
+        let (directory, _) = try!(self.create(directory_name, tag_type, user_metadata, versioned, access_level, Some(parent_directory)));
+        Ok(directory)
+    }
+
+    /// Creates an unversioned public Directory in the network, in the same way as `create`. The parent_directory, if
+    /// passed, is updated in place.
+    /// Returns NfsError::AccessLevelMismatch if the parent_directory is not public, as the contents of a public directory
+    /// are meant to be reachable by following the public directories leading to it.
+    /// Returns the created DirectoryListing
+    pub fn create_public(&self,
+                         directory_name  : String,
+                         user_metadata   : Vec<u8>,
+                         parent_directory: Option<&mut ::directory_listing::DirectoryListing>) -> Result<::directory_listing::DirectoryListing, ::errors::NfsError> {
+        try!(self.check_writable());
+        if parent_directory.iter().any(|dir| *dir.get_key().get_access_level() != ::AccessLevel::Public) {
+            return Err(::errors::NfsError::AccessLevelMismatch);
+        }
+        let (directory, _) = try!(self.create(directory_name,
+                                              ::UNVERSIONED_DIRECTORY_LISTING_TAG,
+                                              user_metadata,
+                                              false,
+                                              ::AccessLevel::Public,
+                                              parent_directory));
+        Ok(directory)
+    }
+
+    /// Creates a Directory in the network under the parent_directory, in the same way as `create`, inheriting the access
+    /// level and whether it is versioned from the parent_directory unless they are overridden by passing access_level or
+    /// versioned, e.g. so that a directory created under a private tree is private too. The type tag is
+    /// VERSIONED_DIRECTORY_LISTING_TAG or UNVERSIONED_DIRECTORY_LISTING_TAG accordingly. The parent_directory is updated
+    /// in place.
+    /// Returns the created DirectoryListing
+    pub fn create_inheriting(&self,
+                             directory_name  : String,
+                             user_metadata   : Vec<u8>,
+                             versioned       : Option<bool>,
+                             access_level    : Option<::AccessLevel>,
+                             parent_directory: &mut ::directory_listing::DirectoryListing) -> Result<::directory_listing::DirectoryListing, ::errors::NfsError> {
+        try!(self.check_writable());
+        let versioned = versioned.unwrap_or(parent_directory.get_key().is_versioned());
+        let access_level = access_level.unwrap_or(parent_directory.get_key().get_access_level().clone());
+        let tag_type = if versioned {
+            ::VERSIONED_DIRECTORY_LISTING_TAG
+        } else {
+            ::UNVERSIONED_DIRECTORY_LISTING_TAG
+        };
+        let (directory, _) = try!(self.create(directory_name, tag_type, user_metadata, versioned, access_level, Some(parent_directory)));
+        Ok(directory)
+    }
 
+    /// Creates a Directory in the network in the same way as `create`, with created_time as its time of creation and
+    /// modification instead of the current time, e.g. to preserve the dates of a directory restored from a backup.
+    /// Returns NfsError::ParameterIsNotValid if created_time is before the Unix epoch or more than
+    /// MAX_CREATED_TIME_AHEAD_SECONDS ahead of the current time
+    pub fn create_with_time(&self,
+                            directory_name  : String,
+                            tag_type        : u64,
+                            user_metadata   : Vec<u8>,
+                            versioned       : bool,
+                            access_level    : ::AccessLevel,
+                            parent_directory: Option<&mut ::directory_listing::DirectoryListing>,
+                            created_time    : ::time::Tm) -> Result<(::directory_listing::DirectoryListing,
+                                                                     Option<::directory_listing::DirectoryListing>), ::errors::NfsError> {
+        try!(self.check_writable());
+        let created_seconds = created_time.to_timespec().sec;
+        if created_seconds < 0 || created_seconds > ::time::get_time().sec + MAX_CREATED_TIME_AHEAD_SECONDS {
+            return Err(::errors::NfsError::ParameterIsNotValid);
+        }
+        try!(::directory_listing::DirectoryListing::validate_name(&directory_name));
         if parent_directory.iter().next().and_then(|dir| dir.find_sub_directory(&directory_name)).is_some() {
              return Err(::errors::NfsError::DirectoryAlreadyExistsWithSameName);
         }
 
-        let directory = try!(::directory_listing::DirectoryListing::new(directory_name,
-                                                                        tag_type,
-                                                                        user_metadata,
-                                                                        versioned,
-                                                                        access_level,
-                                                                        parent_directory.iter().next().map(|directory| {
-                                                                            directory.get_key().clone()
-                                                                        })));
+        let mut directory = try!(::directory_listing::DirectoryListing::new(directory_name,
+                                                                            tag_type,
+                                                                            user_metadata,
+                                                                            versioned,
+                                                                            access_level,
+                                                                            parent_directory.iter().next().map(|directory| {
+                                                                                directory.get_key().clone()
+                                                                            })));
+        directory.get_mut_metadata().set_created_time(created_time.clone());
+        directory.get_mut_metadata().set_modified_time(created_time);
+        self.put_directory(directory, parent_directory)
+    }
 
-        let structured_data = try!(self.save_directory_listing(&directory));
-        debug!("Posting PUT request to network to save structured data for directory ...");
-        try!(eval_result!(self.client.lock()).put(::routing::data::Data::StructuredData(structured_data), None));
-        if let Some(mut parent_directory) = parent_directory {
-            parent_directory.upsert_sub_directory(directory.get_metadata().clone());
-            Ok((directory, try!(self.update(parent_directory))))
-        } else {
-            Ok((directory, None))
+    /// Saves a DirectoryListing assembled offline, along with its files and sub directories, as a new directory in the
+    /// network, so that a whole structure can be imported without saving the directory once for each entry added.
+    /// The listing, along with the names of its entries, is validated first and must not already be saved in the network.
+    /// If a parent_directory is passed, the listing is made its child and the parent_directory is updated in place.
+    /// Returns the saved DirectoryListing
+    pub fn put_listing(&self,
+                       mut listing     : ::directory_listing::DirectoryListing,
+                       parent_directory: Option<&mut ::directory_listing::DirectoryListing>) -> Result<::directory_listing::DirectoryListing, ::errors::NfsError> {
+        try!(self.check_writable());
+        try!(::directory_listing::DirectoryListing::validate_name(listing.get_metadata().get_name()));
+        try!(listing.validate_names());
+        try!(listing.validate());
+        if parent_directory.iter().next().and_then(|dir| dir.find_sub_directory(listing.get_metadata().get_name())).is_some() {
+             return Err(::errors::NfsError::DirectoryAlreadyExistsWithSameName);
+        }
+        let parent_dir_key = parent_directory.iter().next().map(|directory| directory.get_key().clone());
+        listing.get_mut_metadata().set_parent_dir_key(parent_dir_key);
+        debug!("Saving directory listing assembled offline ...");
+        let (directory, _) = try!(self.put_directory(listing, parent_directory));
+        Ok(directory)
+    }
+
+    /// Creates a Directory in the network holding the files passed, as (file name, content) pairs, with the tag type,
+    /// versioning and access level set in the options.
+    /// The content of the files is self-encrypted and uploaded concurrently, by up to MAX_PARALLEL_UPLOADS files at a
+    /// time, and the directory is saved once all the uploads are done, with all the files in it. The new directory
+    /// therefore starts with a single version instead of one for each file added.
+    /// A file which fails to be uploaded, or whose name is already taken by an earlier file of the batch, is left out of
+    /// the directory and reported along with its error, unless all_or_nothing is set in the options, see
+    /// `CreateWithFilesOptions::with_all_or_nothing`.
+    /// The parent_directory is updated in place in the same way as by `create`.
+    /// Returns (created_directory, Vec<(name of the failed file, error)>)
+    pub fn create_with_files(&self,
+                             directory_name  : String,
+                             options         : CreateWithFilesOptions,
+                             parent_directory: Option<&mut ::directory_listing::DirectoryListing>,
+                             files           : Vec<(String, Vec<u8>)>) -> Result<(::directory_listing::DirectoryListing,
+                                                                                  Vec<(String, ::errors::NfsError)>), ::errors::NfsError> {
+        try!(self.check_writable());
+        if parent_directory.iter().next().and_then(|dir| dir.find_sub_directory(&directory_name)).is_some() {
+             return Err(::errors::NfsError::DirectoryAlreadyExistsWithSameName);
+        }
+
+        let mut failures = Vec::new();
+        let mut unique_files = Vec::with_capacity(files.len());
+        for (file_name, content) in files {
+            if unique_files.iter().any(|&(ref name, _)| *name == file_name) {
+                failures.push((file_name, ::errors::NfsError::FileAlreadyExistsWithSameName));
+            } else {
+                unique_files.push((file_name, content));
+            }
+        }
+
+        let file_names: Vec<String> = unique_files.iter().map(|&(ref file_name, _)| file_name.clone()).collect();
+        let storage = self.storage.clone();
+        let uploads = run_batched(unique_files, MAX_PARALLEL_UPLOADS, move |(file_name, content)| {
+            debug!("Uploading content of {:?} for the new directory ...", file_name);
+            upload_file(storage.clone(), file_name, content)
+        });
+        let mut uploaded_files = Vec::with_capacity(uploads.len());
+        for (file_name, upload) in file_names.into_iter().zip(uploads) {
+            match upload {
+                Ok(file) => uploaded_files.push(file),
+                Err(error) => failures.push((file_name, error)),
+            }
+        }
+        if options.all_or_nothing && !failures.is_empty() {
+            return Err(failures.swap_remove(0).1);
+        }
+
+        let mut directory = try!(::directory_listing::DirectoryListing::new(directory_name,
+                                                                            options.tag_type,
+                                                                            Vec::new(),
+                                                                            options.versioned,
+                                                                            options.access_level,
+                                                                            parent_directory.iter().next().map(|directory| {
+                                                                                directory.get_key().clone()
+                                                                            })));
+        for file in uploaded_files {
+            directory.upsert_file(file);
+        }
+        let (directory, _) = try!(self.put_directory(directory, parent_directory));
+        Ok((directory, failures))
+    }
+
+    /// Creates a Directory in the network under the specified DirectoryKey rather than under a freshly generated one.
+    /// This is meant for restoring a directory whose key was recorded, but whose StructuredData has been lost.
+    /// If a StructuredData owned by the client already exists under the key, the existing directory is returned instead,
+    /// so that a restore can safely be repeated. NfsError::ParameterIsNotValid is returned if the existing StructuredData
+    /// is owned by someone else. The directory is only created if the network reports that it holds no data under the
+    /// key; any other failure to fetch the StructuredData is returned as it is, see `get`.
+    /// Returns (created_directory, Option<parent_directory's parent>)
+    pub fn create_with_key(&self,
+                           directory_key   : ::metadata::directory_key::DirectoryKey,
+                           directory_name  : String,
+                           user_metadata   : Vec<u8>,
+                           parent_directory: Option<&mut ::directory_listing::DirectoryListing>) -> Result<(::directory_listing::DirectoryListing,
+                                                                                                            Option<::directory_listing::DirectoryListing>), ::errors::NfsError> {
+        try!(self.check_writable());
+        let existing_structured_data = match self.get_structured_data(directory_key.get_id(), directory_key.get_type_tag()) {
+            Ok(structured_data) => Some(structured_data),
+            Err(error) => match directory_not_found(error, &directory_key) {
+                ::errors::NfsError::DirectoryNotFound(_) => None,
+                error => return Err(error),
+            },
+        };
+        if let Some(structured_data) = existing_structured_data {
+            let owner_key = try!(eval_result!(self.client.lock()).get_public_signing_key()).clone();
+            if !structured_data.get_owner_keys().contains(&owner_key) {
+                return Err(::errors::NfsError::ParameterIsNotValid);
+            }
+            debug!("Directory already exists under the key ...");
+            let directory = try!(self.get(&directory_key));
+            return if let Some(mut parent_directory) = parent_directory {
+                try!(parent_directory.upsert_sub_directory_checked(directory.aggregated_metadata(), false));
+                Ok((directory, try!(self.update(parent_directory))))
+            } else {
+                Ok((directory, None))
+            };
+        }
+
+        let parent_dir_key = parent_directory.iter().next().map(|directory| directory.get_key().clone());
+        let directory = ::directory_listing::DirectoryListing::new_with_key(directory_key, directory_name, user_metadata, parent_dir_key);
+        self.put_directory(directory, parent_directory)
+    }
+
+    /// Creates a tree of directories under the parent_directory.
+    /// Each entry of the layout is a path relative to the parent_directory, with its components separated by `/`.
+    /// The entries are created in order, so an entry can only be nested under directories that already exist or that
+    /// were created by an earlier entry of the layout.
+    /// If a directory can not be created, the directories already created by this call are removed from their parents in
+    /// reverse order on a best-effort basis, so that a half built tree is not left behind. Their StructuredData can not
+    /// be deleted from the network and is left unreferenced; NfsError::TreeCreationRolledBack is returned holding the
+    /// error along with the keys of those directories, e.g. for them to be reused.
+    /// Returns the keys of the created directories, in the order they were created
+    pub fn create_tree_transactional(&self,
+                                     parent_directory: &mut ::directory_listing::DirectoryListing,
+                                     layout          : &[&str],
+                                     tag_type        : u64,
+                                     versioned       : bool,
+                                     access_level    : ::AccessLevel) -> Result<Vec<::metadata::directory_key::DirectoryKey>, ::errors::NfsError> {
+        try!(self.check_writable());
+        let mut created_directories = Vec::new();
+        for path in layout {
+            if let Err(error) = self.create_tree_entry(parent_directory, path, tag_type, versioned, access_level.clone(), &mut created_directories) {
+                debug!("Rolling back {:?} directories created for the tree ...", created_directories.len());
+                for &(ref parent_dir_key, ref directory_name, _) in created_directories.iter().rev() {
+                    if let Ok(mut parent) = self.get(parent_dir_key) {
+                        let _ = self.delete(&mut parent, directory_name);
+                    }
+                }
+                if let Ok(refreshed_parent_directory) = self.get(parent_directory.get_key()) {
+                    *parent_directory = refreshed_parent_directory;
+                }
+                return Err(::errors::NfsError::TreeCreationRolledBack {
+                    error               : Box::new(error),
+                    unlinked_directories: created_directories.into_iter().map(|(_, _, directory_key)| directory_key).collect(),
+                });
+            }
         }
+        Ok(created_directories.into_iter().map(|(_, _, directory_key)| directory_key).collect())
     }
 
     /// Deletes a sub directory
@@ -71,19 +401,46 @@ impl DirectoryHelper {
     pub fn delete(&self,
                   parent_directory   : &mut ::directory_listing::DirectoryListing,
                   directory_to_delete: &String) -> Result<Option<::directory_listing::DirectoryListing>, ::errors::NfsError> {
+        try!(self.check_writable());
         try!(parent_directory.remove_sub_directory(directory_to_delete));
         parent_directory.get_mut_metadata().set_modified_time(::time::now_utc());
         self.update(&parent_directory)
     }
 
+    /// Removes all the files and sub directories of the directory and saves it in the network.
+    /// If recursive is set, each sub directory is emptied in the same way first, so that none of the sub-tree is left
+    /// referring to the removed entries. Otherwise the sub directories are only removed from the directory, as by `delete`.
+    /// The StructuredData of a directory can not be deleted from the network through the client, so the sub-trees are
+    /// emptied rather than deleted: each sub directory is saved once more without its entries, which adds a version to a
+    /// versioned one, and whoever still holds its key finds it empty. Earlier versions keep their entries.
+    /// The directory passed is updated in place.
+    /// Returns Option<directory's parent>
+    pub fn empty_directory(&self,
+                           directory: &mut ::directory_listing::DirectoryListing,
+                           recursive: bool) -> Result<Option<::directory_listing::DirectoryListing>, ::errors::NfsError> {
+        try!(self.check_writable());
+        if recursive {
+            for sub_directory in directory.get_sub_directories().clone() {
+                debug!("Emptying sub directory {:?} ...", sub_directory.get_name());
+                let mut sub_directory_listing = try!(self.get(sub_directory.get_key()));
+                let _ = try!(self.empty_directory(&mut sub_directory_listing, true));
+            }
+        }
+        directory.clear();
+        directory.get_mut_metadata().set_modified_time(::time::now_utc());
+        self.update(directory)
+    }
+
     /// Updates an existing DirectoryListing in the network.
-    /// The parent_directory's parent is also updated and the same is returned
+    /// The parent_directory's parent is also updated and the same is returned. The size and child count of the directory
+    /// are refreshed in its entry in the parent, see `DirectoryListing::aggregated_metadata`
     /// Returns Option<parent_directory's parent>
     pub fn update(&self, directory: &::directory_listing::DirectoryListing) -> Result<Option<::directory_listing::DirectoryListing>, ::errors::NfsError> {
+        try!(self.check_writable());
         try!(self.update_directory_listing(directory));
         if let Some(parent_dir_key) = directory.get_metadata().get_parent_dir_key() {
             let mut parent_directory = try!(self.get(&parent_dir_key));
-            parent_directory.upsert_sub_directory(directory.get_metadata().clone());
+            try!(parent_directory.upsert_sub_directory_checked(directory.aggregated_metadata(), false));
             try!(self.update_directory_listing(&parent_directory));
             Ok(Some(parent_directory))
         } else {
@@ -91,25 +448,240 @@ impl DirectoryHelper {
         }
     }
 
-    /// Return the versions of the directory
+    /// Updates an existing DirectoryListing in the network in the same way as `update`, also reporting whether the
+    /// directory was saved. A versioned directory which is the same as last_saved, the state of the directory the caller
+    /// last saved or fetched, is not saved again, so that no version is added for an update which changes nothing.
+    /// Nothing is fetched from the network to tell, and neither the directory nor its parent is touched when the update
+    /// is skipped. An unversioned directory, or one updated without a last_saved state, is always saved.
+    /// Returns (Option<parent_directory's parent>, true if the directory was saved); the parent is None when the
+    /// update is skipped
+    pub fn update_with_status(&self,
+                              directory : &::directory_listing::DirectoryListing,
+                              last_saved: Option<&::directory_listing::DirectoryListing>) -> Result<(Option<::directory_listing::DirectoryListing>, bool), ::errors::NfsError> {
+        try!(self.check_writable());
+        if directory.get_key().is_versioned() && last_saved.map_or(false, |last_saved| *last_saved == *directory) {
+            debug!("Directory is the same as its last saved state, skipping the update ...");
+            return Ok((None, false));
+        }
+        let parent_directory = try!(self.update(directory));
+        Ok((parent_directory, true))
+    }
+
+    /// Updates an existing DirectoryListing in the network along with up to `levels` of its ancestors.
+    /// Each ancestor is saved with the metadata of the updated child, so the metadata which a directory holds about its
+    /// sub directories stays consistent up the chain. With `levels` of 1 this is the same as `update`. The walk stops
+    /// early at a directory which has no parent.
+    pub fn update_propagating(&self,
+                              directory: &::directory_listing::DirectoryListing,
+                              levels   : usize) -> Result<(), ::errors::NfsError> {
+        try!(self.check_writable());
+        try!(self.update_directory_listing(directory));
+        let mut child_metadata = directory.aggregated_metadata();
+        for level in 0..levels {
+            let parent_dir_key = match child_metadata.get_parent_dir_key() {
+                Some(parent_dir_key) => parent_dir_key.clone(),
+                None => break,
+            };
+            debug!("Updating parent directory at level {:?} ...", level + 1);
+            let mut parent_directory = try!(self.get(&parent_dir_key));
+            try!(parent_directory.upsert_sub_directory_checked(child_metadata, false));
+            try!(self.update_directory_listing(&parent_directory));
+            child_metadata = parent_directory.aggregated_metadata();
+        }
+        Ok(())
+    }
+
+    /// Return the versions of the directory, from the oldest to the latest.
+    /// For n versions, the first version is at index 0 and the latest version at index n - 1.
     pub fn get_versions(&self, directory_id: &::routing::NameType, type_tag: u64) -> Result<Vec<::routing::NameType>, ::errors::NfsError> {
         let structured_data = try!(self.get_structured_data(directory_id, type_tag));
         Ok(try!(::safe_core::structured_data_operations::versioned::get_all_versions(&mut *eval_result!(self.client.lock()), &structured_data)))
     }
 
-    /// Return the DirectoryListing for the specified version
+    /// Return the versions of the directory, from the latest to the oldest, e.g. to list the recent changes first.
+    /// For n versions, the latest version is at index 0 and the first version at index n - 1.
+    pub fn get_versions_desc(&self, directory_id: &::routing::NameType, type_tag: u64) -> Result<Vec<::routing::NameType>, ::errors::NfsError> {
+        let mut versions = try!(self.get_versions(directory_id, type_tag));
+        versions.reverse();
+        Ok(versions)
+    }
+
+    /// Converts a versioned directory to an unversioned one, keeping only its latest state.
+    /// The converted directory keeps the id, access level and created and modified times of the directory, but is
+    /// stored under UNVERSIONED_DIRECTORY_LISTING_TAG, so it has a new DirectoryKey. The parent directory and the sub
+    /// directories are updated to refer to the new key. The StructuredData and the versions of the versioned directory
+    /// are left in the network and can be reclaimed separately, see `list_version_chunks` and `prune_versions`.
+    /// Returns the converted directory
+    pub fn convert_to_unversioned(&self,
+                                  directory: &::directory_listing::DirectoryListing) -> Result<::directory_listing::DirectoryListing, ::errors::NfsError> {
+        try!(self.check_writable());
+        if !directory.get_key().is_versioned() {
+            return Err(::errors::NfsError::ParameterIsNotValid);
+        }
+        let latest_directory = try!(self.get(directory.get_key()));
+        let mut directory_key = ::metadata::directory_key::DirectoryKey::new(latest_directory.get_key().get_id().clone(),
+                                                                             ::UNVERSIONED_DIRECTORY_LISTING_TAG,
+                                                                             false,
+                                                                             latest_directory.get_key().get_access_level().clone());
+        if let Some(nonce) = latest_directory.get_key().get_nonce() {
+            directory_key = directory_key.with_nonce(nonce);
+        }
+        let parent_dir_key = latest_directory.get_metadata().get_parent_dir_key().cloned();
+        let mut converted_directory = ::directory_listing::DirectoryListing::new_with_key(directory_key.clone(),
+                                                                                         latest_directory.get_metadata().get_name().clone(),
+                                                                                         latest_directory.get_metadata().get_user_metadata().clone(),
+                                                                                         parent_dir_key.clone());
+        converted_directory.get_mut_metadata().set_created_time(latest_directory.get_metadata().get_created_time().clone());
+        converted_directory.get_mut_metadata().set_modified_time(latest_directory.get_metadata().get_modified_time().clone());
+        *converted_directory.get_mut_files() = latest_directory.get_files().clone();
+        *converted_directory.get_mut_sub_directories() = latest_directory.get_sub_directories().clone();
+        debug!("Saving unversioned copy of versioned directory ...");
+        let _ = try!(self.put_directory(converted_directory, None));
+
+        for sub_directory in latest_directory.get_sub_directories() {
+            let mut sub_directory_listing = try!(self.get(sub_directory.get_key()));
+            sub_directory_listing.get_mut_metadata().set_parent_dir_key(Some(directory_key.clone()));
+            let _ = try!(self.update(&sub_directory_listing));
+        }
+        let converted_directory = try!(self.get(&directory_key));
+
+        if let Some(parent_dir_key) = parent_dir_key {
+            let mut parent_directory = try!(self.get(&parent_dir_key));
+            try!(parent_directory.upsert_sub_directory_checked(converted_directory.aggregated_metadata(), true));
+            let _ = try!(self.update(&parent_directory));
+        }
+        Ok(converted_directory)
+    }
+
+    /// Return an iterator over the versions of a versioned directory, from the latest to the oldest.
+    /// Each version can be resolved to its DirectoryListing through the iterator when needed.
+    pub fn versions_iter(&self,
+                         directory_key: &::metadata::directory_key::DirectoryKey) -> Result<::helper::version_iterator::VersionIterator, ::errors::NfsError> {
+        if !directory_key.is_versioned() {
+            return Err(::errors::NfsError::ParameterIsNotValid);
+        }
+        let versions = try!(self.get_versions(directory_key.get_id(), directory_key.get_type_tag()));
+        let mut version_iterator = ::helper::version_iterator::VersionIterator::new(self.client.clone(), directory_key.clone(), versions);
+        version_iterator.set_shared_key(self.shared_key.clone());
+        Ok(version_iterator)
+    }
+
+    /// Removes the specified versions from the version history of a versioned directory.
+    /// The latest version can not be pruned as it represents the current state of the directory.
+    /// Only the references to the versions are dropped, the ImmutableData holding the pruned
+    /// versions is left as it is in the network.
+    /// Returns NfsError::Unexpected if the pruned version history could not be saved in the network
+    pub fn prune_versions(&self,
+                          directory_key    : &::metadata::directory_key::DirectoryKey,
+                          versions_to_prune: &[::routing::NameType]) -> Result<(), ::errors::NfsError> {
+        try!(self.check_writable());
+        if !directory_key.is_versioned() {
+            return Err(::errors::NfsError::ParameterIsNotValid);
+        }
+        let structured_data = try!(self.get_structured_data(directory_key.get_id(), directory_key.get_type_tag()));
+        let versions = try!(::safe_core::structured_data_operations::versioned::get_all_versions(&mut *eval_result!(self.client.lock()), &structured_data));
+        let latest_version = *try!(versions.last().ok_or(::errors::NfsError::from("Programming Error - Please report this as a Bug.")));
+        if versions_to_prune.contains(&latest_version) {
+            return Err(::errors::NfsError::ParameterIsNotValid);
+        }
+        let retained_versions: Vec<::routing::NameType> = versions.into_iter().filter(|version| !versions_to_prune.contains(version)).collect();
+
+        let signing_key = try!(eval_result!(self.client.lock()).get_secret_signing_key()).clone();
+        let owner_key = try!(eval_result!(self.client.lock()).get_public_signing_key()).clone();
+        // Each appended version bumps the version of the StructuredData, so the StructuredData holding the first retained
+        // version is created at the version which leaves the one holding all of them at the next version of the stored one
+        let pruned_version = structured_data.get_version() + 1;
+        let mut pruned_structured_data = try!(::safe_core::structured_data_operations::versioned::create(& *eval_result!(self.client.lock()),
+                                                                                                        retained_versions[0],
+                                                                                                        directory_key.get_type_tag(),
+                                                                                                        directory_key.get_id().clone(),
+                                                                                                        pruned_version + 1 - retained_versions.len() as u64,
+                                                                                                        vec![owner_key],
+                                                                                                        Vec::new(),
+                                                                                                        &signing_key));
+        for version in retained_versions.iter().skip(1) {
+            pruned_structured_data = try!(::safe_core::structured_data_operations::versioned::append_version(&mut *eval_result!(self.client.lock()),
+                                                                                                             pruned_structured_data,
+                                                                                                             *version,
+                                                                                                             &signing_key));
+        }
+        if pruned_structured_data.get_version() != pruned_version {
+            return Err(::errors::NfsError::from("Programming Error - Please report this as a Bug."));
+        }
+        debug!("Posting pruned version history to the network ...");
+        eval_result!(self.client.lock()).post(::routing::data::Data::StructuredData(pruned_structured_data), None);
+        // The network does not report the outcome of a POST, so the saved history is read back to confirm it
+        if try!(self.get_versions(directory_key.get_id(), directory_key.get_type_tag())) != retained_versions {
+            return Err(::errors::NfsError::Unexpected("Pruned version history was not saved".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Returns the names of the ImmutableData referenced by the stored versions of a versioned directory.
+    /// This includes the ImmutableData holding each version and, for private directories, the self-encrypted chunks of
+    /// the version's content. It can be used to preview what `prune_versions` would leave unreferenced.
+    pub fn list_version_chunks(&self,
+                               directory_key: &::metadata::directory_key::DirectoryKey) -> Result<Vec<::routing::NameType>, ::errors::NfsError> {
+        let access_level = directory_key.get_access_level();
+        let mut names = Vec::new();
+        for version in try!(self.get_versions(directory_key.get_id(), directory_key.get_type_tag())) {
+            names.push(version);
+            if *access_level != ::AccessLevel::Public {
+                let immutable_data = try!(self.get_immutable_data(version, ::routing::immutable_data::ImmutableDataType::Normal));
+                let datamap = if let ::AccessLevel::Shared = *access_level {
+                    try!(::directory_listing::DirectoryListing::decrypt_datamap_with_shared_key(try!(self.get_shared_key()),
+                                                                                                immutable_data.value()))
+                } else {
+                    try!(::directory_listing::DirectoryListing::decrypt_datamap(self.client.clone(),
+                                                                                directory_key,
+                                                                                immutable_data.value()))
+                };
+                if let ::self_encryption::datamap::DataMap::Chunks(ref chunks) = datamap {
+                    for chunk in chunks {
+                        let name = ::helper::chunk_info::chunk_name(&chunk.hash);
+                        if !names.contains(&name) {
+                            names.push(name);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(names)
+    }
+
+    /// Return the DirectoryListing for the specified version of the directory with the given key
     pub fn get_by_version(&self,
-                          directory_id: &::routing::NameType,
-                          access_level: &::AccessLevel,
-                          version     : ::routing::NameType) -> Result<::directory_listing::DirectoryListing, ::errors::NfsError> {
-          let immutable_data = try!(self.get_immutable_data(version, ::routing::immutable_data::ImmutableDataType::Normal));
-          match *access_level {
-              ::AccessLevel::Private => ::directory_listing::DirectoryListing::decrypt(self.client.clone(), directory_id, immutable_data.value().clone()),
-              ::AccessLevel::Public  => Ok(try!(::safe_core::utility::deserialise(immutable_data.value()))),
-          }
+                          directory_key: &::metadata::directory_key::DirectoryKey,
+                          version      : ::routing::NameType) -> Result<::directory_listing::DirectoryListing, ::errors::NfsError> {
+        let immutable_data = try!(self.get_immutable_data(version, ::routing::immutable_data::ImmutableDataType::Normal));
+        match *directory_key.get_access_level() {
+            ::AccessLevel::Private => ::directory_listing::DirectoryListing::decrypt(self.client.clone(),
+                                                                                     directory_key,
+                                                                                     immutable_data.value().clone()),
+            ::AccessLevel::Public  => ::directory_listing::DirectoryListing::deserialise(immutable_data.value()),
+            ::AccessLevel::Shared  => ::directory_listing::DirectoryListing::decrypt_with_shared_key(self.client.clone(),
+                                                                                                     try!(self.get_shared_key()),
+                                                                                                     immutable_data.value()),
+        }
+    }
+
+    /// Return the DirectoryListing for the specified version, checking first that the version is one of known_versions, as
+    /// returned by an earlier call to `get_versions`. This avoids fetching the version list again when many versions are
+    /// visited and rejects an unknown version without going to the network.
+    /// Returns NfsError::NotFound if the version is not in known_versions
+    pub fn get_by_version_cached(&self,
+                                 directory_key : &::metadata::directory_key::DirectoryKey,
+                                 version       : ::routing::NameType,
+                                 known_versions: &[::routing::NameType]) -> Result<::directory_listing::DirectoryListing, ::errors::NfsError> {
+        if !known_versions.contains(&version) {
+            return Err(::errors::NfsError::NotFound);
+        }
+        self.get_by_version(directory_key, version)
     }
 
     /// Return the DirectoryListing for the latest version
+    /// Returns NfsError::DirectoryNotFound holding the key if the network holds no data for the directory; any other
+    /// failure to fetch the directory is returned as it is
     pub fn get(&self, directory_key: &::metadata::directory_key::DirectoryKey) -> Result<::directory_listing::DirectoryListing, ::errors::NfsError> {
         let directory_id = directory_key.get_id();
         let type_tag  = directory_key.get_type_tag();
@@ -117,9 +689,9 @@ impl DirectoryHelper {
         let access_level = directory_key.get_access_level();
 
         if versioned {
-           let versions = try!(self.get_versions(directory_id, type_tag));
-           let latest_version = try!(versions.last().ok_or(::errors::NfsError::from("Programming Error - Please report this as a Bug.")));
-           self.get_by_version(directory_id, access_level, *latest_version)
+           let versions = try!(self.get_versions(directory_id, type_tag).map_err(|error| directory_not_found(error, directory_key)));
+           let latest_version = try!(versions.last().ok_or(::errors::NfsError::DirectoryNotFound(Some(directory_key.clone()))));
+           self.get_by_version(directory_key, *latest_version)
         } else {
             let private_key;
             let secret_key;
@@ -129,21 +701,357 @@ impl DirectoryHelper {
                 ::AccessLevel::Private => {
                     private_key = try!(eval_result!(self.client.lock()).get_public_encryption_key()).clone();
                     secret_key = try!(eval_result!(self.client.lock()).get_secret_encryption_key()).clone();
-                    nonce = ::directory_listing::DirectoryListing::generate_nonce(directory_id);
+                    nonce = directory_key.get_encryption_nonce();
 
                     Some((&private_key,
                          &secret_key,
                          &nonce))
                 },
-                ::AccessLevel::Public => None,
+                ::AccessLevel::Public | ::AccessLevel::Shared => None,
+            };
+
+            let structured_data = try!(self.get_structured_data(directory_id, type_tag).map_err(|error| directory_not_found(error, directory_key)));
+            let mut serialised_directory_listing = try!(::safe_core::structured_data_operations::unversioned::get_data(self.client.clone(),
+                                                                                                                     &structured_data,
+                                                                                                                     encryption_keys));
+            if let ::AccessLevel::Shared = *access_level {
+                serialised_directory_listing = try!(::directory_listing::DirectoryListing::open_with_shared_key(try!(self.get_shared_key()),
+                                                                                                                &serialised_directory_listing));
+            }
+            ::directory_listing::DirectoryListing::deserialise(&serialised_directory_listing)
+        }
+    }
+
+    /// Returns an EntryPager which pages through the entries of the directory, page_size entries at a time. The
+    /// directory is only fetched from the network when the first page is requested.
+    /// A DirectoryListing is stored as a single serialised blob, so the whole listing is fetched and held in memory while
+    /// paging; the pager bounds the number of entries handed out at a time, not the memory used.
+    /// Returns NfsError::ParameterIsNotValid if page_size is 0
+    pub fn entries_paged(&self,
+                         directory_key: &::metadata::directory_key::DirectoryKey,
+                         page_size    : usize) -> Result<::helper::entry_pager::EntryPager, ::errors::NfsError> {
+        if page_size == 0 {
+            return Err(::errors::NfsError::ParameterIsNotValid);
+        }
+        Ok(::helper::entry_pager::EntryPager::new(self.clone(), directory_key.clone(), page_size))
+    }
+
+    /// Return the DirectoryListing for the latest version, in the same way as `get`, waiting for each response of the
+    /// network for up to the timeout, see `timeout::get_response`. Nothing more is requested once a wait times out. The
+    /// chunks of a listing too large to be saved in its StructuredData are read through safe_core, without a timeout.
+    /// Returns NfsError::Timeout if the network did not respond to a request within the timeout
+    pub fn get_with_timeout(&self,
+                            directory_key: &::metadata::directory_key::DirectoryKey,
+                            timeout      : ::std::time::Duration) -> Result<::directory_listing::DirectoryListing, ::errors::NfsError> {
+        let mut directory_helper = self.clone();
+        directory_helper.timeout = Some(timeout);
+        directory_helper.get(directory_key)
+    }
+
+    /// Return the DirectoryListing for the latest version along with the latest versions of all its immediate sub
+    /// directories, in the order of the sub directories in the listing. Up to MAX_PARALLEL_FETCHES sub directories
+    /// are fetched concurrently.
+    /// Returns the first error met in fetching the directory or any of its sub directories
+    pub fn get_with_children(&self,
+                             directory_key: &::metadata::directory_key::DirectoryKey) -> Result<(::directory_listing::DirectoryListing,
+                                                                                                 Vec<::directory_listing::DirectoryListing>), ::errors::NfsError> {
+        let directory = try!(self.get(directory_key));
+        let sub_directory_keys: Vec<::metadata::directory_key::DirectoryKey> =
+            directory.get_sub_directories().iter().map(|sub_directory| sub_directory.get_key().clone()).collect();
+        let directory_helper = self.clone();
+        let fetches = run_batched(sub_directory_keys, MAX_PARALLEL_FETCHES, move |sub_directory_key| {
+            debug!("Fetching sub directory with id {:?} ...", sub_directory_key.get_id());
+            directory_helper.get(&sub_directory_key)
+        });
+        let mut children = Vec::with_capacity(fetches.len());
+        for child in fetches {
+            children.push(try!(child));
+        }
+        Ok((directory, children))
+    }
+
+    /// Return the DirectoryListing for the latest version of a directory which may be stored under either tag, e.g. when
+    /// a tree holds directories from before and after a migration between versioned and unversioned directories.
+    /// The directory is looked for under UNVERSIONED_DIRECTORY_LISTING_TAG first and then under
+    /// VERSIONED_DIRECTORY_LISTING_TAG. Only the network reporting that it holds no data under a tag moves on to the
+    /// next tag; any other failure, and errors in reading a directory which is found, are returned as they are.
+    /// Returns NfsError::DirectoryNotFound if the directory is found under neither tag
+    pub fn get_resilient(&self,
+                         directory_id: &::routing::NameType,
+                         access_level: &::AccessLevel) -> Result<::directory_listing::DirectoryListing, ::errors::NfsError> {
+        for &(type_tag, versioned) in &[(::UNVERSIONED_DIRECTORY_LISTING_TAG, false), (::VERSIONED_DIRECTORY_LISTING_TAG, true)] {
+            let directory_key = ::metadata::directory_key::DirectoryKey::new(directory_id.clone(), type_tag, versioned, access_level.clone());
+            match self.get_structured_data(directory_id, type_tag).map_err(|error| directory_not_found(error, &directory_key)) {
+                Ok(_) => return self.get(&directory_key),
+                Err(::errors::NfsError::DirectoryNotFound(_)) => debug!("Directory with id {:?} not found under tag {:?} ...", directory_id, type_tag),
+                Err(error) => return Err(error),
+            }
+        }
+        Err(::errors::NfsError::DirectoryNotFound(None))
+    }
+
+    /// Fetches the latest state of the directory, using the directory's own key, and updates the directory in place if it
+    /// differs from it. This gives polling callers a cheap way to tell whether anything changed.
+    /// Returns true if the directory was changed
+    pub fn refresh(&self, directory: &mut ::directory_listing::DirectoryListing) -> Result<bool, ::errors::NfsError> {
+        let latest_directory = try!(self.get(directory.get_key()));
+        if latest_directory == *directory {
+            Ok(false)
+        } else {
+            debug!("Directory changed in the network, refreshing it ...");
+            *directory = latest_directory;
+            Ok(true)
+        }
+    }
+
+    /// Return the DirectoryListing for the latest version, deciding from the type_tag whether the directory is versioned.
+    /// Only the directory listing tags of safe_nfs can be resolved this way, for any other tag
+    /// NfsError::ParameterIsNotValid is returned and `get` has to be used with an explicit DirectoryKey
+    pub fn get_auto(&self,
+                    directory_id: &::routing::NameType,
+                    type_tag    : u64,
+                    access_level: &::AccessLevel) -> Result<::directory_listing::DirectoryListing, ::errors::NfsError> {
+        let versioned = match type_tag {
+            ::VERSIONED_DIRECTORY_LISTING_TAG   => true,
+            ::UNVERSIONED_DIRECTORY_LISTING_TAG => false,
+            _ => return Err(::errors::NfsError::ParameterIsNotValid),
+        };
+        self.get(&::metadata::directory_key::DirectoryKey::new(directory_id.clone(), type_tag, versioned, access_level.clone()))
+    }
+
+    /// Return the DirectoryListing for the latest version, in the same way as `get`, or None if the directory does not
+    /// exist in the network, e.g. to check whether a directory exists. Only the network reporting that it holds no data
+    /// for the directory, see `get`, is taken as the directory not existing; every other failure, including other
+    /// failures to fetch the directory, is returned as an error.
+    pub fn try_get(&self,
+                   directory_key: &::metadata::directory_key::DirectoryKey) -> Result<Option<::directory_listing::DirectoryListing>, ::errors::NfsError> {
+        match self.get(directory_key) {
+            Ok(directory) => Ok(Some(directory)),
+            Err(::errors::NfsError::DirectoryNotFound(_)) => {
+                debug!("Directory with id {:?} does not exist ...", directory_key.get_id());
+                Ok(None)
+            },
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Returns whether the name is that of a file or of a sub directory of the parent directory, or None if the parent
+    /// directory has no entry with the name. Only the parent directory passed is looked at, nothing is fetched from the
+    /// network.
+    pub fn entry_kind(&self,
+                      parent: &::directory_listing::DirectoryListing,
+                      name  : &str) -> Option<::directory_listing::entry::EntryKind> {
+        if parent.locate_file(name).is_some() {
+            Some(::directory_listing::entry::EntryKind::File)
+        } else if parent.get_sub_directories().iter().any(|sub_directory| *sub_directory.get_name() == name) {
+            Some(::directory_listing::entry::EntryKind::Directory)
+        } else {
+            None
+        }
+    }
+
+    /// Walks the sub-tree starting at the root directory, checking that each sub directory can be fetched and that the
+    /// first chunk of each file can be retrieved from the network. Problems do not stop the walk; all of them are
+    /// collected in the returned ValidationReport. The sub-tree of an unreachable directory can not be checked.
+    pub fn validate_tree(&self,
+                         root: &::directory_listing::DirectoryListing) -> Result<::helper::validation_report::ValidationReport, ::errors::NfsError> {
+        let mut report = ::helper::validation_report::ValidationReport::new();
+        self.validate_directory(root, root.get_metadata().get_name(), &mut report);
+        Ok(report)
+    }
+
+    /// Returns the paths, relative to the root directory, of the files of the sub-tree starting at the root directory
+    /// which match the pattern. The pattern is made of `/` separated segments, each matching a single name as by
+    /// `DirectoryListing::glob`, except for `**` which matches any number of nested directories, e.g. `**/*.png` matches
+    /// the PNG files anywhere in the sub-tree. Sub directories are only fetched when their path matches the literal and
+    /// wildcard segments of the pattern leading up to its last segment or to its first `**`, so `images/**/*.png` never
+    /// fetches directories outside `images`.
+    pub fn glob_recursive(&self,
+                          root   : &::directory_listing::DirectoryListing,
+                          pattern: &str) -> Result<Vec<String>, ::errors::NfsError> {
+        let pattern_segments: Vec<&str> = pattern.split('/').collect();
+        let mut paths = Vec::new();
+        try!(self.glob_directory(root, &mut Vec::new(), &pattern_segments, &mut paths));
+        Ok(paths)
+    }
+
+    /// Saves the directories of the sub-tree starting at the root directory which are stored under old_tag under new_tag
+    /// instead, keeping their ids, and updates the keys their parents and sub directories hold for them to match. Sub
+    /// directories are saved before their parents and the directories stored under old_tag are left in place, so the
+    /// tree stays readable if the migration is interrupted, and running the migration again over the root directory, at
+    /// either tag, completes it. Only the latest version of a versioned directory is carried over. The parent of the
+    /// root directory, if any, is not updated.
+    /// Returns NfsError::ParameterIsNotValid if old_tag and new_tag are the same.
+    /// Returns the number of directories saved under new_tag
+    pub fn migrate_tags(&self,
+                        root   : &::directory_listing::DirectoryListing,
+                        old_tag: u64,
+                        new_tag: u64) -> Result<usize, ::errors::NfsError> {
+        try!(self.check_writable());
+        if old_tag == new_tag {
+            return Err(::errors::NfsError::ParameterIsNotValid);
+        }
+        let mut migrated = 0;
+        let parent_dir_key = root.get_metadata().get_parent_dir_key().cloned();
+        let _ = try!(self.migrate_directory(root.clone(), parent_dir_key, old_tag, new_tag, &mut migrated));
+        Ok(migrated)
+    }
+
+    /// Returns the path, relative to the root directory, and the size of each file of the sub-tree starting at the root
+    /// directory, as given for each directory by `DirectoryListing::file_sizes`. Every sub directory is fetched, but no
+    /// file content is read.
+    pub fn tree_file_sizes(&self, root: &::directory_listing::DirectoryListing) -> Result<Vec<(String, u64)>, ::errors::NfsError> {
+        let mut sizes = Vec::new();
+        try!(self.collect_file_sizes(root, &mut Vec::new(), &mut sizes));
+        Ok(sizes)
+    }
+
+    /// Follows the `/` separated path, relative to the root directory, as far as its directories exist, e.g. to find the
+    /// directories `mkdir -p` would have to create. Empty components are ignored.
+    /// Returns (the deepest directory of the path which exists, the names of the components of the path below it)
+    pub fn resolve_partial(&self,
+                           root: &::directory_listing::DirectoryListing,
+                           path: &str) -> Result<(::directory_listing::DirectoryListing, Vec<String>), ::errors::NfsError> {
+        let mut components = path.split('/').filter(|component| !component.is_empty()).map(|component| component.to_string()).peekable();
+        let mut directory = root.clone();
+        loop {
+            let sub_directory_key = match components.peek() {
+                Some(component) => match directory.find_sub_directory(component) {
+                    Some(sub_directory) => sub_directory.get_key().clone(),
+                    None => break,
+                },
+                None => break,
+            };
+            debug!("Resolving path component {:?} ...", components.peek());
+            directory = try!(self.get(&sub_directory_key));
+            let _ = components.next();
+        }
+        Ok((directory, components.collect()))
+    }
+
+    /// Creates every directory of the `/` separated path, relative to the root directory, which does not exist yet, like
+    /// `mkdir -p`. Directories of the path which already exist are reused.
+    /// Returns the directory at the end of the path
+    pub fn create_path(&self,
+                       root        : &mut ::directory_listing::DirectoryListing,
+                       path        : &str,
+                       versioned   : bool,
+                       access_level: ::AccessLevel) -> Result<::directory_listing::DirectoryListing, ::errors::NfsError> {
+        try!(self.check_writable());
+        if path.split('/').all(|component| component.is_empty()) {
+            return Err(::errors::NfsError::ParameterIsNotValid);
+        }
+        let tag_type = if versioned {
+            ::VERSIONED_DIRECTORY_LISTING_TAG
+        } else {
+            ::UNVERSIONED_DIRECTORY_LISTING_TAG
+        };
+        let (mut directory, remaining) = try!(self.resolve_partial(root, path));
+        for directory_name in remaining {
+            debug!("Creating missing directory {:?} of the path ...", directory_name);
+            let (created_directory, _) = if directory.get_key() == root.get_key() {
+                try!(self.create(directory_name, tag_type, Vec::new(), versioned, access_level.clone(), Some(&mut *root)))
+            } else {
+                try!(self.create(directory_name, tag_type, Vec::new(), versioned, access_level.clone(), Some(&mut directory)))
             };
+            directory = created_directory;
+        }
+        Ok(directory)
+    }
+
+    /// Returns the path, relative to the root directory, of a file of the directory with the given key, e.g. to show where
+    /// a file found through the key of its directory is located. The sub-tree of the root directory is searched for the
+    /// directory.
+    /// Returns NfsError::DirectoryNotFound if the directory is not in the sub-tree and NfsError::FileNotFound if the
+    /// directory has no file with the name
+    pub fn path_of_file(&self,
+                        root              : &::directory_listing::DirectoryListing,
+                        file_name         : &str,
+                        containing_dir_key: &::metadata::directory_key::DirectoryKey) -> Result<String, ::errors::NfsError> {
+        let mut path = Vec::new();
+        let directory = match try!(self.find_directory_path(root, containing_dir_key, &mut path)) {
+            Some(directory) => directory,
+            None => return Err(::errors::NfsError::DirectoryNotFound(Some(containing_dir_key.clone()))),
+        };
+        let _ = try!(directory.locate_file(file_name).ok_or(::errors::NfsError::FileNotFound));
+        path.push(file_name.to_string());
+        Ok(path.join("/"))
+    }
+
+    /// Writes the sub-tree starting at the root directory to out as a tar archive.
+    /// The entries are placed under a top level directory named after the root directory and carry the size and the
+    /// modified time of the files and directories. The content of each file is streamed from the network in parts, so
+    /// the memory used does not grow with the size of the files. A file of 8 GiB or more does not fit a tar header and is
+    /// reported as NfsError::LocalIo.
+    pub fn export_tar<W: ::std::io::Write>(&self,
+                                           root   : &::directory_listing::DirectoryListing,
+                                           mut out: W) -> Result<(), ::errors::NfsError> {
+        try!(self.export_tar_entries(root, root.get_metadata().get_name(), &mut out));
+        try!(::helper::tar::write_end(&mut out));
+        Ok(try!(out.flush()))
+    }
+
+    /// Reads a tar archive from input and mirrors its content under the destination directory.
+    /// Directories missing along the path of an entry are created, with the access_level passed and the type of the
+    /// destination directory. The content of each file is streamed into the network in parts. Entries other than
+    /// directories and regular files are skipped. Malformed archives are reported as NfsError::LocalIo.
+    /// A leading `/` and `.` components are dropped from the path of an entry, so absolute paths are imported under the
+    /// destination directory, and a path with a `..` component is rejected with NfsError::InvalidName so no entry can
+    /// be placed outside the destination directory. Entries imported before the rejected one are kept.
+    /// The destination directory is refreshed in place once the archive has been imported.
+    pub fn import_tar<R: ::std::io::Read>(&self,
+                                          destination : &mut ::directory_listing::DirectoryListing,
+                                          access_level: ::AccessLevel,
+                                          mut input   : R) -> Result<(), ::errors::NfsError> {
+        try!(self.check_writable());
+        let result = self.import_tar_entries(destination, access_level, &mut input);
+        *destination = try!(self.get(destination.get_key()));
+        result
+    }
+
+    /// Compares the SAFE directory tree with the local directory tree at local_path and returns the plan of the actions
+    /// needed to make the SAFE directory mirror the local one. A local file is considered changed if its size differs from
+    /// the file in the network or if it was modified after it. Neither the network nor the local filesystem is modified.
+    /// An entry replaced by one of the other kind, e.g. a file by a directory, is deleted before its replacement is
+    /// created. Local entries which are neither files nor directories, e.g. symlinks, are skipped, and the entries of the
+    /// same name in the network are kept.
+    pub fn diff_against_local(&self,
+                              safe_directory: &::directory_listing::DirectoryListing,
+                              local_path    : &::std::path::Path) -> Result<::helper::sync_plan::SyncPlan, ::errors::NfsError> {
+        let mut plan = ::helper::sync_plan::SyncPlan::new();
+        try!(self.diff_directory(Some(safe_directory), local_path, "", &mut plan));
+        Ok(plan)
+    }
 
-            let structured_data = try!(self.get_structured_data(directory_id, type_tag));
-            let serialised_directory_listing = try!(::safe_core::structured_data_operations::unversioned::get_data(self.client.clone(),
-                                                                                                                 &structured_data,
-                                                                                                                 encryption_keys));
-            Ok(try!(::safe_core::utility::deserialise(&serialised_directory_listing)))
+    /// Performs the actions of the plan, as returned by `diff_against_local`, on the directory tree, reading the content
+    /// of uploaded and updated files from under local_root. File content is streamed in parts.
+    /// A failing entry does not stop the sync; its error is recorded in the returned SyncReport and the remaining entries
+    /// are still applied. The directory is refreshed in place once the plan has been applied.
+    pub fn apply_sync_plan(&self,
+                           plan      : ::helper::sync_plan::SyncPlan,
+                           directory : &mut ::directory_listing::DirectoryListing,
+                           local_root: &::std::path::Path) -> Result<::helper::sync_plan::SyncReport, ::errors::NfsError> {
+        try!(self.check_writable());
+        let mut report = ::helper::sync_plan::SyncReport::new();
+        for entry in plan {
+            debug!("Applying {:?} for {:?} ...", entry.action, entry.path);
+            let result = self.apply_sync_entry(&entry, directory, local_root);
+            report.record(entry, result);
         }
+        *directory = try!(self.get(directory.get_key()));
+        Ok(report)
+    }
+
+    /// Returns true if the Root Directory of the user has been created, e.g. to tell a new account from an existing one.
+    /// Unlike `get_user_root_directory_listing`, the Root Directory is not created if it does not exist.
+    pub fn user_root_exists(&self) -> bool {
+        eval_result!(self.client.lock()).get_user_root_directory_id().is_some()
+    }
+
+    /// Returns true if the configuration root directory has been created. Unlike `get_configuration_directory_listing`,
+    /// the configuration root directory is not created if it does not exist.
+    pub fn configuration_root_exists(&self) -> bool {
+        eval_result!(self.client.lock()).get_configuration_root_directory_id().is_some()
     }
 
     /// Returns the Root Directory
@@ -171,12 +1079,8 @@ impl DirectoryHelper {
     /// Returns the Configuration DirectoryListing from the configuration root folder
     /// Creates the directory or the root or both if it doesn't find one.
     pub fn get_configuration_directory_listing(&self, directory_name: String) -> Result<::directory_listing::DirectoryListing, ::errors::NfsError> {
-        let config_dir_id = eval_result!(self.client.lock()).get_configuration_root_directory_id().map(|id| { id.clone() });
-        let mut config_directory_listing = match config_dir_id {
-            Some(id) => {
-                debug!("Retrieving root configuration directory at id {:?} ...", id);
-                try!(self.get(&::metadata::directory_key::DirectoryKey::new(id, ::UNVERSIONED_DIRECTORY_LISTING_TAG, false, ::AccessLevel::Private)))
-            },
+        let mut config_directory_listing = match try!(self.find_configuration_root_directory_listing()) {
+            Some(config_directory_listing) => config_directory_listing,
             None => {
                 debug!("Creating root configuration directory ...");
                 let (created_directory, _) = try!(self.create(::CONFIGURATION_DIRECTORY_NAME.to_string(),
@@ -208,83 +1112,645 @@ impl DirectoryHelper {
         }
     }
 
-    /// Creates a StructuredData in the Network
-    /// The StructuredData is created based on the version and AccessLevel of the DirectoryListing
-    fn save_directory_listing(&self, directory: &::directory_listing::DirectoryListing) -> Result<::routing::structured_data::StructuredData, ::errors::NfsError> {
-        let signing_key = try!(eval_result!(self.client.lock()).get_secret_signing_key()).clone();
-        let owner_key = try!(eval_result!(self.client.lock()).get_public_signing_key()).clone();
-        let access_level = directory.get_key().get_access_level();
-        let versioned = directory.get_key().is_versioned();
-
-        if versioned {
-            let serialised_data = match *access_level {
-                ::AccessLevel::Private => try!(directory.encrypt(self.client.clone())),
-                ::AccessLevel::Public => try!(::safe_core::utility::serialise(&directory)),
-            };
-            let version = try!(self.save_as_immutable_data(serialised_data,
-                                                           ::routing::immutable_data::ImmutableDataType::Normal));
-            Ok(try!(::safe_core::structured_data_operations::versioned::create(& *eval_result!(self.client.lock()),
-                                                                                 version,
-                                                                                 directory.get_key().get_type_tag(),
-                                                                                 directory.get_key().get_id().clone(),
-                                                                                 0,
-                                                                                 vec![owner_key],
-                                                                                 Vec::new(),
-                                                                                 &signing_key)))
-        } else {
-            let private_key = try!(eval_result!(self.client.lock()).get_public_encryption_key()).clone();
-            let secret_key = try!(eval_result!(self.client.lock()).get_secret_encryption_key()).clone();
-            let nonce = ::directory_listing::DirectoryListing::generate_nonce(directory.get_key().get_id());
-            let serialised_data = try!(::safe_core::utility::serialise(&directory));
+    /// Returns the names of the configuration directories created through `get_configuration_directory_listing`, in the
+    /// order they were created. The directory in which safe_nfs itself records the directories shared with the user,
+    /// SHARED_DIRECTORIES_CONFIGURATION_NAME, is left out. Nothing is created; if the configuration root directory does
+    /// not exist yet, no names are returned.
+    pub fn list_configuration_directories(&self) -> Result<Vec<String>, ::errors::NfsError> {
+        match try!(self.find_configuration_root_directory_listing()) {
+            Some(config_directory_listing) => Ok(config_directory_listing.get_sub_directories()
+                                                                         .iter()
+                                                                         .map(|metadata| metadata.get_name().clone())
+                                                                         .filter(|name| *name != ::SHARED_DIRECTORIES_CONFIGURATION_NAME)
+                                                                         .collect()),
+            None => Ok(Vec::new()),
+        }
+    }
 
-            let encryption_keys = match *access_level {
-                ::AccessLevel::Private => Some((&private_key,
-                                                &secret_key,
-                                                &nonce)),
-                ::AccessLevel::Public => None,
-            };
-            Ok(try!(::safe_core::structured_data_operations::unversioned::create(self.client.clone(),
-                                                                                   directory.get_key().get_type_tag(),
-                                                                                   directory.get_key().get_id().clone(),
-                                                                                   0,
-                                                                                   serialised_data,
-                                                                                   vec![owner_key.clone()],
-                                                                                   Vec::new(),
-                                                                                   &signing_key,
-                                                                                   encryption_keys)))
+    /// Returns the directories shared with the user, as saved through `add_shared_directory`, in the order they were added.
+    /// Nothing is created; if no directory has been saved yet, none are returned.
+    /// Returns Vec<(name of the shared directory, key of the shared directory)>
+    pub fn list_shared_directories(&self) -> Result<Vec<(String, ::metadata::directory_key::DirectoryKey)>, ::errors::NfsError> {
+        match try!(self.find_configuration_directory_listing(::SHARED_DIRECTORIES_CONFIGURATION_NAME)) {
+            Some(registry) => Ok(try!(DirectoryHelper::decode_shared_directories(&registry)).into_iter()
+                                                                                            .map(|shared| (shared.name, shared.key))
+                                                                                            .collect()),
+            None => Ok(Vec::new()),
         }
     }
 
-    fn update_directory_listing(&self, directory: &::directory_listing::DirectoryListing) -> Result<(), ::errors::NfsError> {
-        let structured_data = try!(self.get_structured_data(directory.get_key().get_id(), directory.get_key().get_type_tag()));
+    /// Checks that a share token, e.g. one pasted by a user, is well formed without making any request to the network.
+    /// Returns ((id, type_tag) of the shared directory, AccessLevel of the shared directory)
+    /// Returns NfsError::InvalidKeyFormat if the token is not a valid share token
+    pub fn parse_share_token(token: &str) -> Result<((::routing::NameType, u64), ::AccessLevel), ::errors::NfsError> {
+        let directory_key = try!(DirectoryHelper::decode_share_token(token));
+        Ok(((directory_key.get_id().clone(), directory_key.get_type_tag()), directory_key.get_access_level().clone()))
+    }
 
-        let signing_key = try!(eval_result!(self.client.lock()).get_secret_signing_key()).clone();
-        let owner_key = try!(eval_result!(self.client.lock()).get_public_signing_key()).clone();
-        let access_level = directory.get_key().get_access_level();
-        let versioned = directory.get_key().is_versioned();
+    /// Fetches the directory shared through a share token. The token is checked as by `parse_share_token` before any
+    /// request is made to the network, and a Shared directory is read with the shared key of the helper.
+    /// Returns NfsError::InvalidKeyFormat if the token is not a valid share token
+    pub fn get_from_share_token(&self, token: &str) -> Result<::directory_listing::DirectoryListing, ::errors::NfsError> {
+        let directory_key = try!(DirectoryHelper::decode_share_token(token));
+        self.get(&directory_key)
+    }
 
-        let updated_structured_data = if versioned {
-            let serialised_data = match *access_level {
-                ::AccessLevel::Private => try!(directory.encrypt(self.client.clone())),
-                ::AccessLevel::Public => try!(::safe_core::utility::serialise(&directory)),
-            };
-            let version = try!(self.save_as_immutable_data(serialised_data,
-                                                           ::routing::immutable_data::ImmutableDataType::Normal));
-            try!(::safe_core::structured_data_operations::versioned::append_version(&mut *eval_result!(self.client.lock()),
-                                                                                      structured_data,
-                                                                                      version,
-                                                                                      &signing_key))
-        } else {
-            let private_key = try!(eval_result!(self.client.lock()).get_public_encryption_key()).clone();
-            let secret_key = try!(eval_result!(self.client.lock()).get_secret_encryption_key()).clone();
-            let nonce = ::directory_listing::DirectoryListing::generate_nonce(directory.get_key().get_id());
-            let serialised_data = try!(::safe_core::utility::serialise(&directory));
+    /// Saves a directory shared with the user, from its share token, under the name passed. The directories saved are kept
+    /// in a configuration directory, so they can be listed through `list_shared_directories` in later sessions.
+    /// A directory already saved under the same name is replaced.
+    /// Returns NfsError::InvalidKeyFormat if the token is not a valid share token
+    pub fn add_shared_directory(&self, name: &str, token: &str) -> Result<(), ::errors::NfsError> {
+        try!(self.check_writable());
+        let directory_key = try!(DirectoryHelper::decode_share_token(token));
+        let mut registry = try!(self.get_configuration_directory_listing(::SHARED_DIRECTORIES_CONFIGURATION_NAME.to_string()));
+        let mut shared_directories = try!(DirectoryHelper::decode_shared_directories(&registry));
+        let shared_directory = SharedDirectory {
+            name: name.to_string(),
+            key : directory_key,
+        };
+        match shared_directories.iter().position(|shared| shared.name == name) {
+            Some(index) => shared_directories[index] = shared_directory,
+            None => shared_directories.push(shared_directory),
+        }
+        registry.get_mut_metadata().set_user_metadata(try!(::errors::serialise(&shared_directories)));
+        debug!("Saving shared directory {:?} ...", name);
+        let _ = try!(self.update(&registry));
+        Ok(())
+    }
 
-            let encryption_keys = match *access_level {
-                ::AccessLevel::Private => Some((&private_key,
-                                                &secret_key,
-                                                &nonce)),
+    /// Returns the DirectoryKey held by a share token, ignoring the whitespace around it
+    fn decode_share_token(token: &str) -> Result<::metadata::directory_key::DirectoryKey, ::errors::NfsError> {
+        ::metadata::directory_key::DirectoryKey::from_share_token(token.trim())
+    }
+
+    /// Returns the root configuration directory, or None if it has not been created yet
+    fn find_configuration_root_directory_listing(&self) -> Result<Option<::directory_listing::DirectoryListing>, ::errors::NfsError> {
+        let config_dir_id = eval_result!(self.client.lock()).get_configuration_root_directory_id().map(|id| { id.clone() });
+        match config_dir_id {
+            Some(id) => {
+                debug!("Retrieving root configuration directory at id {:?} ...", id);
+                Ok(Some(try!(self.get(&::metadata::directory_key::DirectoryKey::new(id,
+                                                                                     ::UNVERSIONED_DIRECTORY_LISTING_TAG,
+                                                                                     false,
+                                                                                     ::AccessLevel::Private)))))
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the configuration directory with the name passed, or None if it has not been created yet. Unlike
+    /// `get_configuration_directory_listing`, nothing is created.
+    fn find_configuration_directory_listing(&self, directory_name: &str) -> Result<Option<::directory_listing::DirectoryListing>, ::errors::NfsError> {
+        let config_directory_listing = match try!(self.find_configuration_root_directory_listing()) {
+            Some(config_directory_listing) => config_directory_listing,
+            None => return Ok(None),
+        };
+        match config_directory_listing.get_sub_directories().iter().find(|metadata| *metadata.get_name() == directory_name) {
+            Some(metadata) => {
+                debug!("Retrieving {:?} specific configuration directory ...", directory_name);
+                Ok(Some(try!(self.get(metadata.get_key()))))
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Decodes the directories saved in the registry kept in the user metadata of the shared directories configuration
+    /// directory. A registry nothing has been saved in yet holds no directories.
+    fn decode_shared_directories(registry: &::directory_listing::DirectoryListing) -> Result<Vec<SharedDirectory>, ::errors::NfsError> {
+        let user_metadata = registry.get_metadata().get_user_metadata();
+        if user_metadata.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(try!(::safe_core::utility::deserialise(user_metadata)))
+    }
+
+    /// Creates the directory at the path relative to the root_directory and records the key of its parent along with its
+    /// name and its own key in created_directories
+    fn create_tree_entry(&self,
+                         root_directory     : &mut ::directory_listing::DirectoryListing,
+                         path               : &str,
+                         tag_type           : u64,
+                         versioned          : bool,
+                         access_level       : ::AccessLevel,
+                         created_directories: &mut Vec<(::metadata::directory_key::DirectoryKey,
+                                                        String,
+                                                        ::metadata::directory_key::DirectoryKey)>) -> Result<(), ::errors::NfsError> {
+        let mut components: Vec<&str> = path.split('/').filter(|component| !component.is_empty()).collect();
+        let directory_name = try!(components.pop().ok_or(::errors::NfsError::ParameterIsNotValid)).to_string();
+        let (created_directory, _) = if components.is_empty() {
+            try!(self.create(directory_name.clone(), tag_type, Vec::new(), versioned, access_level, Some(&mut *root_directory)))
+        } else {
+            let mut parent = try!(self.get(try!(root_directory.find_sub_directory(&components[0].to_string())
+                                                              .ok_or(::errors::NfsError::DirectoryNotFound(None))).get_key()));
+            for component in &components[1..] {
+                parent = try!(self.get(try!(parent.find_sub_directory(&component.to_string())
+                                                  .ok_or(::errors::NfsError::DirectoryNotFound(None))).get_key()));
+            }
+            try!(self.create(directory_name.clone(), tag_type, Vec::new(), versioned, access_level, Some(&mut parent)))
+        };
+        let parent_dir_key = try!(created_directory.get_metadata().get_parent_dir_key()
+                                                   .ok_or(::errors::NfsError::from("Programming Error - Please report this as a Bug."))).clone();
+        created_directories.push((parent_dir_key, directory_name, created_directory.get_key().clone()));
+        Ok(())
+    }
+
+    /// Saves a new directory in the network and adds it to the parent_directory, if one is passed
+    /// Returns (directory, Option<parent_directory's parent>)
+    fn put_directory(&self,
+                     directory       : ::directory_listing::DirectoryListing,
+                     parent_directory: Option<&mut ::directory_listing::DirectoryListing>) -> Result<(::directory_listing::DirectoryListing,
+                                                                                                      Option<::directory_listing::DirectoryListing>), ::errors::NfsError> {
+        if let Some(ref parent_directory) = parent_directory {
+            try!(parent_directory.check_sub_directory_conflict(directory.get_metadata()));
+        }
+        let structured_data = try!(self.save_directory_listing(&directory));
+        debug!("Posting PUT request to network to save structured data for directory ...");
+        try!(eval_result!(self.client.lock()).put(::routing::data::Data::StructuredData(structured_data), None));
+        if let Some(mut parent_directory) = parent_directory {
+            try!(parent_directory.upsert_sub_directory_checked(directory.aggregated_metadata(), false));
+            Ok((directory, try!(self.update(parent_directory))))
+        } else {
+            Ok((directory, None))
+        }
+    }
+
+    /// Checks the files and sub directories of the directory, recording the problems found in the report
+    fn validate_directory(&self,
+                          directory: &::directory_listing::DirectoryListing,
+                          path     : &str,
+                          report   : &mut ::helper::validation_report::ValidationReport) {
+        debug!("Validating directory {:?} ...", path);
+        for file in directory.get_files() {
+            if let ::self_encryption::datamap::DataMap::Chunks(ref chunks) = *file.get_datamap() {
+                let first_chunk = chunks.iter().min_by_key(|chunk| chunk.chunk_num);
+                let result = match first_chunk {
+                    Some(chunk) => self.get_immutable_data(::helper::chunk_info::chunk_name(&chunk.hash), ::routing::immutable_data::ImmutableDataType::Normal).map(|_| ()),
+                    None => Err(::errors::NfsError::from("DataMap holds no chunks")),
+                };
+                if let Err(error) = result {
+                    report.add_broken_file(format!("{}/{}", path, file.get_name()), error);
+                }
+            }
+        }
+        for sub_directory in directory.get_sub_directories() {
+            let sub_directory_path = format!("{}/{}", path, sub_directory.get_name());
+            match self.get(sub_directory.get_key()) {
+                Ok(sub_directory_listing) => self.validate_directory(&sub_directory_listing, &sub_directory_path, report),
+                Err(error) => report.add_unreachable_directory(sub_directory_path, error),
+            }
+        }
+    }
+
+    /// Searches the sub-tree of the directory for the directory with the given key, leaving its path relative to the
+    /// directory passed in path when it is found.
+    /// Returns the DirectoryListing of the directory found
+    fn find_directory_path(&self,
+                           directory    : &::directory_listing::DirectoryListing,
+                           directory_key: &::metadata::directory_key::DirectoryKey,
+                           path         : &mut Vec<String>) -> Result<Option<::directory_listing::DirectoryListing>, ::errors::NfsError> {
+        if directory.get_key() == directory_key {
+            return Ok(Some(directory.clone()));
+        }
+        for sub_directory in directory.get_sub_directories() {
+            let sub_directory_listing = try!(self.get(sub_directory.get_key()));
+            path.push(sub_directory.get_name().clone());
+            if let Some(found_directory) = try!(self.find_directory_path(&sub_directory_listing, directory_key, path)) {
+                return Ok(Some(found_directory));
+            }
+            let _ = path.pop();
+        }
+        Ok(None)
+    }
+
+    /// Adds the paths of the files of the directory, and of its sub-tree, which match the pattern to paths
+    fn glob_directory(&self,
+                      directory       : &::directory_listing::DirectoryListing,
+                      path            : &mut Vec<String>,
+                      pattern_segments: &[&str],
+                      paths           : &mut Vec<String>) -> Result<(), ::errors::NfsError> {
+        for file in directory.get_files() {
+            path.push(file.get_name().clone());
+            let path_segments: Vec<&str> = path.iter().map(|segment| &segment[..]).collect();
+            if ::directory_listing::glob::matches_path(pattern_segments, &path_segments) {
+                paths.push(path.join("/"));
+            }
+            let _ = path.pop();
+        }
+        for sub_directory in directory.get_sub_directories() {
+            path.push(sub_directory.get_name().clone());
+            let could_match = {
+                let path_segments: Vec<&str> = path.iter().map(|segment| &segment[..]).collect();
+                ::directory_listing::glob::matches_path_below(pattern_segments, &path_segments)
+            };
+            let _ = path.pop();
+            if !could_match {
+                continue;
+            }
+            debug!("Searching sub directory {:?} for files matching pattern ...", sub_directory.get_name());
+            let sub_directory_listing = try!(self.get(sub_directory.get_key()));
+            path.push(sub_directory.get_name().clone());
+            try!(self.glob_directory(&sub_directory_listing, path, pattern_segments, paths));
+            let _ = path.pop();
+        }
+        Ok(())
+    }
+
+    /// Migrates the sub-tree starting at the directory, see `migrate_tags`, counting the directories saved under new_tag
+    /// in migrated.
+    /// Returns the key of the directory once migrated
+    fn migrate_directory(&self,
+                         mut directory : ::directory_listing::DirectoryListing,
+                         parent_dir_key: Option<::metadata::directory_key::DirectoryKey>,
+                         old_tag       : u64,
+                         new_tag       : u64,
+                         migrated      : &mut usize) -> Result<::metadata::directory_key::DirectoryKey, ::errors::NfsError> {
+        let original = directory.clone();
+        let original_key = directory.get_key().clone();
+        let key = if original_key.get_type_tag() == old_tag {
+            let key = ::metadata::directory_key::DirectoryKey::new(original_key.get_id().clone(),
+                                                                   new_tag,
+                                                                   original_key.is_versioned(),
+                                                                   original_key.get_access_level().clone());
+            match original_key.get_nonce() {
+                Some(nonce) => key.with_nonce(nonce),
+                None => key,
+            }
+        } else {
+            original_key.clone()
+        };
+        directory.get_mut_metadata().set_key(key.clone());
+        directory.get_mut_metadata().set_parent_dir_key(parent_dir_key);
+        for index in 0..directory.get_sub_directories().len() {
+            let mut sub_directory = directory.get_sub_directories()[index].clone();
+            debug!("Migrating sub directory {:?} ...", sub_directory.get_name());
+            let sub_directory_listing = try!(self.get(sub_directory.get_key()));
+            let sub_directory_key = try!(self.migrate_directory(sub_directory_listing, Some(key.clone()), old_tag, new_tag, migrated));
+            sub_directory.set_key(sub_directory_key);
+            sub_directory.set_parent_dir_key(Some(key.clone()));
+            directory.get_mut_sub_directories()[index] = sub_directory;
+        }
+        let stored = if original_key == key {
+            Some(original)
+        } else {
+            // Only the network holding no data for the key means the directory still has to be saved under new_tag
+            try!(self.try_get(&key))
+        };
+        match stored {
+            Some(ref stored) if *stored == directory => return Ok(key),
+            Some(_) => {
+                debug!("Updating keys held by directory {:?} ...", directory.get_metadata().get_name());
+                try!(self.update_directory_listing(&directory));
+            },
+            None => {
+                debug!("Saving directory {:?} under tag {:?} ...", directory.get_metadata().get_name(), new_tag);
+                let _ = try!(self.put_directory(directory, None));
+            },
+        }
+        if original_key != key {
+            *migrated += 1;
+        }
+        Ok(key)
+    }
+
+    /// Adds the path and the size of the files of the sub-tree starting at the directory to sizes
+    fn collect_file_sizes(&self,
+                          directory: &::directory_listing::DirectoryListing,
+                          path     : &mut Vec<String>,
+                          sizes    : &mut Vec<(String, u64)>) -> Result<(), ::errors::NfsError> {
+        for (name, size) in directory.file_sizes() {
+            path.push(name);
+            sizes.push((path.join("/"), size));
+            let _ = path.pop();
+        }
+        for sub_directory in directory.get_sub_directories() {
+            debug!("Collecting file sizes of sub directory {:?} ...", sub_directory.get_name());
+            let sub_directory_listing = try!(self.get(sub_directory.get_key()));
+            path.push(sub_directory.get_name().clone());
+            try!(self.collect_file_sizes(&sub_directory_listing, path, sizes));
+            let _ = path.pop();
+        }
+        Ok(())
+    }
+
+    /// Writes the directory and its content under the given path to the tar archive
+    fn export_tar_entries<W: ::std::io::Write>(&self,
+                                               directory: &::directory_listing::DirectoryListing,
+                                               path     : &str,
+                                               out      : &mut W) -> Result<(), ::errors::NfsError> {
+        debug!("Exporting directory {:?} to tar archive ...", path);
+        try!(::helper::tar::write_header(out,
+                                         &format!("{}/", path),
+                                         0,
+                                         directory.get_metadata().get_modified_time().to_timespec().sec,
+                                         true));
+        for file in directory.get_files() {
+            let mut reader = ::helper::reader::Reader::with_storage(self.client.clone(), self.storage.clone(), file);
+            let size = reader.size();
+            try!(::helper::tar::write_header(out,
+                                             &format!("{}/{}", path, file.get_name()),
+                                             size,
+                                             file.get_metadata().get_modified_time().to_timespec().sec,
+                                             false));
+            let mut position = 0;
+            while position < size {
+                let length = ::std::cmp::min(STREAM_BUFFER_SIZE, size - position);
+                try!(out.write_all(&try!(reader.read(position, length))));
+                position += length;
+            }
+            try!(::helper::tar::write_padding(out, size));
+        }
+        for sub_directory in directory.get_sub_directories() {
+            let sub_directory_listing = try!(self.get(sub_directory.get_key()));
+            try!(self.export_tar_entries(&sub_directory_listing, &format!("{}/{}", path, sub_directory.get_name()), out));
+        }
+        Ok(())
+    }
+
+    /// Creates the directories and files for each entry of the tar archive under the destination directory
+    fn import_tar_entries<R: ::std::io::Read>(&self,
+                                              destination : &::directory_listing::DirectoryListing,
+                                              access_level: ::AccessLevel,
+                                              input       : &mut R) -> Result<(), ::errors::NfsError> {
+        let file_helper = self.get_file_helper();
+        while let Some(header) = try!(::helper::tar::read_header(input)) {
+            debug!("Importing {:?} from tar archive ...", header.path);
+            let mut components: Vec<&str> = header.path.split('/').filter(|component| !component.is_empty() && *component != ".").collect();
+            if components.iter().any(|component| *component == "..") {
+                return Err(::errors::NfsError::InvalidName);
+            }
+            match header.kind {
+                ::helper::tar::EntryKind::Directory => {
+                    let _ = try!(self.get_or_create_path(destination, &components, access_level.clone()));
+                    try!(::helper::tar::skip_content(input, header.size));
+                },
+                ::helper::tar::EntryKind::File => {
+                    let file_name = try!(components.pop().ok_or(::errors::NfsError::ParameterIsNotValid)).to_string();
+                    let parent_directory = try!(self.get_or_create_path(destination, &components, access_level.clone()));
+                    let mut writer = try!(file_helper.create(file_name, Vec::new(), parent_directory));
+                    let mut buffer = vec![0u8; ::std::cmp::min(STREAM_BUFFER_SIZE, header.size) as usize];
+                    let mut position = 0;
+                    while position < header.size {
+                        let length = ::std::cmp::min(STREAM_BUFFER_SIZE, header.size - position) as usize;
+                        try!(::helper::tar::read_exact(input, &mut buffer[..length]));
+                        writer.write(&buffer[..length], position);
+                        position += length as u64;
+                    }
+                    let _ = try!(writer.close());
+                    try!(::helper::tar::skip_padding(input, header.size));
+                },
+                ::helper::tar::EntryKind::Unsupported => try!(::helper::tar::skip_content(input, header.size)),
+            }
+        }
+        Ok(())
+    }
+
+    /// Walks the path of directory names from the root_directory, creating the directories which do not exist yet
+    /// Returns the directory at the end of the path
+    fn get_or_create_path(&self,
+                          root_directory: &::directory_listing::DirectoryListing,
+                          path          : &[&str],
+                          access_level  : ::AccessLevel) -> Result<::directory_listing::DirectoryListing, ::errors::NfsError> {
+        let mut directory = try!(self.get(root_directory.get_key()));
+        for directory_name in path {
+            let existing_key = directory.find_sub_directory(&directory_name.to_string()).map(|metadata| metadata.get_key().clone());
+            directory = match existing_key {
+                Some(key) => try!(self.get(&key)),
+                None => {
+                    let type_tag = directory.get_key().get_type_tag();
+                    let versioned = directory.get_key().is_versioned();
+                    let (created_directory, _) = try!(self.create(directory_name.to_string(),
+                                                                  type_tag,
+                                                                  Vec::new(),
+                                                                  versioned,
+                                                                  access_level.clone(),
+                                                                  Some(&mut directory)));
+                    created_directory
+                },
+            };
+        }
+        Ok(directory)
+    }
+
+    /// Adds the actions needed to make the safe_directory mirror the local directory to the plan.
+    /// safe_directory is None when the local directory does not exist in the network yet.
+    fn diff_directory(&self,
+                      safe_directory: Option<&::directory_listing::DirectoryListing>,
+                      local_path    : &::std::path::Path,
+                      relative_path : &str,
+                      plan          : &mut ::helper::sync_plan::SyncPlan) -> Result<(), ::errors::NfsError> {
+        let mut local_entries = Vec::new();
+        for entry in try!(::std::fs::read_dir(local_path)) {
+            let entry = try!(entry);
+            let name = try!(entry.file_name().into_string().map_err(|_| ::errors::NfsError::InvalidUtf8));
+            local_entries.push((name, entry.path(), try!(entry.metadata())));
+        }
+        local_entries.sort_by(|lhs, rhs| lhs.0.cmp(&rhs.0));
+
+        // Local entries which are neither files nor directories, e.g. symlinks, are not synced but keep the entries of
+        // the same name in the network from being deleted
+        let local_names: Vec<String> = local_entries.iter().map(|&(ref name, _, _)| name.clone()).collect();
+        for (name, path, metadata) in local_entries {
+            let entry_path = if relative_path.is_empty() { name.clone() } else { format!("{}/{}", relative_path, name) };
+            if metadata.is_dir() {
+                // A file replaced by a directory of the same name is deleted before the directory is created
+                if safe_directory.and_then(|directory| directory.find_file(&name)).is_some() {
+                    plan.push(entry_path.clone(), ::helper::sync_plan::SyncAction::DeleteFile);
+                }
+                match safe_directory.and_then(|directory| directory.find_sub_directory(&name)) {
+                    Some(sub_directory) => {
+                        let sub_directory_listing = try!(self.get(sub_directory.get_key()));
+                        try!(self.diff_directory(Some(&sub_directory_listing), &path, &entry_path, plan));
+                    },
+                    None => {
+                        plan.push(entry_path.clone(), ::helper::sync_plan::SyncAction::CreateDirectory);
+                        try!(self.diff_directory(None, &path, &entry_path, plan));
+                    },
+                }
+            } else if metadata.is_file() {
+                // A directory replaced by a file of the same name is deleted before the file is uploaded
+                if safe_directory.and_then(|directory| directory.find_sub_directory(&name)).is_some() {
+                    plan.push(entry_path.clone(), ::helper::sync_plan::SyncAction::DeleteDirectory);
+                }
+                match safe_directory.and_then(|directory| directory.find_file(&name)) {
+                    Some(file) => {
+                        if file.logical_size() != metadata.len() ||
+                           try!(local_modified_time(&metadata)) > file.get_metadata().get_modified_time().to_timespec().sec {
+                            plan.push(entry_path, ::helper::sync_plan::SyncAction::Update);
+                        }
+                    },
+                    None => plan.push(entry_path, ::helper::sync_plan::SyncAction::Upload),
+                }
+            } else {
+                debug!("Skipping local entry {:?} which is neither a file nor a directory ...", entry_path);
+            }
+        }
+
+        if let Some(directory) = safe_directory {
+            let join = |name: &String| if relative_path.is_empty() { name.clone() } else { format!("{}/{}", relative_path, name) };
+            for file in directory.get_files().iter().filter(|file| !local_names.contains(file.get_name())) {
+                plan.push(join(file.get_name()), ::helper::sync_plan::SyncAction::DeleteFile);
+            }
+            for sub_directory in directory.get_sub_directories().iter().filter(|sub_directory| !local_names.contains(sub_directory.get_name())) {
+                plan.push(join(sub_directory.get_name()), ::helper::sync_plan::SyncAction::DeleteDirectory);
+            }
+        }
+        Ok(())
+    }
+
+    /// Performs the action of a single entry of a SyncPlan
+    fn apply_sync_entry(&self,
+                        entry     : &::helper::sync_plan::SyncEntry,
+                        root      : &::directory_listing::DirectoryListing,
+                        local_root: &::std::path::Path) -> Result<(), ::errors::NfsError> {
+        let mut components: Vec<&str> = entry.path.split('/').filter(|component| !component.is_empty()).collect();
+        let name = try!(components.pop().ok_or(::errors::NfsError::ParameterIsNotValid)).to_string();
+        let mut parent = try!(self.get(root.get_key()));
+        for component in &components {
+            parent = try!(self.get(try!(parent.find_sub_directory(&component.to_string())
+                                              .ok_or(::errors::NfsError::DirectoryNotFound(None))).get_key()));
+        }
+        let local_path = components.iter().fold(local_root.to_path_buf(), |path, component| path.join(component)).join(&name);
+
+        let file_helper = self.get_file_helper();
+        match entry.action {
+            ::helper::sync_plan::SyncAction::CreateDirectory => {
+                let type_tag = parent.get_key().get_type_tag();
+                let versioned = parent.get_key().is_versioned();
+                let access_level = parent.get_key().get_access_level().clone();
+                let _ = try!(self.create(name, type_tag, Vec::new(), versioned, access_level, Some(&mut parent)));
+            },
+            ::helper::sync_plan::SyncAction::Upload => {
+                let writer = try!(file_helper.create(name, Vec::new(), parent));
+                try!(upload_local_file(writer, &local_path));
+            },
+            ::helper::sync_plan::SyncAction::Update => {
+                let file = try!(parent.find_file(&name).ok_or(::errors::NfsError::FileNotFound)).clone();
+                let writer = try!(file_helper.update_content(file, ::helper::writer::Mode::Overwrite, parent));
+                try!(upload_local_file(writer, &local_path));
+            },
+            ::helper::sync_plan::SyncAction::DeleteFile => {
+                let _ = try!(file_helper.delete(name, &mut parent));
+            },
+            ::helper::sync_plan::SyncAction::DeleteDirectory => {
+                let _ = try!(self.delete(&mut parent, &name));
+            },
+        }
+        Ok(())
+    }
+
+    /// Returns a FileHelper holding the same shared key as this helper
+    fn get_file_helper(&self) -> ::helper::file_helper::FileHelper {
+        ::helper::file_helper::FileHelper::from_parts(self.client.clone(), self.shared_key.clone(), self.read_only)
+            .with_chunk_storage(self.storage.clone())
+    }
+
+    /// Returns NfsError::ReadOnly if the helper is in read-only mode
+    fn check_writable(&self) -> Result<(), ::errors::NfsError> {
+        if self.read_only {
+            return Err(::errors::NfsError::ReadOnly);
+        }
+        Ok(())
+    }
+
+    /// Returns the shared key Shared directories are encrypted with, or NfsError::MissingSharedKey if the helper has none
+    fn get_shared_key(&self) -> Result<&::sodiumoxide::crypto::secretbox::Key, ::errors::NfsError> {
+        self.shared_key.as_ref().ok_or(::errors::NfsError::MissingSharedKey)
+    }
+
+
+    /// Creates a StructuredData in the Network
+    /// The StructuredData is created based on the version and AccessLevel of the DirectoryListing
+    fn save_directory_listing(&self, directory: &::directory_listing::DirectoryListing) -> Result<::routing::structured_data::StructuredData, ::errors::NfsError> {
+        try!(self.check_writable());
+        try!(directory.validate());
+        let signing_key = try!(eval_result!(self.client.lock()).get_secret_signing_key()).clone();
+        let owner_key = try!(eval_result!(self.client.lock()).get_public_signing_key()).clone();
+        let access_level = directory.get_key().get_access_level();
+        let versioned = directory.get_key().is_versioned();
+
+        if versioned {
+            let serialised_data = match *access_level {
+                ::AccessLevel::Private => try!(directory.encrypt(self.client.clone())),
+                ::AccessLevel::Public => try!(directory.serialise()),
+                ::AccessLevel::Shared => try!(directory.encrypt_with_shared_key(self.client.clone(), try!(self.get_shared_key()))),
+            };
+            let version = try!(self.save_as_immutable_data(serialised_data,
+                                                           ::routing::immutable_data::ImmutableDataType::Normal));
+            Ok(try!(::safe_core::structured_data_operations::versioned::create(& *eval_result!(self.client.lock()),
+                                                                                 version,
+                                                                                 directory.get_key().get_type_tag(),
+                                                                                 directory.get_key().get_id().clone(),
+                                                                                 0,
+                                                                                 vec![owner_key],
+                                                                                 Vec::new(),
+                                                                                 &signing_key)))
+        } else {
+            let private_key = try!(eval_result!(self.client.lock()).get_public_encryption_key()).clone();
+            let secret_key = try!(eval_result!(self.client.lock()).get_secret_encryption_key()).clone();
+            let nonce = directory.get_key().get_encryption_nonce();
+            let mut serialised_data = try!(directory.serialise());
+
+            let encryption_keys = match *access_level {
+                ::AccessLevel::Private => Some((&private_key,
+                                                &secret_key,
+                                                &nonce)),
+                ::AccessLevel::Public => None,
+                ::AccessLevel::Shared => {
+                    serialised_data = ::directory_listing::DirectoryListing::seal_with_shared_key(try!(self.get_shared_key()),
+                                                                                                  &serialised_data);
+                    None
+                },
+            };
+            Ok(try!(::safe_core::structured_data_operations::unversioned::create(self.client.clone(),
+                                                                                   directory.get_key().get_type_tag(),
+                                                                                   directory.get_key().get_id().clone(),
+                                                                                   0,
+                                                                                   serialised_data,
+                                                                                   vec![owner_key.clone()],
+                                                                                   Vec::new(),
+                                                                                   &signing_key,
+                                                                                   encryption_keys)))
+        }
+    }
+
+    fn update_directory_listing(&self, directory: &::directory_listing::DirectoryListing) -> Result<(), ::errors::NfsError> {
+        try!(self.check_writable());
+        try!(directory.validate());
+        let structured_data = try!(self.get_structured_data(directory.get_key().get_id(), directory.get_key().get_type_tag()));
+
+        let signing_key = try!(eval_result!(self.client.lock()).get_secret_signing_key()).clone();
+        let owner_key = try!(eval_result!(self.client.lock()).get_public_signing_key()).clone();
+        let access_level = directory.get_key().get_access_level();
+        let versioned = directory.get_key().is_versioned();
+
+        let updated_structured_data = if versioned {
+            let serialised_data = match *access_level {
+                ::AccessLevel::Private => try!(directory.encrypt(self.client.clone())),
+                ::AccessLevel::Public => try!(directory.serialise()),
+                ::AccessLevel::Shared => try!(directory.encrypt_with_shared_key(self.client.clone(), try!(self.get_shared_key()))),
+            };
+            let version = try!(self.save_as_immutable_data(serialised_data,
+                                                           ::routing::immutable_data::ImmutableDataType::Normal));
+            try!(::safe_core::structured_data_operations::versioned::append_version(&mut *eval_result!(self.client.lock()),
+                                                                                      structured_data,
+                                                                                      version,
+                                                                                      &signing_key))
+        } else {
+            let private_key = try!(eval_result!(self.client.lock()).get_public_encryption_key()).clone();
+            let secret_key = try!(eval_result!(self.client.lock()).get_secret_encryption_key()).clone();
+            let nonce = directory.get_key().get_encryption_nonce();
+            let mut serialised_data = try!(directory.serialise());
+
+            let encryption_keys = match *access_level {
+                ::AccessLevel::Private => Some((&private_key,
+                                                &secret_key,
+                                                &nonce)),
                 ::AccessLevel::Public => None,
+                ::AccessLevel::Shared => {
+                    serialised_data = ::directory_listing::DirectoryListing::seal_with_shared_key(try!(self.get_shared_key()),
+                                                                                                  &serialised_data);
+                    None
+                },
             };
             try!(::safe_core::structured_data_operations::unversioned::create(self.client.clone(),
                                                                                 directory.get_key().get_type_tag(),
@@ -319,7 +1785,7 @@ impl DirectoryHelper {
         let request = ::routing::data::DataRequest::StructuredData(id.clone(), type_tag);
         debug!("Getting structured data from the network ...");
         let response_getter = eval_result!(self.client.lock()).get(request, None);
-        match try!(response_getter.get()) {
+        match try!(::helper::timeout::get_response(response_getter, self.timeout)) {
             ::routing::data::Data::StructuredData(structured_data) => Ok(structured_data),
             _ => Err(::errors::NfsError::from(::safe_core::errors::CoreError::ReceivedUnexpectedData)),
         }
@@ -332,21 +1798,101 @@ impl DirectoryHelper {
         let request = ::routing::data::DataRequest::ImmutableData(id, data_type);
         debug!("Getting immutable data from the network ...");
         let response_getter = eval_result!(self.client.lock()).get(request, None);
-        match try!(response_getter.get()) {
+        match try!(::helper::timeout::get_response(response_getter, self.timeout)) {
             ::routing::data::Data::ImmutableData(immutable_data) => Ok(immutable_data),
             _ => Err(::errors::NfsError::from(::safe_core::errors::CoreError::ReceivedUnexpectedData)),
         }
     }
 }
 
+/// Returns NfsError::DirectoryNotFound holding the directory_key if the error is the network reporting that it holds no
+/// data for the directory, else the error as it is
+fn directory_not_found(error        : ::errors::NfsError,
+                       directory_key: &::metadata::directory_key::DirectoryKey) -> ::errors::NfsError {
+    match error {
+        ::errors::NfsError::CoreError(::safe_core::errors::CoreError::GetFailure { reason: ::routing::error::ResponseError::NoData, .. }) => {
+            debug!("Directory with tag {:?} and id {:?} not found ...", directory_key.get_type_tag(), directory_key.get_id());
+            ::errors::NfsError::DirectoryNotFound(Some(directory_key.clone()))
+        },
+        error => error,
+    }
+}
+
+/// Runs the operation on each of the items, on up to batch_size threads at a time, and returns the results in the order
+/// of the items. An operation which panics gives an error in place of its result.
+fn run_batched<I, T, F>(items     : Vec<I>,
+                        batch_size: usize,
+                        operation : F) -> Vec<Result<T, ::errors::NfsError>>
+        where I: Send + 'static, T: Send + 'static, F: Fn(I) -> Result<T, ::errors::NfsError> + Send + Sync + 'static {
+    let operation = ::std::sync::Arc::new(operation);
+    let mut results = Vec::with_capacity(items.len());
+    let mut pending_items = items.into_iter();
+    loop {
+        let batch: Vec<::std::thread::JoinHandle<Result<T, ::errors::NfsError>>> =
+            pending_items.by_ref().take(batch_size).map(|item| {
+                let operation = operation.clone();
+                ::std::thread::spawn(move || operation(item))
+            }).collect();
+        if batch.is_empty() {
+            return results;
+        }
+        for handle in batch {
+            results.push(match handle.join() {
+                Ok(result) => result,
+                Err(_) => Err(::errors::NfsError::from("Batched operation panicked")),
+            });
+        }
+    }
+}
+
+/// Self-encrypts and uploads the content, returning the File holding it
+fn upload_file(storage  : ::std::sync::Arc<::helper::chunk_storage::ChunkStorage>,
+               file_name: String,
+               content  : Vec<u8>) -> Result<::file::File, ::errors::NfsError> {
+    let mut self_encryptor = ::self_encryption::SelfEncryptor::new(::helper::chunk_storage::SelfEncryptionChunkStorage::new(storage),
+                                                                   ::self_encryption::datamap::DataMap::None);
+    self_encryptor.write(&content, 0);
+    let mut file = try!(::file::File::new(::metadata::file_metadata::FileMetadata::new(file_name, Vec::new()), self_encryptor.close()));
+    file.get_mut_metadata().set_size(content.len() as u64);
+    Ok(file)
+}
+
+/// Returns the modified time of a local file in seconds since the epoch
+fn local_modified_time(metadata: &::std::fs::Metadata) -> Result<i64, ::errors::NfsError> {
+    let modified = try!(metadata.modified());
+    Ok(modified.duration_since(::std::time::UNIX_EPOCH).map(|duration| duration.as_secs() as i64).unwrap_or(0))
+}
+
+/// Streams the content of the local file through the writer and closes it
+fn upload_local_file(mut writer: ::helper::writer::Writer, local_path: &::std::path::Path) -> Result<(), ::errors::NfsError> {
+    use ::std::io::Read;
+    let mut local_file = try!(::std::fs::File::open(local_path));
+    let mut buffer = vec![0u8; STREAM_BUFFER_SIZE as usize];
+    let mut position = 0;
+    loop {
+        let length = try!(local_file.read(&mut buffer));
+        if length == 0 {
+            break;
+        }
+        writer.write(&buffer[..length], position);
+        position += length as u64;
+    }
+    let _ = try!(writer.close());
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
 
+    fn get_client() -> ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>> {
+        let test_client = eval_result!(::safe_core::utility::test_utils::get_client());
+        ::std::sync::Arc::new(::std::sync::Mutex::new(test_client))
+    }
+
     #[test]
     fn create_dir_listing() {
-        let test_client = eval_result!(::safe_core::utility::test_utils::get_client());
-        let client = ::std::sync::Arc::new(::std::sync::Mutex::new(test_client));
+        let client = get_client();
         let dir_helper = DirectoryHelper::new(client.clone());
         // Create a Directory
         let (mut directory, grand_parent) = eval_result!(dir_helper.create("DirName".to_string(),
@@ -368,6 +1914,8 @@ mod test {
         // Assert whether parent is updated
         let parent = eval_result!(dir_helper.get(directory.get_key()));
         assert!(parent.find_sub_directory(child_directory.get_metadata().get_name()).is_some());
+        // The parent passed in is updated in place to match the saved parent
+        assert_eq!(parent, directory);
 
         let (grand_child_directory, grand_parent) = eval_result!(dir_helper.create("Grand Child".to_string(),
                                                                                    ::VERSIONED_DIRECTORY_LISTING_TAG,
@@ -391,8 +1939,7 @@ mod test {
     fn create_versioned_public_directory() {
         let public_directory;
         {
-            let test_client = eval_result!(::safe_core::utility::test_utils::get_client());
-            let client = ::std::sync::Arc::new(::std::sync::Mutex::new(test_client));
+            let client = get_client();
             let dir_helper = DirectoryHelper::new(client.clone());
             let (directory, _) = eval_result!(dir_helper.create("PublicDirectory".to_string(),
                                                                 ::VERSIONED_DIRECTORY_LISTING_TAG,
@@ -403,8 +1950,7 @@ mod test {
             public_directory = directory;
         }
         {
-            let test_client = eval_result!(::safe_core::utility::test_utils::get_client());
-            let client = ::std::sync::Arc::new(::std::sync::Mutex::new(test_client));
+            let client = get_client();
             let dir_helper = DirectoryHelper::new(client.clone());
             let retrieved_public_directory = eval_result!(dir_helper.get(public_directory.get_key()));
             assert_eq!(retrieved_public_directory, public_directory);
@@ -415,8 +1961,7 @@ mod test {
     fn create_unversioned_public_directory() {
         let public_directory;
         {
-            let test_client = eval_result!(::safe_core::utility::test_utils::get_client());
-            let client = ::std::sync::Arc::new(::std::sync::Mutex::new(test_client));
+            let client = get_client();
             let dir_helper = DirectoryHelper::new(client.clone());
             let (directory, _) = eval_result!(dir_helper.create("PublicDirectory".to_string(),
                                                                 ::UNVERSIONED_DIRECTORY_LISTING_TAG,
@@ -427,8 +1972,7 @@ mod test {
             public_directory = directory;
         }
         {
-            let test_client = eval_result!(::safe_core::utility::test_utils::get_client());
-            let client = ::std::sync::Arc::new(::std::sync::Mutex::new(test_client));
+            let client = get_client();
             let dir_helper = DirectoryHelper::new(client.clone());
             let retrieved_public_directory = eval_result!(dir_helper.get(public_directory.get_key()));
             assert_eq!(retrieved_public_directory, public_directory);
@@ -437,8 +1981,7 @@ mod test {
 
     #[test]
     fn user_root_configuration() {
-        let test_client = eval_result!(::safe_core::utility::test_utils::get_client());
-        let client = ::std::sync::Arc::new(::std::sync::Mutex::new(test_client));
+        let client = get_client();
         let dir_helper = DirectoryHelper::new(client.clone());
 
         let mut root_dir = eval_result!(dir_helper.get_user_root_directory_listing());
@@ -454,8 +1997,7 @@ mod test {
 
     #[test]
     fn configuration_directory() {
-        let test_client = eval_result!(::safe_core::utility::test_utils::get_client());
-        let client = ::std::sync::Arc::new(::std::sync::Mutex::new(test_client));
+        let client = get_client();
         let dir_helper = DirectoryHelper::new(client.clone());
         let config_dir = eval_result!(dir_helper.get_configuration_directory_listing("DNS".to_string()));
         assert_eq!(config_dir.get_metadata().get_name().clone(), "DNS".to_string());
@@ -466,8 +2008,7 @@ mod test {
 
     #[test]
     fn update_and_versioning() {
-        let test_client = eval_result!(::safe_core::utility::test_utils::get_client());
-        let client = ::std::sync::Arc::new(::std::sync::Mutex::new(test_client));
+        let client = get_client();
         let dir_helper = DirectoryHelper::new(client.clone());
 
         let (mut dir_listing, _) = eval_result!(dir_helper.create("DirName2".to_string(),
@@ -486,21 +2027,19 @@ mod test {
         versions = eval_result!(dir_helper.get_versions(dir_listing.get_key().get_id(), dir_listing.get_key().get_type_tag()));
         assert_eq!(versions.len(), 2);
 
-        let rxd_dir_listing = eval_result!(dir_helper.get_by_version(dir_listing.get_key().get_id(),
-                                                                     dir_listing.get_key().get_access_level(),
-                                                                     versions[versions.len() - 1].clone()));
+        let rxd_dir_listing = eval_result!(dir_helper.get_by_version(dir_listing.get_key(), versions[versions.len() - 1].clone()));
         assert_eq!(rxd_dir_listing, dir_listing);
 
-        let rxd_dir_listing = eval_result!(dir_helper.get_by_version(dir_listing.get_key().get_id(),
-                                                                     dir_listing.get_key().get_access_level(),
-                                                                     versions[0].clone()));
+        let rxd_dir_listing = eval_result!(dir_helper.get_by_version(dir_listing.get_key(), versions[0].clone()));
         assert_eq!(*rxd_dir_listing.get_metadata().get_name(), "DirName2".to_string());
+
+        let versions_desc = eval_result!(dir_helper.get_versions_desc(dir_listing.get_key().get_id(), dir_listing.get_key().get_type_tag()));
+        assert_eq!(versions_desc, vec![versions[1], versions[0]]);
     }
 
     #[test]
     fn delete_directory() {
-        let test_client = eval_result!(::safe_core::utility::test_utils::get_client());
-        let client = ::std::sync::Arc::new(::std::sync::Mutex::new(test_client));
+        let client = get_client();
         let dir_helper = DirectoryHelper::new(client.clone());
         // Create a Directory
         let (mut directory, grand_parent) = eval_result!(dir_helper.create("DirName".to_string(),
@@ -540,4 +2079,1642 @@ mod test {
         assert!(delete_result.is_none());
     }
 
+    #[test]
+    fn create_tree_transactional() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+        let created_keys = eval_result!(dir_helper.create_tree_transactional(&mut directory,
+                                                                             &["src", "src/helper", "docs"],
+                                                                             ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                             true,
+                                                                             ::AccessLevel::Private));
+        assert_eq!(created_keys.len(), 3);
+        assert_eq!(directory.get_sub_directories().len(), 2);
+        let src = eval_result!(dir_helper.get(eval_option!(directory.find_sub_directory(&"src".to_string()), "Directory not found").get_key()));
+        assert!(src.find_sub_directory(&"helper".to_string()).is_some());
+
+        // A failing entry rolls back the directories created before it
+        match dir_helper.create_tree_transactional(&mut directory,
+                                                   &["tests", "tests/unit", "missing/child"],
+                                                   ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                   true,
+                                                   ::AccessLevel::Private) {
+            Err(::errors::NfsError::TreeCreationRolledBack { error, unlinked_directories }) => {
+                assert_eq!(*error, ::errors::NfsError::DirectoryNotFound(None));
+                assert_eq!(unlinked_directories.len(), 2);
+            },
+            _ => panic!("Expected TreeCreationRolledBack"),
+        }
+        assert!(directory.find_sub_directory(&"tests".to_string()).is_none());
+        assert_eq!(directory.get_sub_directories().len(), 2);
+    }
+
+    #[test]
+    fn get_auto() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (versioned_directory, _) = eval_result!(dir_helper.create("Versioned".to_string(),
+                                                                      ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                      Vec::new(),
+                                                                      true,
+                                                                      ::AccessLevel::Private,
+                                                                      None));
+        let (unversioned_directory, _) = eval_result!(dir_helper.create("Unversioned".to_string(),
+                                                                        ::UNVERSIONED_DIRECTORY_LISTING_TAG,
+                                                                        Vec::new(),
+                                                                        false,
+                                                                        ::AccessLevel::Public,
+                                                                        None));
+        assert_eq!(eval_result!(dir_helper.get_auto(versioned_directory.get_key().get_id(),
+                                                    ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                    &::AccessLevel::Private)),
+                   versioned_directory);
+        assert_eq!(eval_result!(dir_helper.get_auto(unversioned_directory.get_key().get_id(),
+                                                    ::UNVERSIONED_DIRECTORY_LISTING_TAG,
+                                                    &::AccessLevel::Public)),
+                   unversioned_directory);
+        assert!(dir_helper.get_auto(versioned_directory.get_key().get_id(), 10, &::AccessLevel::Private).is_err());
+    }
+
+    #[test]
+    fn prune_versions() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut dir_listing, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                                  ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                  Vec::new(),
+                                                                  true,
+                                                                  ::AccessLevel::Private,
+                                                                  None));
+        for index in 0..3 {
+            dir_listing.get_mut_metadata().set_user_metadata(vec![index; 10]);
+            let _ = eval_result!(dir_helper.update(&dir_listing));
+        }
+        let key = dir_listing.get_key().clone();
+        let versions = eval_result!(dir_helper.get_versions(key.get_id(), key.get_type_tag()));
+        assert_eq!(versions.len(), 4);
+        let stored_version = eval_result!(dir_helper.get_structured_data(key.get_id(), key.get_type_tag())).get_version();
+
+        // Three versions are kept, which takes more than one appended version to rebuild the history
+        eval_result!(dir_helper.prune_versions(&key, &versions[1..2]));
+        let retained_versions = vec![versions[0], versions[2], versions[3]];
+        assert_eq!(eval_result!(dir_helper.get_versions(key.get_id(), key.get_type_tag())), retained_versions);
+        assert_eq!(eval_result!(dir_helper.get_structured_data(key.get_id(), key.get_type_tag())).get_version(), stored_version + 1);
+        assert_eq!(*eval_result!(dir_helper.get(&key)).get_metadata().get_user_metadata(), vec![2u8; 10]);
+        assert_eq!(*eval_result!(dir_helper.get_by_version(&key, versions[2])).get_metadata().get_user_metadata(), vec![1u8; 10]);
+
+        eval_result!(dir_helper.prune_versions(&key, &versions[..1]));
+        assert_eq!(eval_result!(dir_helper.get_versions(key.get_id(), key.get_type_tag())), vec![versions[2], versions[3]]);
+
+        match dir_helper.prune_versions(&key, &versions[3..]) {
+            Err(::errors::NfsError::ParameterIsNotValid) => (),
+            _ => panic!("Expected ParameterIsNotValid"),
+        }
+    }
+
+    #[test]
+    fn list_version_chunks() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut dir_listing, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                                  ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                  Vec::new(),
+                                                                  true,
+                                                                  ::AccessLevel::Private,
+                                                                  None));
+        dir_listing.get_mut_metadata().set_name("NewName".to_string());
+        let _ = eval_result!(dir_helper.update(&dir_listing));
+
+        let versions = eval_result!(dir_helper.get_versions(dir_listing.get_key().get_id(), dir_listing.get_key().get_type_tag()));
+        let names = eval_result!(dir_helper.list_version_chunks(dir_listing.get_key()));
+        for version in versions.iter() {
+            assert!(names.contains(version));
+        }
+    }
+
+    #[test]
+    fn create_with_key() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut parent, _) = eval_result!(dir_helper.create("Parent".to_string(),
+                                                             ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                             Vec::new(),
+                                                             true,
+                                                             ::AccessLevel::Private,
+                                                             None));
+        let id = ::routing::NameType::new(eval_result!(::safe_core::utility::generate_random_array_u8_64()));
+        let directory_key = ::metadata::directory_key::DirectoryKey::new(id, ::VERSIONED_DIRECTORY_LISTING_TAG, true, ::AccessLevel::Private);
+        let (restored, _) = eval_result!(dir_helper.create_with_key(directory_key.clone(),
+                                                                    "Restored".to_string(),
+                                                                    Vec::new(),
+                                                                    Some(&mut parent)));
+        assert_eq!(*restored.get_key(), directory_key);
+        assert_eq!(eval_result!(dir_helper.get(&directory_key)), restored);
+        assert!(parent.find_sub_directory(&"Restored".to_string()).is_some());
+
+        // Restoring again is idempotent
+        let (restored_again, _) = eval_result!(dir_helper.create_with_key(directory_key.clone(),
+                                                                          "Restored".to_string(),
+                                                                          Vec::new(),
+                                                                          Some(&mut parent)));
+        assert_eq!(restored_again, restored);
+        assert_eq!(parent.get_sub_directories().len(), 1);
+
+        // A different key under the same name conflicts before anything is saved
+        let id = ::routing::NameType::new(eval_result!(::safe_core::utility::generate_random_array_u8_64()));
+        let other_key = ::metadata::directory_key::DirectoryKey::new(id, ::VERSIONED_DIRECTORY_LISTING_TAG, true, ::AccessLevel::Private);
+        match dir_helper.create_with_key(other_key.clone(), "Restored".to_string(), Vec::new(), Some(&mut parent)) {
+            Err(::errors::NfsError::SubDirectoryKeyConflict) => (),
+            _ => panic!("Expected SubDirectoryKeyConflict"),
+        }
+        match dir_helper.get(&other_key) {
+            Err(::errors::NfsError::DirectoryNotFound(_)) => (),
+            _ => panic!("Expected DirectoryNotFound"),
+        }
+    }
+
+    #[test]
+    fn export_tar() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut directory, _) = eval_result!(dir_helper.create("Root".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+        let (child, _) = eval_result!(dir_helper.create("Child".to_string(),
+                                                        ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                        Vec::new(),
+                                                        true,
+                                                        ::AccessLevel::Private,
+                                                        Some(&mut directory)));
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        let mut writer = eval_result!(file_helper.create("hello.txt".to_string(), Vec::new(), child));
+        writer.write("Hello World!".as_bytes(), 0);
+        let _ = eval_result!(writer.close());
+        let directory = eval_result!(dir_helper.get(directory.get_key()));
+
+        let mut archive = Vec::new();
+        eval_result!(dir_helper.export_tar(&directory, &mut archive));
+        // Root, Child and hello.txt headers, one block of content and two end blocks
+        assert_eq!(archive.len(), 6 * 512);
+        assert_eq!(&archive[0..5], "Root/".as_bytes());
+        assert_eq!(archive[156], b'5');
+        assert_eq!(&archive[512..523], "Root/Child/".as_bytes());
+        assert_eq!(&archive[1024..1044], "Root/Child/hello.txt".as_bytes());
+        assert_eq!(archive[1024 + 156], b'0');
+        assert_eq!(&archive[1536..1548], "Hello World!".as_bytes());
+        assert!(archive[3 * 512..].iter().skip(12).all(|byte| *byte == 0));
+    }
+
+    #[test]
+    fn import_tar() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (source, _) = eval_result!(dir_helper.create("Source".to_string(),
+                                                         ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                         Vec::new(),
+                                                         true,
+                                                         ::AccessLevel::Private,
+                                                         None));
+        let (mut destination, _) = eval_result!(dir_helper.create("Destination".to_string(),
+                                                                  ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                  Vec::new(),
+                                                                  true,
+                                                                  ::AccessLevel::Private,
+                                                                  None));
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        let mut writer = eval_result!(file_helper.create("hello.txt".to_string(), Vec::new(), source.clone()));
+        writer.write("Hello World!".as_bytes(), 0);
+        let _ = eval_result!(writer.close());
+        let source = eval_result!(dir_helper.get(source.get_key()));
+
+        let mut archive = Vec::new();
+        eval_result!(dir_helper.export_tar(&source, &mut archive));
+        eval_result!(dir_helper.import_tar(&mut destination, ::AccessLevel::Private, &archive[..]));
+
+        // The archive nests everything under "Source", which is created as an intermediate directory
+        let imported = eval_result!(dir_helper.get(eval_option!(destination.find_sub_directory(&"Source".to_string()),
+                                                                "Directory not imported").get_key()));
+        let file = eval_option!(imported.find_file(&"hello.txt".to_string()), "File not imported");
+        let mut reader = file_helper.read(file);
+        let size = reader.size();
+        assert_eq!(eval_result!(reader.read(0, size)), "Hello World!".as_bytes().to_vec());
+
+        // A truncated archive is reported as a local IO error
+        match dir_helper.import_tar(&mut destination, ::AccessLevel::Private, &archive[..1000]) {
+            Err(::errors::NfsError::LocalIo(_)) => (),
+            _ => panic!("Expected LocalIo"),
+        }
+
+        // A leading `/` is dropped, so the entry lands under the destination directory
+        let mut archive = Vec::new();
+        eval_result!(::helper::tar::write_header(&mut archive, "/absolute.txt", 3, 0, false));
+        archive.extend(b"abs".iter().cloned());
+        eval_result!(::helper::tar::write_padding(&mut archive, 3));
+        eval_result!(::helper::tar::write_end(&mut archive));
+        eval_result!(dir_helper.import_tar(&mut destination, ::AccessLevel::Private, &archive[..]));
+        assert!(destination.find_file(&"absolute.txt".to_string()).is_some());
+
+        // A `..` component would escape the destination directory
+        let mut archive = Vec::new();
+        eval_result!(::helper::tar::write_header(&mut archive, "Source/../../escaped.txt", 3, 0, false));
+        archive.extend(b"esc".iter().cloned());
+        eval_result!(::helper::tar::write_padding(&mut archive, 3));
+        eval_result!(::helper::tar::write_end(&mut archive));
+        match dir_helper.import_tar(&mut destination, ::AccessLevel::Private, &archive[..]) {
+            Err(::errors::NfsError::InvalidName) => (),
+            _ => panic!("Expected InvalidName"),
+        }
+        assert!(destination.find_file(&"escaped.txt".to_string()).is_none());
+    }
+
+    #[test]
+    fn diff_against_local() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        let mut writer = eval_result!(file_helper.create("changed.txt".to_string(), Vec::new(), directory.clone()));
+        writer.write("old".as_bytes(), 0);
+        let (directory, _) = eval_result!(writer.close());
+        let mut writer = eval_result!(file_helper.create("removed.txt".to_string(), Vec::new(), directory));
+        writer.write("removed".as_bytes(), 0);
+        let (directory, _) = eval_result!(writer.close());
+
+        let local_root = ::std::env::temp_dir().join(format!("safe_nfs_diff_{}", ::time::precise_time_ns()));
+        eval_result!(::std::fs::create_dir_all(local_root.join("added")));
+        {
+            use ::std::io::Write;
+            eval_result!(eval_result!(::std::fs::File::create(local_root.join("changed.txt"))).write_all("Hello World!".as_bytes()));
+            eval_result!(eval_result!(::std::fs::File::create(local_root.join("added").join("new.txt"))).write_all("new".as_bytes()));
+        }
+
+        let plan = dir_helper.diff_against_local(&directory, &local_root);
+        let _ = ::std::fs::remove_dir_all(&local_root);
+        let plan = eval_result!(plan);
+
+        let entries: Vec<(&str, ::helper::sync_plan::SyncAction)> = plan.get_entries().iter().map(|entry| (&entry.path[..], entry.action)).collect();
+        assert_eq!(entries, vec![("added", ::helper::sync_plan::SyncAction::CreateDirectory),
+                                 ("added/new.txt", ::helper::sync_plan::SyncAction::Upload),
+                                 ("changed.txt", ::helper::sync_plan::SyncAction::Update),
+                                 ("removed.txt", ::helper::sync_plan::SyncAction::DeleteFile)]);
+    }
+
+    #[test]
+    fn diff_against_local_with_replaced_entries() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+        let _ = eval_result!(dir_helper.create("now_file".to_string(),
+                                               ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                               Vec::new(),
+                                               true,
+                                               ::AccessLevel::Private,
+                                               Some(&mut directory)));
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        let mut writer = eval_result!(file_helper.create("now_directory".to_string(), Vec::new(), directory));
+        writer.write("file".as_bytes(), 0);
+        let (mut directory, _) = eval_result!(writer.close());
+
+        let local_root = ::std::env::temp_dir().join(format!("safe_nfs_replaced_{}", ::time::precise_time_ns()));
+        eval_result!(::std::fs::create_dir_all(local_root.join("now_directory")));
+        {
+            use ::std::io::Write;
+            eval_result!(eval_result!(::std::fs::File::create(local_root.join("now_file"))).write_all("file".as_bytes()));
+        }
+
+        let plan = dir_helper.diff_against_local(&directory, &local_root);
+        let plan = eval_result!(plan);
+        let entries: Vec<(&str, ::helper::sync_plan::SyncAction)> = plan.get_entries().iter().map(|entry| (&entry.path[..], entry.action)).collect();
+        assert_eq!(entries, vec![("now_directory", ::helper::sync_plan::SyncAction::DeleteFile),
+                                 ("now_directory", ::helper::sync_plan::SyncAction::CreateDirectory),
+                                 ("now_file", ::helper::sync_plan::SyncAction::DeleteDirectory),
+                                 ("now_file", ::helper::sync_plan::SyncAction::Upload)]);
+
+        let report = dir_helper.apply_sync_plan(plan, &mut directory, &local_root);
+        let _ = ::std::fs::remove_dir_all(&local_root);
+        assert!(eval_result!(report).is_success());
+        assert!(directory.find_sub_directory(&"now_directory".to_string()).is_some());
+        assert!(directory.find_file(&"now_directory".to_string()).is_none());
+        assert!(directory.find_file(&"now_file".to_string()).is_some());
+        assert!(directory.find_sub_directory(&"now_file".to_string()).is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn diff_against_local_keeps_entries_of_symlinks() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        let mut writer = eval_result!(file_helper.create("linked.txt".to_string(), Vec::new(), directory));
+        writer.write("linked".as_bytes(), 0);
+        let (directory, _) = eval_result!(writer.close());
+
+        let local_root = ::std::env::temp_dir().join(format!("safe_nfs_symlink_{}", ::time::precise_time_ns()));
+        eval_result!(::std::fs::create_dir_all(&local_root));
+        eval_result!(::std::os::unix::fs::symlink(local_root.join("missing_target"), local_root.join("linked.txt")));
+
+        let plan = dir_helper.diff_against_local(&directory, &local_root);
+        let _ = ::std::fs::remove_dir_all(&local_root);
+        assert!(eval_result!(plan).is_empty());
+    }
+
+    #[test]
+    fn apply_sync_plan() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+
+        let local_root = ::std::env::temp_dir().join(format!("safe_nfs_sync_{}", ::time::precise_time_ns()));
+        eval_result!(::std::fs::create_dir_all(local_root.join("nested")));
+        {
+            use ::std::io::Write;
+            eval_result!(eval_result!(::std::fs::File::create(local_root.join("nested").join("hello.txt"))).write_all("Hello World!".as_bytes()));
+        }
+
+        let mut plan = eval_result!(dir_helper.diff_against_local(&directory, &local_root));
+        assert_eq!(plan.get_entries().len(), 2);
+        // An entry which can not be applied must not stop the rest of the plan
+        plan.push("missing.txt".to_string(), ::helper::sync_plan::SyncAction::Upload);
+        let report = dir_helper.apply_sync_plan(plan, &mut directory, &local_root);
+        let resynced_plan = dir_helper.diff_against_local(&directory, &local_root);
+        let _ = ::std::fs::remove_dir_all(&local_root);
+        let report = eval_result!(report);
+
+        assert!(!report.is_success());
+        assert_eq!(report.get_succeeded().len(), 2);
+        assert_eq!(report.get_failed().len(), 1);
+        assert_eq!(report.get_failed()[0].0.path, "missing.txt".to_string());
+        match report.get_failed()[0].1 {
+            ::errors::NfsError::LocalIo(_) => (),
+            _ => panic!("Expected LocalIo"),
+        }
+        assert!(eval_result!(resynced_plan).is_empty());
+
+        let nested = eval_result!(dir_helper.get(eval_option!(directory.find_sub_directory(&"nested".to_string()),
+                                                              "Directory not synced").get_key()));
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        let mut reader = file_helper.read(eval_option!(nested.find_file(&"hello.txt".to_string()), "File not synced"));
+        let size = reader.size();
+        assert_eq!(eval_result!(reader.read(0, size)), "Hello World!".as_bytes().to_vec());
+    }
+
+    #[test]
+    fn versions_iter() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+        directory.get_mut_metadata().set_name("NewName".to_string());
+        let _ = eval_result!(dir_helper.update(&directory));
+
+        let mut versions = eval_result!(dir_helper.versions_iter(directory.get_key()));
+        assert_eq!(versions.size_hint(), (2, Some(2)));
+        let latest_version = eval_option!(versions.next(), "Version not found");
+        assert_eq!(eval_result!(versions.resolve(latest_version)).get_metadata().get_name(), "NewName");
+        let first_version = eval_option!(versions.next(), "Version not found");
+        assert_eq!(eval_result!(versions.resolve(first_version)).get_metadata().get_name(), "DirName");
+        assert!(versions.next().is_none());
+
+        let (unversioned_directory, _) = eval_result!(dir_helper.create("Unversioned".to_string(),
+                                                                        ::UNVERSIONED_DIRECTORY_LISTING_TAG,
+                                                                        Vec::new(),
+                                                                        false,
+                                                                        ::AccessLevel::Private,
+                                                                        None));
+        match dir_helper.versions_iter(unversioned_directory.get_key()) {
+            Err(::errors::NfsError::ParameterIsNotValid) => (),
+            _ => panic!("Expected ParameterIsNotValid"),
+        }
+    }
+
+    #[test]
+    fn create_with_files() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut parent, _) = eval_result!(dir_helper.create("Parent".to_string(),
+                                                             ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                             Vec::new(),
+                                                             true,
+                                                             ::AccessLevel::Private,
+                                                             None));
+        let (directory, failures) = eval_result!(dir_helper.create_with_files("DirName".to_string(),
+                                                                              CreateWithFilesOptions::new(::VERSIONED_DIRECTORY_LISTING_TAG, true, ::AccessLevel::Private).with_all_or_nothing(true),
+                                                                              Some(&mut parent),
+                                                                              vec![("index.html".to_string(), "<html></html>".to_string().into_bytes()),
+                                                                                   ("style.css".to_string(), Vec::new())]));
+        assert!(failures.is_empty());
+        {
+            let sub_directory = eval_option!(parent.find_sub_directory(directory.get_metadata().get_name()), "Directory not found");
+            assert_eq!(sub_directory.get_size(), 13);
+            assert_eq!(sub_directory.get_child_count(), 2);
+        }
+        assert_eq!(directory, eval_result!(dir_helper.get(directory.get_key())));
+        assert_eq!(eval_result!(dir_helper.get_versions(directory.get_key().get_id(), directory.get_key().get_type_tag())).len(), 1);
+
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        let file = eval_option!(directory.find_file(&"index.html".to_string()), "File not found");
+        assert_eq!(file.get_metadata().get_size(), 13);
+        let mut reader = file_helper.read(file);
+        let size = reader.size();
+        assert_eq!(eval_result!(reader.read(0, size)), "<html></html>".to_string().into_bytes());
+        assert!(directory.find_file(&"style.css".to_string()).is_some());
+
+        let duplicates = vec![("a.txt".to_string(), Vec::new()), ("a.txt".to_string(), Vec::new())];
+        match dir_helper.create_with_files("Duplicates".to_string(),
+                                           CreateWithFilesOptions::new(::VERSIONED_DIRECTORY_LISTING_TAG, true, ::AccessLevel::Private).with_all_or_nothing(true),
+                                           Some(&mut parent),
+                                           duplicates.clone()) {
+            Err(::errors::NfsError::FileAlreadyExistsWithSameName) => (),
+            _ => panic!("Expected FileAlreadyExistsWithSameName"),
+        }
+        assert!(parent.find_sub_directory(&"Duplicates".to_string()).is_none());
+
+        // Without all_or_nothing the failing file is reported and the rest are kept
+        let (directory, failures) = eval_result!(dir_helper.create_with_files("Duplicates".to_string(),
+                                                                              CreateWithFilesOptions::new(::VERSIONED_DIRECTORY_LISTING_TAG, true, ::AccessLevel::Private),
+                                                                              Some(&mut parent),
+                                                                              duplicates));
+        assert_eq!(directory.get_files().len(), 1);
+        assert_eq!(failures.len(), 1);
+        match failures[0] {
+            (ref name, ::errors::NfsError::FileAlreadyExistsWithSameName) => assert_eq!(*name, "a.txt".to_string()),
+            _ => panic!("Expected FileAlreadyExistsWithSameName"),
+        }
+    }
+
+    #[test]
+    fn run_batched() {
+        let results = super::run_batched((0..10).collect(), 3, |index: usize| {
+            if index == 4 {
+                panic!("Failing operation");
+            }
+            Ok(index * 2)
+        });
+        assert_eq!(results.len(), 10);
+        for (index, result) in results.into_iter().enumerate() {
+            match result {
+                Ok(value) => assert_eq!(value, index * 2),
+                Err(_) => assert_eq!(index, 4),
+            }
+        }
+    }
+
+    #[test]
+    fn create_with_many_files() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let files: Vec<(String, Vec<u8>)> = (0..100).map(|index| (format!("{}.txt", index), vec![index as u8; 1024])).collect();
+
+        let (directory, failures) = eval_result!(dir_helper.create_with_files("Parallel".to_string(),
+                                                                              CreateWithFilesOptions::new(::VERSIONED_DIRECTORY_LISTING_TAG, true, ::AccessLevel::Private).with_all_or_nothing(true),
+                                                                              None,
+                                                                              files));
+        assert!(failures.is_empty());
+        assert_eq!(directory.get_files().len(), 100);
+        // Files are kept in the order they were passed
+        assert_eq!(*eval_option!(directory.get_file_by_index(42), "File not found").get_name(), "42.txt".to_string());
+        assert_eq!(directory, eval_result!(dir_helper.get(directory.get_key())));
+    }
+
+    #[test]
+    fn validate_tree() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+        let (child, _) = eval_result!(dir_helper.create("Child".to_string(),
+                                                        ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                        Vec::new(),
+                                                        true,
+                                                        ::AccessLevel::Private,
+                                                        Some(&mut directory)));
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        let mut writer = eval_result!(file_helper.create("large.txt".to_string(), Vec::new(), child));
+        writer.write(&vec![7u8; 1024 * 1024], 0);
+        let _ = eval_result!(writer.close());
+        let directory = eval_result!(dir_helper.get(directory.get_key()));
+        assert!(eval_result!(dir_helper.validate_tree(&directory)).is_healthy());
+
+        // A sub directory which was never saved and a file whose chunks were never stored
+        let mut damaged_directory = directory.clone();
+        let missing_directory = eval_result!(::directory_listing::DirectoryListing::new("Missing".to_string(),
+                                                                                         ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                                         Vec::new(),
+                                                                                         true,
+                                                                                         ::AccessLevel::Private,
+                                                                                         None));
+        damaged_directory.upsert_sub_directory(missing_directory.get_metadata().clone());
+        let chunks = (0..3).map(|chunk_num| ::self_encryption::datamap::ChunkDetails {
+            chunk_num  : chunk_num,
+            hash       : vec![chunk_num as u8 + 1; 64],
+            pre_hash   : vec![chunk_num as u8 + 1; 64],
+            source_size: 1024,
+        }).collect();
+        damaged_directory.upsert_file(eval_result!(::file::File::new(::metadata::file_metadata::FileMetadata::new("broken.txt".to_string(), Vec::new()),
+                                                                     ::self_encryption::datamap::DataMap::Chunks(chunks))));
+
+        let report = eval_result!(dir_helper.validate_tree(&damaged_directory));
+        assert!(!report.is_healthy());
+        assert_eq!(report.get_unreachable_directories().len(), 1);
+        assert_eq!(report.get_unreachable_directories()[0].0, "DirName/Missing".to_string());
+        assert_eq!(report.get_broken_files().len(), 1);
+        assert_eq!(report.get_broken_files()[0].0, "DirName/broken.txt".to_string());
+    }
+
+    #[test]
+    fn convert_to_unversioned() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut parent, _) = eval_result!(dir_helper.create("Parent".to_string(),
+                                                             ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                             Vec::new(),
+                                                             true,
+                                                             ::AccessLevel::Private,
+                                                             None));
+        let (mut directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                Some(&mut parent)));
+        let (child, _) = eval_result!(dir_helper.create("Child".to_string(),
+                                                        ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                        Vec::new(),
+                                                        true,
+                                                        ::AccessLevel::Private,
+                                                        Some(&mut directory)));
+
+        let converted_directory = eval_result!(dir_helper.convert_to_unversioned(&directory));
+        assert!(!converted_directory.get_key().is_versioned());
+        assert_eq!(converted_directory.get_key().get_type_tag(), ::UNVERSIONED_DIRECTORY_LISTING_TAG);
+        assert_eq!(converted_directory.get_key().get_id(), directory.get_key().get_id());
+        assert_eq!(converted_directory.get_metadata().get_name(), directory.get_metadata().get_name());
+        let directory = eval_result!(dir_helper.get(directory.get_key()));
+        assert_eq!(converted_directory.get_metadata().get_created_time(), directory.get_metadata().get_created_time());
+        assert_eq!(converted_directory.get_metadata().get_modified_time(), directory.get_metadata().get_modified_time());
+
+        let parent = eval_result!(dir_helper.get(parent.get_key()));
+        let directory_metadata = eval_option!(parent.find_sub_directory(&"DirName".to_string()), "Directory not found");
+        assert_eq!(directory_metadata.get_key(), converted_directory.get_key());
+        let child = eval_result!(dir_helper.get(child.get_key()));
+        assert_eq!(child.get_metadata().get_parent_dir_key(), Some(converted_directory.get_key()));
+        assert!(converted_directory.find_sub_directory(&"Child".to_string()).is_some());
+
+        match dir_helper.convert_to_unversioned(&converted_directory) {
+            Err(::errors::NfsError::ParameterIsNotValid) => (),
+            _ => panic!("Expected ParameterIsNotValid"),
+        }
+    }
+
+    #[test]
+    fn refresh() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+        let mut stale_directory = directory.clone();
+        assert!(!eval_result!(dir_helper.refresh(&mut stale_directory)));
+        assert_eq!(stale_directory, directory);
+
+        let _ = eval_result!(dir_helper.create("Child".to_string(),
+                                               ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                               Vec::new(),
+                                               true,
+                                               ::AccessLevel::Private,
+                                               Some(&mut directory)));
+        assert!(eval_result!(dir_helper.refresh(&mut stale_directory)));
+        assert_eq!(stale_directory, directory);
+        assert!(!eval_result!(dir_helper.refresh(&mut stale_directory)));
+    }
+
+    #[test]
+    fn get_by_version_cached() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+        directory.get_mut_metadata().set_name("NewName".to_string());
+        let _ = eval_result!(dir_helper.update(&directory));
+
+        let versions = eval_result!(dir_helper.get_versions(directory.get_key().get_id(), directory.get_key().get_type_tag()));
+        assert_eq!(versions.len(), 2);
+        let first_version = eval_result!(dir_helper.get_by_version_cached(directory.get_key(), versions[0], &versions));
+        assert_eq!(first_version.get_metadata().get_name(), "DirName");
+        let latest_version = eval_result!(dir_helper.get_by_version_cached(directory.get_key(), versions[1], &versions));
+        assert_eq!(latest_version, directory);
+
+        match dir_helper.get_by_version_cached(directory.get_key(), versions[1], &versions[..1]) {
+            Err(::errors::NfsError::NotFound) => (),
+            _ => panic!("Expected NotFound"),
+        }
+    }
+
+    #[test]
+    fn create_shared_directory() {
+        let client = get_client();
+        let shared_key = ::sodiumoxide::crypto::secretbox::gen_key();
+        let dir_helper = DirectoryHelper::with_shared_key(client.clone(), shared_key.clone());
+        for &(tag_type, versioned) in &[(::VERSIONED_DIRECTORY_LISTING_TAG, true), (::UNVERSIONED_DIRECTORY_LISTING_TAG, false)] {
+            let (directory, _) = eval_result!(dir_helper.create("SharedDirectory".to_string(),
+                                                                tag_type,
+                                                                vec![2u8, 10],
+                                                                versioned,
+                                                                ::AccessLevel::Shared,
+                                                                None));
+            let retrieved_directory = eval_result!(dir_helper.get(directory.get_key()));
+            assert_eq!(retrieved_directory, directory);
+
+            match DirectoryHelper::new(client.clone()).get(directory.get_key()) {
+                Err(::errors::NfsError::MissingSharedKey) => (),
+                _ => panic!("Expected MissingSharedKey"),
+            }
+            let other_helper = DirectoryHelper::with_shared_key(client.clone(), ::sodiumoxide::crypto::secretbox::gen_key());
+            assert!(other_helper.get(directory.get_key()).is_err());
+        }
+    }
+
+    #[test]
+    fn shared_directories() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        assert!(eval_result!(dir_helper.list_shared_directories()).is_empty());
+
+        let mut keys = Vec::new();
+        for name in ["First", "Second"].iter() {
+            let (directory, _) = eval_result!(dir_helper.create(name.to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Public,
+                                                                None));
+            keys.push(directory.get_key().clone());
+        }
+        eval_result!(dir_helper.add_shared_directory("Shared", &eval_result!(keys[0].to_share_token())));
+        assert_eq!(eval_result!(dir_helper.list_shared_directories()), vec![("Shared".to_string(), keys[0].clone())]);
+
+        eval_result!(dir_helper.add_shared_directory("Shared", &eval_result!(keys[1].to_share_token())));
+        assert_eq!(eval_result!(dir_helper.list_shared_directories()), vec![("Shared".to_string(), keys[1].clone())]);
+
+        match dir_helper.add_shared_directory("Invalid", "not a token") {
+            Err(::errors::NfsError::ParameterIsNotValid) => (),
+            _ => panic!("Expected ParameterIsNotValid"),
+        }
+        assert_eq!(eval_result!(dir_helper.list_shared_directories()).len(), 1);
+
+        eval_result!(dir_helper.add_shared_directory("Other", &eval_result!(keys[0].to_share_token())));
+        assert_eq!(eval_result!(dir_helper.list_shared_directories()), vec![("Shared".to_string(), keys[1].clone()),
+                                                                            ("Other".to_string(), keys[0].clone())]);
+        let registry = eval_result!(dir_helper.get_configuration_directory_listing(::SHARED_DIRECTORIES_CONFIGURATION_NAME.to_string()));
+        assert!(registry.get_files().is_empty());
+    }
+
+    #[test]
+    fn list_shared_directories_read_only() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone()).with_read_only(true);
+        assert!(eval_result!(dir_helper.list_shared_directories()).is_empty());
+        assert!(!dir_helper.configuration_root_exists());
+    }
+
+    #[test]
+    fn get_resilient() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        for &(tag_type, versioned) in &[(::VERSIONED_DIRECTORY_LISTING_TAG, true), (::UNVERSIONED_DIRECTORY_LISTING_TAG, false)] {
+            let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                                tag_type,
+                                                                Vec::new(),
+                                                                versioned,
+                                                                ::AccessLevel::Private,
+                                                                None));
+            let retrieved_directory = eval_result!(dir_helper.get_resilient(directory.get_key().get_id(), &::AccessLevel::Private));
+            assert_eq!(retrieved_directory, directory);
+        }
+
+        let id = ::routing::NameType(eval_result!(::safe_core::utility::generate_random_array_u8_64()));
+        match dir_helper.get_resilient(&id, &::AccessLevel::Private) {
+            Err(::errors::NfsError::DirectoryNotFound(None)) => (),
+            _ => panic!("Expected DirectoryNotFound"),
+        }
+    }
+
+    #[test]
+    fn create_with_invalid_name() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        for name in ["a/b", "..", "a\0"].iter() {
+            match dir_helper.create(name.to_string(),
+                                    ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                    Vec::new(),
+                                    true,
+                                    ::AccessLevel::Private,
+                                    None) {
+                Err(::errors::NfsError::InvalidName) => (),
+                _ => panic!("Expected InvalidName"),
+            }
+        }
+    }
+
+    #[test]
+    fn update_propagating() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut root, _) = eval_result!(dir_helper.create("Root".to_string(),
+                                                           ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                           Vec::new(),
+                                                           true,
+                                                           ::AccessLevel::Private,
+                                                           None));
+        let (mut child, _) = eval_result!(dir_helper.create("Child".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            Some(&mut root)));
+        let (mut grand_child, _) = eval_result!(dir_helper.create("GrandChild".to_string(),
+                                                                  ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                  Vec::new(),
+                                                                  true,
+                                                                  ::AccessLevel::Private,
+                                                                  Some(&mut child)));
+        let root_versions = eval_result!(dir_helper.get_versions(root.get_key().get_id(), root.get_key().get_type_tag())).len();
+
+        grand_child.get_mut_metadata().set_user_metadata(vec![1u8; 10]);
+        eval_result!(dir_helper.update_propagating(&grand_child, 5));
+
+        let child = eval_result!(dir_helper.get(child.get_key()));
+        let child_metadata = eval_option!(child.find_sub_directory(&"GrandChild".to_string()), "Directory not found");
+        assert_eq!(*child_metadata.get_user_metadata(), vec![1u8; 10]);
+        let root = eval_result!(dir_helper.get(root.get_key()));
+        assert_eq!(eval_option!(root.find_sub_directory(&"Child".to_string()), "Directory not found"), child.get_metadata());
+        assert_eq!(eval_result!(dir_helper.get_versions(root.get_key().get_id(), root.get_key().get_type_tag())).len(),
+                   root_versions + 1);
+    }
+
+    #[test]
+    fn glob_recursive() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let add_files = |directory: &mut ::directory_listing::DirectoryListing, names: &[&str]| {
+            for name in names {
+                directory.upsert_file(eval_result!(::file::File::new(::metadata::file_metadata::FileMetadata::new(name.to_string(), Vec::new()),
+                                                                     ::self_encryption::datamap::DataMap::None)));
+            }
+            let _ = eval_result!(dir_helper.update(directory));
+        };
+        let (mut root, _) = eval_result!(dir_helper.create("Root".to_string(),
+                                                           ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                           Vec::new(),
+                                                           true,
+                                                           ::AccessLevel::Private,
+                                                           None));
+        let (mut images, _) = eval_result!(dir_helper.create("images".to_string(),
+                                                             ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                             Vec::new(),
+                                                             true,
+                                                             ::AccessLevel::Private,
+                                                             Some(&mut root)));
+        let (mut deep, _) = eval_result!(dir_helper.create("deep".to_string(),
+                                                           ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                           Vec::new(),
+                                                           true,
+                                                           ::AccessLevel::Private,
+                                                           Some(&mut images)));
+        add_files(&mut deep, &["d.png"]);
+        let mut images = eval_result!(dir_helper.get(images.get_key()));
+        add_files(&mut images, &["b.png", "c.txt"]);
+        let mut root = eval_result!(dir_helper.get(root.get_key()));
+        add_files(&mut root, &["a.png"]);
+
+        assert_eq!(eval_result!(dir_helper.glob_recursive(&root, "**/*.png")),
+                   vec!["a.png".to_string(), "images/b.png".to_string(), "images/deep/d.png".to_string()]);
+        assert_eq!(eval_result!(dir_helper.glob_recursive(&root, "images/*")),
+                   vec!["images/b.png".to_string(), "images/c.txt".to_string()]);
+        assert_eq!(eval_result!(dir_helper.glob_recursive(&root, "*.png")), vec!["a.png".to_string()]);
+        assert_eq!(eval_result!(dir_helper.glob_recursive(&root, "images/**/*.png")),
+                   vec!["images/b.png".to_string(), "images/deep/d.png".to_string()]);
+        assert!(eval_result!(dir_helper.glob_recursive(&root, "docs/**/*.png")).is_empty());
+        assert!(eval_result!(dir_helper.glob_recursive(&root, "**/*.jpg")).is_empty());
+    }
+
+    #[test]
+    fn entry_kind() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+        let _ = eval_result!(dir_helper.create("Child".to_string(),
+                                               ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                               Vec::new(),
+                                               true,
+                                               ::AccessLevel::Private,
+                                               Some(&mut directory)));
+        directory.upsert_file(eval_result!(::file::File::new(::metadata::file_metadata::FileMetadata::new("file.txt".to_string(), Vec::new()),
+                                                             ::self_encryption::datamap::DataMap::None)));
+        assert_eq!(dir_helper.entry_kind(&directory, "file.txt"), Some(::directory_listing::entry::EntryKind::File));
+        assert_eq!(dir_helper.entry_kind(&directory, "Child"), Some(::directory_listing::entry::EntryKind::Directory));
+        assert_eq!(dir_helper.entry_kind(&directory, "missing"), None);
+    }
+
+    #[test]
+    fn path_of_file() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut root, _) = eval_result!(dir_helper.create("Root".to_string(),
+                                                           ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                           Vec::new(),
+                                                           true,
+                                                           ::AccessLevel::Private,
+                                                           None));
+        let (mut child, _) = eval_result!(dir_helper.create("Child".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            Some(&mut root)));
+        let (mut grand_child, _) = eval_result!(dir_helper.create("GrandChild".to_string(),
+                                                                  ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                  Vec::new(),
+                                                                  true,
+                                                                  ::AccessLevel::Private,
+                                                                  Some(&mut child)));
+        grand_child.upsert_file(eval_result!(::file::File::new(::metadata::file_metadata::FileMetadata::new("file.txt".to_string(), Vec::new()),
+                                                               ::self_encryption::datamap::DataMap::None)));
+        let _ = eval_result!(dir_helper.update(&grand_child));
+
+        assert_eq!(eval_result!(dir_helper.path_of_file(&root, "file.txt", grand_child.get_key())),
+                   "Child/GrandChild/file.txt".to_string());
+        match dir_helper.path_of_file(&root, "missing.txt", grand_child.get_key()) {
+            Err(::errors::NfsError::FileNotFound) => (),
+            _ => panic!("Expected FileNotFound"),
+        }
+        let (other, _) = eval_result!(dir_helper.create("Other".to_string(),
+                                                        ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                        Vec::new(),
+                                                        true,
+                                                        ::AccessLevel::Private,
+                                                        None));
+        match dir_helper.path_of_file(&root, "file.txt", other.get_key()) {
+            Err(::errors::NfsError::DirectoryNotFound(_)) => (),
+            _ => panic!("Expected DirectoryNotFound"),
+        }
+    }
+
+    #[test]
+    fn empty_directory() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut root, _) = eval_result!(dir_helper.create("Root".to_string(),
+                                                           ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                           Vec::new(),
+                                                           true,
+                                                           ::AccessLevel::Private,
+                                                           None));
+        let (mut child, _) = eval_result!(dir_helper.create("Child".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            Some(&mut root)));
+        child.upsert_file(eval_result!(::file::File::new(::metadata::file_metadata::FileMetadata::new("file.txt".to_string(), Vec::new()),
+                                                         ::self_encryption::datamap::DataMap::None)));
+        let _ = eval_result!(dir_helper.update(&child));
+        let mut root = eval_result!(dir_helper.get(root.get_key()));
+
+        let _ = eval_result!(dir_helper.empty_directory(&mut root, true));
+        assert!(root.get_sub_directories().is_empty());
+        assert_eq!(eval_result!(dir_helper.get(root.get_key())), root);
+        assert!(eval_result!(dir_helper.get(child.get_key())).get_files().is_empty());
+    }
+
+    #[test]
+    fn put_listing() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut parent, _) = eval_result!(dir_helper.create("Parent".to_string(),
+                                                             ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                             Vec::new(),
+                                                             true,
+                                                             ::AccessLevel::Private,
+                                                             None));
+        let mut listing = eval_result!(::directory_listing::DirectoryListing::new("Imported".to_string(),
+                                                                                  ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                                  Vec::new(),
+                                                                                  true,
+                                                                                  ::AccessLevel::Private,
+                                                                                  None));
+        for name in vec!["first.txt", "second.txt"] {
+            listing.upsert_file(eval_result!(::file::File::new(::metadata::file_metadata::FileMetadata::new(name.to_string(), Vec::new()),
+                                                               ::self_encryption::datamap::DataMap::None)));
+        }
+
+        let saved = eval_result!(dir_helper.put_listing(listing.clone(), Some(&mut parent)));
+        assert_eq!(saved.get_metadata().get_parent_dir_key(), Some(parent.get_key()));
+        assert!(parent.find_sub_directory(&"Imported".to_string()).is_some());
+        let fetched = eval_result!(dir_helper.get(saved.get_key()));
+        assert_eq!(fetched.get_files().len(), 2);
+        assert!(fetched.find_file(&"second.txt".to_string()).is_some());
+
+        match dir_helper.put_listing(listing.clone(), Some(&mut parent)) {
+            Err(::errors::NfsError::DirectoryAlreadyExistsWithSameName) => (),
+            _ => panic!("Expected DirectoryAlreadyExistsWithSameName"),
+        }
+
+        let mut invalid_listing = listing;
+        invalid_listing.get_mut_metadata().set_name("Invalid".to_string());
+        invalid_listing.upsert_file(eval_result!(::file::File::new(::metadata::file_metadata::FileMetadata::new("a/b".to_string(), Vec::new()),
+                                                                   ::self_encryption::datamap::DataMap::None)));
+        match dir_helper.put_listing(invalid_listing, None) {
+            Err(::errors::NfsError::InvalidName) => (),
+            _ => panic!("Expected InvalidName"),
+        }
+    }
+
+    #[test]
+    fn update_with_status() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut parent, _) = eval_result!(dir_helper.create("Parent".to_string(),
+                                                             ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                             Vec::new(),
+                                                             true,
+                                                             ::AccessLevel::Private,
+                                                             None));
+        let (mut directory, _) = eval_result!(dir_helper.create("Child".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                Some(&mut parent)));
+        let last_saved = directory.clone();
+        directory.get_mut_metadata().set_user_metadata(vec![1u8; 10]);
+        let (updated_parent, saved) = eval_result!(dir_helper.update_with_status(&directory, Some(&last_saved)));
+        assert!(saved);
+        assert!(updated_parent.is_some());
+
+        // Nothing changed since the last update
+        let last_saved = directory.clone();
+        let (updated_parent, saved) = eval_result!(dir_helper.update_with_status(&directory, Some(&last_saved)));
+        assert!(!saved);
+        assert!(updated_parent.is_none());
+        let versions = eval_result!(dir_helper.get_versions(directory.get_key().get_id(), directory.get_key().get_type_tag()));
+        assert_eq!(versions.len(), 2);
+    }
+
+    #[test]
+    fn create_with_nonce() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut parent, _) = eval_result!(dir_helper.create("ParentDir".to_string(),
+                                                             ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                             Vec::new(),
+                                                             true,
+                                                             ::AccessLevel::Private,
+                                                             None));
+        for &(tag_type, versioned) in &[(::VERSIONED_DIRECTORY_LISTING_TAG, true), (::UNVERSIONED_DIRECTORY_LISTING_TAG, false)] {
+            let nonce = ::sodiumoxide::crypto::box_::gen_nonce();
+            let id = ::routing::NameType::new(eval_result!(::safe_core::utility::generate_random_array_u8_64()));
+            let key = ::metadata::directory_key::DirectoryKey::new(id.clone(),
+                                                                   tag_type,
+                                                                   versioned,
+                                                                   ::AccessLevel::Private).with_nonce(nonce);
+            let (mut directory, updated_parent) = eval_result!(dir_helper.create_with_key(key, "DirName".to_string(), Vec::new(), Some(&mut parent)));
+            parent = eval_option!(updated_parent, "Parent not returned");
+            assert_eq!(directory.get_key().get_nonce(), Some(nonce));
+
+            directory.get_mut_metadata().set_user_metadata(vec![1u8; 10]);
+            let updated_parent = eval_result!(dir_helper.update(&directory));
+            parent = eval_option!(updated_parent, "Parent not returned");
+            assert_eq!(eval_result!(dir_helper.get(directory.get_key())), directory);
+            // The parent, saved with the nonce generated from its own id, is still readable
+            assert_eq!(eval_result!(dir_helper.get(parent.get_key())), parent);
+
+            // Files written through FileHelper keep the directory readable
+            let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+            let mut writer = eval_result!(file_helper.create("file.txt".to_string(), Vec::new(), directory.clone()));
+            writer.write(&vec![2u8; 10], 0);
+            let (updated_directory, _) = eval_result!(writer.close());
+            let fetched = eval_result!(DirectoryHelper::new(client.clone()).get(directory.get_key()));
+            assert_eq!(fetched, updated_directory);
+            assert!(fetched.find_file(&"file.txt".to_string()).is_some());
+
+            // The directory can not be read with the nonce generated from its id
+            let key_without_nonce = ::metadata::directory_key::DirectoryKey::new(id, tag_type, versioned, ::AccessLevel::Private);
+            assert!(dir_helper.get(&key_without_nonce).is_err());
+        }
+    }
+
+    #[test]
+    fn get_with_timeout() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let fetched = eval_result!(dir_helper.get_with_timeout(directory.get_key(), ::std::time::Duration::from_secs(60)));
+        assert_eq!(fetched, directory);
+    }
+
+    #[test]
+    fn entries_paged() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+        let _ = eval_result!(dir_helper.create("Child".to_string(),
+                                               ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                               Vec::new(),
+                                               true,
+                                               ::AccessLevel::Private,
+                                               Some(&mut directory)));
+        for index in 0..4 {
+            directory.upsert_file(eval_result!(::file::File::new(::metadata::file_metadata::FileMetadata::new(format!("file-{}.txt", index), Vec::new()),
+                                                                 ::self_encryption::datamap::DataMap::None)));
+        }
+        let _ = eval_result!(dir_helper.update(&directory));
+
+        match dir_helper.entries_paged(directory.get_key(), 0) {
+            Err(::errors::NfsError::ParameterIsNotValid) => (),
+            _ => panic!("Expected ParameterIsNotValid"),
+        }
+        let mut pager = eval_result!(dir_helper.entries_paged(directory.get_key(), 2));
+        let mut names = Vec::new();
+        loop {
+            let page = eval_result!(pager.next_page());
+            if page.is_empty() {
+                break;
+            }
+            assert!(page.len() <= 2);
+            names.extend(page.iter().map(|entry| entry.get_name().clone()));
+        }
+        assert_eq!(names, vec!["file-0.txt", "file-1.txt", "file-2.txt", "file-3.txt", "Child"]);
+    }
+
+    #[test]
+    fn create_with_time() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut parent, _) = eval_result!(dir_helper.create("Parent".to_string(),
+                                                             ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                             Vec::new(),
+                                                             true,
+                                                             ::AccessLevel::Private,
+                                                             None));
+        let created_time = ::time::at_utc(::time::Timespec::new(1_000_000_000, 0));
+        let (directory, _) = eval_result!(dir_helper.create_with_time("Restored".to_string(),
+                                                                      ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                      Vec::new(),
+                                                                      true,
+                                                                      ::AccessLevel::Private,
+                                                                      Some(&mut parent),
+                                                                      created_time.clone()));
+        let fetched = eval_result!(dir_helper.get(directory.get_key()));
+        assert_eq!(*fetched.get_metadata().get_created_time(), created_time);
+        assert_eq!(*fetched.get_metadata().get_modified_time(), created_time);
+        let sub_directory = eval_option!(parent.find_sub_directory(&"Restored".to_string()), "Directory not found");
+        assert_eq!(*sub_directory.get_created_time(), created_time);
+
+        for seconds in vec![-1, ::time::get_time().sec + 2 * MAX_CREATED_TIME_AHEAD_SECONDS] {
+            match dir_helper.create_with_time("Invalid".to_string(),
+                                              ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                              Vec::new(),
+                                              true,
+                                              ::AccessLevel::Private,
+                                              None,
+                                              ::time::at_utc(::time::Timespec::new(seconds, 0))) {
+                Err(::errors::NfsError::ParameterIsNotValid) => (),
+                _ => panic!("Expected ParameterIsNotValid"),
+            }
+        }
+    }
+
+    #[test]
+    fn parse_share_token() {
+        let id = ::routing::NameType::new(eval_result!(::safe_core::utility::generate_random_array_u8_64()));
+        let directory_key = ::metadata::directory_key::DirectoryKey::new(id, ::VERSIONED_DIRECTORY_LISTING_TAG, true, ::AccessLevel::Shared);
+        let token = eval_result!(directory_key.to_share_token());
+        assert_eq!(eval_result!(DirectoryHelper::parse_share_token(&format!(" {}\n", token))),
+                   ((directory_key.get_id().clone(), ::VERSIONED_DIRECTORY_LISTING_TAG), ::AccessLevel::Shared));
+        for invalid_token in vec!["", "zz", &token[..token.len() - 2]] {
+            match DirectoryHelper::parse_share_token(invalid_token) {
+                Err(::errors::NfsError::InvalidKeyFormat) => (),
+                _ => panic!("Expected InvalidKeyFormat"),
+            }
+        }
+    }
+
+    #[test]
+    fn get_from_share_token() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let token = eval_result!(directory.get_key().to_share_token());
+        assert_eq!(eval_result!(dir_helper.get_from_share_token(&format!(" {}\n", token))), directory);
+        match dir_helper.get_from_share_token(&token[..token.len() - 2]) {
+            Err(::errors::NfsError::InvalidKeyFormat) => (),
+            _ => panic!("Expected InvalidKeyFormat"),
+        }
+    }
+
+    #[test]
+    fn create_public() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let mut public_directory = eval_result!(dir_helper.create_public("Public".to_string(), Vec::new(), None));
+        assert!(!public_directory.get_key().is_versioned());
+        assert_eq!(*public_directory.get_key().get_access_level(), ::AccessLevel::Public);
+
+        let child = eval_result!(dir_helper.create_public("Child".to_string(), Vec::new(), Some(&mut public_directory)));
+        assert!(public_directory.find_sub_directory(child.get_metadata().get_name()).is_some());
+        assert_eq!(eval_result!(dir_helper.get(child.get_key())), child);
+
+        let (mut private_directory, _) = eval_result!(dir_helper.create("Private".to_string(),
+                                                                        ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                        Vec::new(),
+                                                                        true,
+                                                                        ::AccessLevel::Private,
+                                                                        None));
+        match dir_helper.create_public("Child".to_string(), Vec::new(), Some(&mut private_directory)) {
+            Err(::errors::NfsError::AccessLevelMismatch) => (),
+            _ => panic!("Expected AccessLevelMismatch"),
+        }
+        assert!(private_directory.get_sub_directories().is_empty());
+    }
+
+    #[test]
+    fn get_with_children() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+        let (fetched, children) = eval_result!(dir_helper.get_with_children(directory.get_key()));
+        assert_eq!(fetched, directory);
+        assert!(children.is_empty());
+
+        for index in 0..MAX_PARALLEL_FETCHES + 2 {
+            let _ = eval_result!(dir_helper.create(format!("Child{}", index),
+                                                   ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                   Vec::new(),
+                                                   true,
+                                                   ::AccessLevel::Private,
+                                                   Some(&mut directory)));
+        }
+        let (fetched, children) = eval_result!(dir_helper.get_with_children(directory.get_key()));
+        assert_eq!(fetched, directory);
+        assert_eq!(children.len(), MAX_PARALLEL_FETCHES + 2);
+        for (sub_directory, child) in directory.get_sub_directories().iter().zip(children.iter()) {
+            assert_eq!(child.get_key(), sub_directory.get_key());
+            assert_eq!(child.get_metadata().get_name(), sub_directory.get_name());
+        }
+    }
+
+    #[test]
+    fn create_unique() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+        for expected_name in vec!["New Folder", "New Folder (1)", "New Folder (2)"] {
+            let created = eval_result!(dir_helper.create_unique("New Folder",
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                &mut directory));
+            assert_eq!(created.get_metadata().get_name(), expected_name);
+        }
+        assert_eq!(directory.get_sub_directories().len(), 3);
+        assert_eq!(eval_result!(dir_helper.get(directory.get_key())), directory);
+    }
+
+    #[test]
+    fn tree_file_sizes() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut root, _) = eval_result!(dir_helper.create("Root".to_string(),
+                                                           ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                           Vec::new(),
+                                                           true,
+                                                           ::AccessLevel::Private,
+                                                           None));
+        let (child, _) = eval_result!(dir_helper.create("Child".to_string(),
+                                                        ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                        Vec::new(),
+                                                        true,
+                                                        ::AccessLevel::Private,
+                                                        Some(&mut root)));
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        let mut writer = eval_result!(file_helper.create("top.dat".to_string(), Vec::new(), root));
+        writer.write(&[1u8; 10], 0);
+        let (root, _) = eval_result!(writer.close());
+        let mut writer = eval_result!(file_helper.create("nested.dat".to_string(), Vec::new(), child));
+        writer.write(&[1u8; 2048], 0);
+        let _ = eval_result!(writer.close());
+
+        let root = eval_result!(dir_helper.get(root.get_key()));
+        assert_eq!(eval_result!(dir_helper.tree_file_sizes(&root)),
+                   vec![("top.dat".to_string(), 10), ("Child/nested.dat".to_string(), 2048)]);
+    }
+
+    #[test]
+    fn read_only() {
+        let client = get_client();
+        let read_only_helper = DirectoryHelper::new(client.clone()).with_read_only(true);
+        match read_only_helper.get_user_root_directory_listing() {
+            Err(::errors::NfsError::ReadOnly) => (),
+            _ => panic!("Expected ReadOnly"),
+        }
+
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+        let _ = eval_result!(dir_helper.create("Child".to_string(),
+                                               ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                               Vec::new(),
+                                               true,
+                                               ::AccessLevel::Private,
+                                               Some(&mut directory)));
+        assert_eq!(eval_result!(read_only_helper.get(directory.get_key())), directory);
+
+        let original_directory = directory.clone();
+        let read_only_file_helper = read_only_helper.get_file_helper();
+        let results = vec![read_only_helper.create("Other".to_string(),
+                                                   ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                   Vec::new(),
+                                                   true,
+                                                   ::AccessLevel::Private,
+                                                   Some(&mut directory)).map(|_| ()),
+                           read_only_helper.update(&directory).map(|_| ()),
+                           read_only_helper.delete(&mut directory, &"Child".to_string()).map(|_| ()),
+                           read_only_helper.empty_directory(&mut directory, false).map(|_| ()),
+                           read_only_helper.create_path(&mut directory, "a/b", true, ::AccessLevel::Private).map(|_| ()),
+                           read_only_file_helper.create("file.txt".to_string(), Vec::new(), directory.clone()).map(|_| ()),
+                           read_only_file_helper.delete("file.txt".to_string(), &mut directory).map(|_| ())];
+        for result in results {
+            match result {
+                Err(::errors::NfsError::ReadOnly) => (),
+                _ => panic!("Expected ReadOnly"),
+            }
+        }
+        // The directory of the caller is left as it was
+        assert_eq!(directory, original_directory);
+        assert_eq!(eval_result!(dir_helper.get(directory.get_key())), directory);
+    }
+
+    #[test]
+    fn migrate_tags() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let new_tag = ::UNVERSIONED_DIRECTORY_LISTING_TAG + 10;
+        let (mut root, _) = eval_result!(dir_helper.create("Root".to_string(),
+                                                           ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                           Vec::new(),
+                                                           true,
+                                                           ::AccessLevel::Private,
+                                                           None));
+        let (mut child, _) = eval_result!(dir_helper.create("Child".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            Some(&mut root)));
+        let _ = eval_result!(dir_helper.create("GrandChild".to_string(),
+                                               ::UNVERSIONED_DIRECTORY_LISTING_TAG,
+                                               Vec::new(),
+                                               false,
+                                               ::AccessLevel::Private,
+                                               Some(&mut child)));
+        let root = eval_result!(dir_helper.get(root.get_key()));
+
+        match dir_helper.migrate_tags(&root, new_tag, new_tag) {
+            Err(::errors::NfsError::ParameterIsNotValid) => (),
+            _ => panic!("Expected ParameterIsNotValid"),
+        }
+        assert_eq!(eval_result!(dir_helper.migrate_tags(&root, ::VERSIONED_DIRECTORY_LISTING_TAG, new_tag)), 2);
+
+        let migrated_root_key = ::metadata::directory_key::DirectoryKey::new(root.get_key().get_id().clone(),
+                                                                             new_tag,
+                                                                             true,
+                                                                             ::AccessLevel::Private);
+        let migrated_root = eval_result!(dir_helper.get(&migrated_root_key));
+        let migrated_child_key = migrated_root.get_sub_directories()[0].get_key().clone();
+        assert_eq!(migrated_child_key.get_type_tag(), new_tag);
+        let migrated_child = eval_result!(dir_helper.get(&migrated_child_key));
+        assert_eq!(migrated_child.get_metadata().get_parent_dir_key(), Some(&migrated_root_key));
+        let grand_child_key = migrated_child.get_sub_directories()[0].get_key();
+        assert_eq!(grand_child_key.get_type_tag(), ::UNVERSIONED_DIRECTORY_LISTING_TAG);
+        assert_eq!(eval_result!(dir_helper.get(grand_child_key)).get_metadata().get_parent_dir_key(), Some(&migrated_child_key));
+
+        // The directories under the old tag are kept and a second run has nothing left to migrate
+        assert_eq!(eval_result!(dir_helper.get(root.get_key())), root);
+        assert_eq!(eval_result!(dir_helper.migrate_tags(&root, ::VERSIONED_DIRECTORY_LISTING_TAG, new_tag)), 0);
+        assert_eq!(eval_result!(dir_helper.migrate_tags(&migrated_root, ::VERSIONED_DIRECTORY_LISTING_TAG, new_tag)), 0);
+    }
+
+    #[test]
+    fn resolve_partial() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut root, _) = eval_result!(dir_helper.create("Root".to_string(),
+                                                           ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                           Vec::new(),
+                                                           true,
+                                                           ::AccessLevel::Private,
+                                                           None));
+        let (mut first, _) = eval_result!(dir_helper.create("a".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            Some(&mut root)));
+        let (second, _) = eval_result!(dir_helper.create("b".to_string(),
+                                                         ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                         Vec::new(),
+                                                         true,
+                                                         ::AccessLevel::Private,
+                                                         Some(&mut first)));
+
+        let (directory, remaining) = eval_result!(dir_helper.resolve_partial(&root, "/a/b/c/d"));
+        assert_eq!(directory, second);
+        assert_eq!(remaining, vec!["c".to_string(), "d".to_string()]);
+
+        let (directory, remaining) = eval_result!(dir_helper.resolve_partial(&root, "a//b"));
+        assert_eq!(directory, second);
+        assert!(remaining.is_empty());
+
+        let (directory, remaining) = eval_result!(dir_helper.resolve_partial(&root, "x/a"));
+        assert_eq!(directory, root);
+        assert_eq!(remaining, vec!["x".to_string(), "a".to_string()]);
+    }
+
+    #[test]
+    fn create_path() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut root, _) = eval_result!(dir_helper.create("Root".to_string(),
+                                                           ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                           Vec::new(),
+                                                           true,
+                                                           ::AccessLevel::Private,
+                                                           None));
+        let (mut first, _) = eval_result!(dir_helper.create("a".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            Some(&mut root)));
+
+        let created = eval_result!(dir_helper.create_path(&mut root, "a/b/c", true, ::AccessLevel::Private));
+        assert_eq!(created.get_metadata().get_name(), "c");
+        first = eval_result!(dir_helper.get(first.get_key()));
+        let second = eval_result!(dir_helper.get(eval_option!(first.find_sub_directory(&"b".to_string()),
+                                                              "Directory not found").get_key()));
+        assert!(second.find_sub_directory(&"c".to_string()).is_some());
+
+        // Creating it again only resolves the existing directories
+        assert_eq!(eval_result!(dir_helper.create_path(&mut root, "/a/b/c/", true, ::AccessLevel::Private)), created);
+
+        let created = eval_result!(dir_helper.create_path(&mut root, "x/y", false, ::AccessLevel::Private));
+        assert!(root.find_sub_directory(&"x".to_string()).is_some());
+        assert!(!created.get_key().is_versioned());
+
+        match dir_helper.create_path(&mut root, "//", true, ::AccessLevel::Private) {
+            Err(::errors::NfsError::ParameterIsNotValid) => (),
+            _ => panic!("Expected ParameterIsNotValid"),
+        }
+    }
+
+    #[test]
+    fn try_get() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        assert_eq!(eval_result!(dir_helper.try_get(directory.get_key())), Some(directory));
+
+        let id = ::routing::NameType::new(eval_result!(::safe_core::utility::generate_random_array_u8_64()));
+        for &(tag, versioned) in &[(::VERSIONED_DIRECTORY_LISTING_TAG, true), (::UNVERSIONED_DIRECTORY_LISTING_TAG, false)] {
+            let key = ::metadata::directory_key::DirectoryKey::new(id.clone(), tag, versioned, ::AccessLevel::Private);
+            assert!(eval_result!(dir_helper.try_get(&key)).is_none());
+            match dir_helper.get(&key) {
+                Err(::errors::NfsError::DirectoryNotFound(Some(ref missing_key))) if *missing_key == key => (),
+                _ => panic!("Expected DirectoryNotFound"),
+            }
+        }
+    }
+
+    #[test]
+    fn create_inheriting() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut private_directory, _) = eval_result!(dir_helper.create("Private".to_string(),
+                                                                        ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                        Vec::new(),
+                                                                        true,
+                                                                        ::AccessLevel::Private,
+                                                                        None));
+        let child = eval_result!(dir_helper.create_inheriting("Child".to_string(), Vec::new(), None, None, &mut private_directory));
+        assert!(child.get_key().is_versioned());
+        assert_eq!(child.get_key().get_type_tag(), ::VERSIONED_DIRECTORY_LISTING_TAG);
+        assert_eq!(*child.get_key().get_access_level(), ::AccessLevel::Private);
+        assert!(private_directory.find_sub_directory(&"Child".to_string()).is_some());
+
+        let child = eval_result!(dir_helper.create_inheriting("Unversioned".to_string(), Vec::new(), Some(false), None, &mut private_directory));
+        assert!(!child.get_key().is_versioned());
+        assert_eq!(child.get_key().get_type_tag(), ::UNVERSIONED_DIRECTORY_LISTING_TAG);
+        assert_eq!(*child.get_key().get_access_level(), ::AccessLevel::Private);
+
+        let mut public_directory = eval_result!(dir_helper.create_public("Public".to_string(), Vec::new(), None));
+        let child = eval_result!(dir_helper.create_inheriting("Child".to_string(), Vec::new(), None, None, &mut public_directory));
+        assert!(!child.get_key().is_versioned());
+        assert_eq!(*child.get_key().get_access_level(), ::AccessLevel::Public);
+
+        let child = eval_result!(dir_helper.create_inheriting("Hidden".to_string(),
+                                                              Vec::new(),
+                                                              None,
+                                                              Some(::AccessLevel::Private),
+                                                              &mut public_directory));
+        assert_eq!(*child.get_key().get_access_level(), ::AccessLevel::Private);
+        assert_eq!(eval_result!(dir_helper.get(child.get_key())), child);
+    }
+
+    #[test]
+    fn update_refreshes_cached_aggregates() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        let (mut root, _) = eval_result!(dir_helper.create("Root".to_string(),
+                                                           ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                           Vec::new(),
+                                                           true,
+                                                           ::AccessLevel::Private,
+                                                           None));
+        let (child, _) = eval_result!(dir_helper.create("Child".to_string(),
+                                                        ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                        Vec::new(),
+                                                        true,
+                                                        ::AccessLevel::Private,
+                                                        Some(&mut root)));
+        {
+            let entry = eval_option!(root.find_sub_directory(&"Child".to_string()), "Directory not found");
+            assert_eq!(entry.get_size(), 0);
+            assert_eq!(entry.get_child_count(), 0);
+        }
+
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        let mut writer = eval_result!(file_helper.create("file.txt".to_string(), Vec::new(), child));
+        writer.write(&[7u8; 10], 0);
+        let (mut child, updated_root) = eval_result!(writer.close());
+        let (grand_child, _) = eval_result!(dir_helper.create("GrandChild".to_string(),
+                                                              ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                              Vec::new(),
+                                                              true,
+                                                              ::AccessLevel::Private,
+                                                              Some(&mut child)));
+        root = eval_option!(updated_root, "Parent not updated");
+        {
+            let entry = eval_option!(root.find_sub_directory(&"Child".to_string()), "Directory not found");
+            assert_eq!(entry.get_size(), 10);
+            assert_eq!(entry.get_child_count(), 1);
+        }
+
+        root = eval_result!(dir_helper.get(root.get_key()));
+        let entry = eval_option!(root.find_sub_directory(&"Child".to_string()), "Directory not found");
+        assert_eq!(entry.get_size(), 10);
+        assert_eq!(entry.get_child_count(), 2);
+        assert_eq!(*entry.get_modified_time(), *child.get_metadata().get_modified_time());
+        assert_eq!(grand_child.get_metadata().get_size(), 0);
+    }
+
+    #[test]
+    fn root_exists() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        assert!(!dir_helper.user_root_exists());
+        assert!(!dir_helper.configuration_root_exists());
+
+        let _ = eval_result!(dir_helper.get_user_root_directory_listing());
+        assert!(dir_helper.user_root_exists());
+        assert!(!dir_helper.configuration_root_exists());
+
+        let _ = eval_result!(dir_helper.get_configuration_directory_listing("DNS".to_string()));
+        assert!(dir_helper.configuration_root_exists());
+    }
+
+    #[test]
+    fn list_configuration_directories() {
+        let client = get_client();
+        let dir_helper = DirectoryHelper::new(client.clone());
+        assert!(eval_result!(dir_helper.list_configuration_directories()).is_empty());
+        assert!(!dir_helper.configuration_root_exists());
+
+        let _ = eval_result!(dir_helper.get_configuration_directory_listing("DNS".to_string()));
+        let _ = eval_result!(dir_helper.get_configuration_directory_listing("Launcher".to_string()));
+        let _ = eval_result!(dir_helper.get_configuration_directory_listing("DNS".to_string()));
+        assert_eq!(eval_result!(dir_helper.list_configuration_directories()), vec!["DNS".to_string(), "Launcher".to_string()]);
+
+        let _ = eval_result!(dir_helper.list_shared_directories());
+        assert_eq!(eval_result!(dir_helper.list_configuration_directories()), vec!["DNS".to_string(), "Launcher".to_string()]);
+    }
+
+    #[test]
+    fn with_chunk_storage() {
+        let client = get_client();
+        let storage = ::std::sync::Arc::new(::helper::chunk_storage::MemoryChunkStorage::new());
+        let dir_helper = DirectoryHelper::new(client.clone()).with_chunk_storage(storage.clone());
+        let content = (0..10 * 1024).map(|index| (index % 251) as u8).collect::<Vec<u8>>();
+        let (mut directory, failures) = eval_result!(dir_helper.create_with_files("DirName".to_string(),
+                                                                                  CreateWithFilesOptions::new(::VERSIONED_DIRECTORY_LISTING_TAG, true, ::AccessLevel::Private).with_all_or_nothing(true),
+                                                                                  None,
+                                                                                  vec![("file.dat".to_string(), content.clone())]));
+        assert!(failures.is_empty());
+        assert!(!storage.is_empty());
+
+        // Files are read and deleted through the storage of the helper
+        let file_helper = dir_helper.get_file_helper();
+        {
+            let file = eval_option!(directory.find_file(&"file.dat".to_string()), "File not found");
+            let mut reader = file_helper.read(file);
+            assert_eq!(eval_result!(reader.read(0, content.len() as u64)), content);
+        }
+        let _ = eval_result!(file_helper.delete_with_content("file.dat".to_string(), &mut directory));
+        assert!(storage.is_empty());
+    }
 }
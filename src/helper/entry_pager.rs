@@ -0,0 +1,58 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+/// Pages through the entries of a directory, files first and then sub directories, each in the order in which they are
+/// stored. The DirectoryListing is only fetched from the network when the first page is requested.
+/// The whole DirectoryListing is fetched at once and kept until the pager is dropped, as a listing is not stored in
+/// parts which could be fetched separately.
+pub struct EntryPager {
+    directory_helper: ::helper::directory_helper::DirectoryHelper,
+    directory_key   : ::metadata::directory_key::DirectoryKey,
+    page_size       : usize,
+    directory       : Option<::directory_listing::DirectoryListing>,
+    position        : usize,
+}
+
+impl EntryPager {
+    /// Create a new EntryPager over the entries of the directory, returning up to page_size entries at a time
+    pub fn new(directory_helper: ::helper::directory_helper::DirectoryHelper,
+               directory_key   : ::metadata::directory_key::DirectoryKey,
+               page_size       : usize) -> EntryPager {
+        EntryPager {
+            directory_helper: directory_helper,
+            directory_key   : directory_key,
+            page_size       : page_size,
+            directory       : None,
+            position        : 0,
+        }
+    }
+
+    /// Returns the next page of up to page_size entries. An empty page is returned once all the entries have been paged
+    /// through
+    pub fn next_page(&mut self) -> Result<Vec<::directory_listing::entry::Entry>, ::errors::NfsError> {
+        if self.directory.is_none() {
+            debug!("Fetching directory listing to page through ...");
+            self.directory = Some(try!(self.directory_helper.get(&self.directory_key)));
+        }
+        let directory = eval_option!(self.directory.as_ref(), "Directory listing must have been fetched");
+        let files = directory.get_files().iter().map(|file| ::directory_listing::entry::Entry::File(file));
+        let sub_directories = directory.get_sub_directories().iter().map(|sub_directory| ::directory_listing::entry::Entry::Directory(sub_directory));
+        let page: Vec<::directory_listing::entry::Entry> = files.chain(sub_directories).skip(self.position).take(self.page_size).collect();
+        self.position += page.len();
+        Ok(page)
+    }
+}
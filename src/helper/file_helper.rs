@@ -17,49 +17,204 @@
 
 /// File provides helper functions to perform Operations on Files
 pub struct FileHelper {
-    client: ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
+    client    : ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
+    shared_key: Option<::sodiumoxide::crypto::secretbox::Key>,
+    storage   : ::std::sync::Arc<::helper::chunk_storage::ChunkStorage>,
+    read_only : bool,
 }
 
 impl FileHelper {
     /// Create a new FileHelper instance
     pub fn new(client: ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>) -> FileHelper {
+        FileHelper::from_parts(client, None, false)
+    }
+
+    /// Create a new FileHelper instance which updates Shared parent directories with the shared_key passed
+    pub fn with_shared_key(client    : ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
+                           shared_key: ::sodiumoxide::crypto::secretbox::Key) -> FileHelper {
+        FileHelper::from_parts(client, Some(shared_key), false)
+    }
+
+    /// Create a new FileHelper instance with the shared key, if any, and the read-only mode passed, e.g. to carry those
+    /// of another helper, see `with_shared_key` and `with_read_only`
+    pub fn from_parts(client    : ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
+                      shared_key: Option<::sodiumoxide::crypto::secretbox::Key>,
+                      read_only : bool) -> FileHelper {
         FileHelper {
-            client: client,
+            client    : client.clone(),
+            shared_key: shared_key,
+            storage   : ::std::sync::Arc::new(::helper::chunk_storage::NetworkChunkStorage::new(client)),
+            read_only : read_only,
         }
     }
 
+    /// Create a new FileHelper instance which reads and writes the chunks of file contents through the storage passed
+    /// instead of the network, e.g. a MemoryChunkStorage in tests or a caching layer. Directories are still saved in
+    /// the network through the client. For Shared directories, see `with_chunk_storage`.
+    pub fn with_storage(client : ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
+                        storage: ::std::sync::Arc<::helper::chunk_storage::ChunkStorage>) -> FileHelper {
+        FileHelper {
+            client    : client,
+            shared_key: None,
+            storage   : storage,
+            read_only : false,
+        }
+    }
+
+    /// Sets the storage through which the chunks of file contents are read, written and deleted, in the same way as
+    /// `with_storage`, e.g. for a FileHelper created with `with_shared_key`. The storage is also used by the helpers
+    /// and the buffered files derived from the FileHelper.
+    pub fn with_chunk_storage(mut self, storage: ::std::sync::Arc<::helper::chunk_storage::ChunkStorage>) -> FileHelper {
+        self.storage = storage;
+        self
+    }
+
+    /// Sets whether the FileHelper is in read-only mode. In read-only mode every operation which would write to the
+    /// network returns NfsError::ReadOnly without writing anything, see `DirectoryHelper::with_read_only`
+    pub fn with_read_only(mut self, read_only: bool) -> FileHelper {
+        self.read_only = read_only;
+        self
+    }
+
     /// Helper function to create a file in a directory listing
     /// A writer object is returned, through which the data for the file can be written to the network
     /// The file is actually saved in the directory listing only after `writer.close()` is invoked
+    /// Returns NfsError::NameIsEmpty or NfsError::InvalidName if the name can not be addressed by path
     pub fn create(&self,
                   name            : String,
                   user_metatdata  : Vec<u8>,
                   parent_directory: ::directory_listing::DirectoryListing) -> Result<::helper::writer::Writer, ::errors::NfsError> {
+        try!(self.check_writable());
+        try!(::directory_listing::DirectoryListing::validate_name(&name));
         match parent_directory.find_file(&name) {
             Some(_) => Err(::errors::NfsError::FileAlreadyExistsWithSameName),
             None => {
                 let file = try!(::file::File::new(::metadata::file_metadata::FileMetadata::new(name, user_metatdata), ::self_encryption::datamap::DataMap::None));
-                Ok(::helper::writer::Writer::new(self.client.clone(), ::helper::writer::Mode::Overwrite, parent_directory, file))
+                let mut writer = ::helper::writer::Writer::with_storage(self.client.clone(),
+                                                                        self.storage.clone(),
+                                                                        ::helper::writer::Mode::Overwrite,
+                                                                        parent_directory,
+                                                                        file);
+                writer.set_shared_key(self.shared_key.clone());
+                Ok(writer)
             },
         }
     }
 
+    /// Helper function to create a file in a directory listing, in the same way as `create`, named base_name or, if the
+    /// name is taken by an entry of the directory listing, base_name followed by the first of ` (1)`, ` (2)`, etc. which
+    /// makes the name free. The name chosen is given by `writer.get_file_name()`
+    pub fn create_unique(&self,
+                         base_name       : &str,
+                         user_metadata   : Vec<u8>,
+                         parent_directory: ::directory_listing::DirectoryListing) -> Result<::helper::writer::Writer, ::errors::NfsError> {
+        try!(self.check_writable());
+        let name = parent_directory.unique_name(base_name);
+        self.create(name, user_metadata, parent_directory)
+    }
+
+    /// Helper function to create a small file whose content is written to memory and only self-encrypted and saved in a
+    /// directory listing once `buffered_file.commit()` is invoked. The content is limited to MAX_BUFFERED_FILE_SIZE bytes
+    pub fn create_buffered(&self,
+                           name         : String,
+                           user_metadata: Vec<u8>) -> ::helper::buffered_file::BufferedFile {
+        let mut buffered_file = ::helper::buffered_file::BufferedFile::new(self.client.clone(), name, user_metadata);
+        buffered_file.set_shared_key(self.shared_key.clone());
+        buffered_file.set_storage(self.storage.clone());
+        buffered_file.set_read_only(self.read_only);
+        buffered_file
+    }
+
     /// Delete a file from the DirectoryListing
     /// Returns Option<parent_directory's parent>
     pub fn delete(&self,
                   file_name       : String,
                   parent_directory: &mut ::directory_listing::DirectoryListing) -> Result<Option<::directory_listing::DirectoryListing>, ::errors::NfsError> {
+        try!(self.check_writable());
          debug!("Deleting {:?} file from directory listing ...", file_name);
          try!(parent_directory.remove_file(&file_name));
-         let directory_helper = ::helper::directory_helper::DirectoryHelper::new(self.client.clone());
+         let directory_helper = self.get_directory_helper();
          directory_helper.update(&parent_directory)
     }
 
+    /// Deletes a file from the DirectoryListing along with its content.
+    /// Once the file is removed from the directory, each self-encrypted chunk of the file is released through the chunk
+    /// storage of the FileHelper, which only removes the chunks no longer referenced, see `ChunkStorage::delete`.
+    /// Chunks in the network may back other files, directory versions or the data of other users, so they are never
+    /// removed and only chunks held by other storages, e.g. MemoryChunkStorage, are reclaimed. Chunks which are also used
+    /// by another file of the directory are not released. The older versions of a versioned directory may still refer
+    /// to the removed chunks, so that the file can no longer be read from those versions.
+    /// Returns Option<parent_directory's parent>
+    pub fn delete_with_content(&self,
+                               file_name       : String,
+                               parent_directory: &mut ::directory_listing::DirectoryListing) -> Result<Option<::directory_listing::DirectoryListing>, ::errors::NfsError> {
+        try!(self.check_writable());
+        let file = try!(parent_directory.find_file(&file_name).map(|file| file.clone()).ok_or(::errors::NfsError::FileNotFound));
+        let updated_grand_parent = try!(self.delete(file_name, parent_directory));
+        if let ::self_encryption::datamap::DataMap::Chunks(ref chunks) = *file.get_datamap() {
+            if parent_directory.get_key().is_versioned() {
+                warn!("Deleting content of file which older versions of the directory may still refer to ...");
+            }
+            for chunk in chunks {
+                if parent_directory.get_files().iter().any(|other_file| uses_chunk(other_file, &chunk.hash)) {
+                    continue;
+                }
+                debug!("Deleting chunk {:?} of deleted file ...", chunk.chunk_num);
+                self.storage.delete(chunk.hash.clone());
+            }
+        }
+        Ok(updated_grand_parent)
+    }
+
+    /// Renames a file of the directory_listing and saves the directory_listing in the network.
+    /// The file keeps its DataMap, so its content and the versions of it held by the directory are preserved.
+    /// The directory_listing is only updated in place once it has been saved.
+    /// Returns the saved DirectoryListing
+    pub fn set_name(&self,
+                    file_name        : &str,
+                    new_name         : String,
+                    directory_listing: &mut ::directory_listing::DirectoryListing) -> Result<::directory_listing::DirectoryListing, ::errors::NfsError> {
+        try!(self.check_writable());
+        {
+            let file = try!(directory_listing.find_file(&file_name.to_string()).ok_or(::errors::NfsError::FileNotFound));
+            try!(check_lock(file, directory_listing));
+        }
+        let mut renamed_listing = directory_listing.clone();
+        try!(renamed_listing.rename_file(file_name, new_name));
+        let directory_helper = self.get_directory_helper();
+        let _ = try!(directory_helper.update(&renamed_listing));
+        *directory_listing = renamed_listing;
+        Ok(directory_listing.clone())
+    }
+
+    /// Exchanges the names of two files of the directory_listing and saves the directory_listing in the network, in a
+    /// single update, so the directory is never saved with one of the names missing or duplicated.
+    /// The directory_listing is only updated in place once it has been saved.
+    /// Returns NfsError::FileNotFound if either file is not in the directory_listing.
+    /// Returns the saved DirectoryListing
+    pub fn swap_names(&self,
+                      first_name       : &str,
+                      second_name      : &str,
+                      directory_listing: &mut ::directory_listing::DirectoryListing) -> Result<::directory_listing::DirectoryListing, ::errors::NfsError> {
+        try!(self.check_writable());
+        for name in &[first_name, second_name] {
+            let file = try!(directory_listing.find_file(&name.to_string()).ok_or(::errors::NfsError::FileNotFound));
+            try!(check_lock(file, directory_listing));
+        }
+        let mut swapped_listing = directory_listing.clone();
+        try!(swapped_listing.swap_file_names(first_name, second_name));
+        let directory_helper = self.get_directory_helper();
+        let _ = try!(directory_helper.update(&swapped_listing));
+        *directory_listing = swapped_listing;
+        Ok(directory_listing.clone())
+    }
+
     /// Updates the file metadata.
     /// Returns Option<parent_directory's parent>
     pub fn update_metadata(&self,
                            file            : ::file::File,
                            parent_directory: &mut ::directory_listing::DirectoryListing) -> Result<Option<::directory_listing::DirectoryListing>, ::errors::NfsError> {
+        try!(self.check_writable());
         {
             let existing_file = try!(parent_directory.find_file_by_id(file.get_id()).ok_or(::errors::NfsError::FileNotFound));
             if existing_file.get_name() != file.get_name() &&
@@ -67,9 +222,8 @@ impl FileHelper {
                return Err(::errors::NfsError::FileAlreadyExistsWithSameName)
             }
         }
-        parent_directory.upsert_file(file);
-        let directory_helper = ::helper::directory_helper::DirectoryHelper::new(self.client.clone());
-        directory_helper.update(&parent_directory)
+        try!(check_lock(&file, parent_directory));
+        self.save_metadata(file, parent_directory)
     }
 
     /// Helper function to Update content of a file in a directory listing
@@ -79,29 +233,96 @@ impl FileHelper {
                           file            : ::file::File,
                           mode            : ::helper::writer::Mode,
                           parent_directory: ::directory_listing::DirectoryListing) -> Result<::helper::writer::Writer, ::errors::NfsError> {
+        try!(self.check_writable());
+        try!(check_lock(&file, &parent_directory));
         {
             let existing_file = try!(parent_directory.find_file(file.get_name()).ok_or(::errors::NfsError::FileNotFound));
             if *existing_file != file {
                 return Err(::errors::NfsError::FileDoesNotMatch);
             }
         }
-        Ok(::helper::writer::Writer::new(self.client.clone(), mode, parent_directory, file))
+        let mut writer = ::helper::writer::Writer::with_storage(self.client.clone(), self.storage.clone(), mode, parent_directory, file);
+        writer.set_shared_key(self.shared_key.clone());
+        Ok(writer)
     }
 
+    /// Rewrites the content of a file of the directory_listing in a single self-encryption pass and saves the
+    /// directory_listing in the network, so that a file edited many times gets the DataMap of a file written at once.
+    /// The metadata of the file, including its modified time, is kept. The directory_listing is only updated in place
+    /// once it has been saved.
+    /// Returns the saved DirectoryListing
+    pub fn compact(&self,
+                   file             : ::file::File,
+                   directory_listing: &mut ::directory_listing::DirectoryListing) -> Result<::directory_listing::DirectoryListing, ::errors::NfsError> {
+        try!(self.check_writable());
+        let content = {
+            let mut reader = self.read(&file);
+            let size = reader.size();
+            try!(reader.read(0, size))
+        };
+        debug!("Compacting {:?} bytes of content of {:?} ...", content.len(), file.get_name());
+        let mut writer = try!(self.update_content(file, ::helper::writer::Mode::Overwrite, directory_listing.clone()));
+        writer.set_keep_modified_time(true);
+        writer.write(&content, 0);
+        let (updated_directory, _) = try!(writer.close());
+        *directory_listing = updated_directory;
+        Ok(directory_listing.clone())
+    }
+
+    /// Helper function to write a file of a directory listing, creating it if the directory listing has no file with its
+    /// name, like opening a file for writing with `O_CREAT`.
+    /// If the file exists the writer is the one returned by `update_content`, so the existing file is updated in the given
+    /// mode and its earlier versions are kept. Otherwise the writer is the one returned by `create` for a file with the
+    /// name and user metadata of the file passed, and the mode is ignored.
+    /// The file is actually saved in the directory listing only after `writer.close()` is invoked
+    pub fn update_or_create(&self,
+                            file             : ::file::File,
+                            mode             : ::helper::writer::Mode,
+                            directory_listing: ::directory_listing::DirectoryListing) -> Result<::helper::writer::Writer, ::errors::NfsError> {
+        try!(self.check_writable());
+        if directory_listing.find_file(file.get_name()).is_some() {
+            self.update_content(file, mode, directory_listing)
+        } else {
+            debug!("Creating missing file {:?} for writing ...", file.get_name());
+            self.create(file.get_name().clone(), file.get_metadata().get_user_metadata().clone(), directory_listing)
+        }
+    }
+
+    /// Returns true if the content of the two files differs, e.g. of two versions of a file, telling a change of the bytes
+    /// of a file apart from a change of only its metadata. The data-maps of the files are compared, so chunked content
+    /// is compared by the hashes of its chunks without fetching any chunk from the network.
+    pub fn content_changed(&self, lhs: &::file::File, rhs: &::file::File) -> bool {
+        if lhs.logical_size() != rhs.logical_size() {
+            return true;
+        }
+        match (lhs.get_datamap(), rhs.get_datamap()) {
+            (&::self_encryption::datamap::DataMap::Chunks(ref lhs_chunks), &::self_encryption::datamap::DataMap::Chunks(ref rhs_chunks)) => {
+                let chunk_hashes = |chunks: &Vec<::self_encryption::datamap::ChunkDetails>| {
+                    let mut hashes: Vec<_> = chunks.iter().map(|chunk| (chunk.chunk_num, &chunk.hash)).collect();
+                    hashes.sort();
+                    hashes
+                };
+                chunk_hashes(lhs_chunks) != chunk_hashes(rhs_chunks)
+            },
+            (&::self_encryption::datamap::DataMap::Content(ref lhs_content), &::self_encryption::datamap::DataMap::Content(ref rhs_content)) => {
+                lhs_content != rhs_content
+            },
+            (&::self_encryption::datamap::DataMap::None, &::self_encryption::datamap::DataMap::None) => false,
+            _ => true,
+        }
+    }
 
     /// Return the versions of a directory containing modified versions of a file
     pub fn get_versions(&self,
                         file            : &::file::File,
                         parent_directory: &::directory_listing::DirectoryListing) -> Result<Vec<::file::File>, ::errors::NfsError> {
         let mut versions = Vec::<::file::File>::new();
-        let directory_helper = ::helper::directory_helper::DirectoryHelper::new(self.client.clone());
+        let directory_helper = self.get_directory_helper();
 
         let sdv_versions = try!(directory_helper.get_versions(parent_directory.get_key().get_id(), parent_directory.get_key().get_type_tag()));
         let mut modified_time = ::time::empty_tm();
         for version_id in sdv_versions {
-            let directory_listing = try!(directory_helper.get_by_version(parent_directory.get_key().get_id(),
-                                                                         parent_directory.get_key().get_access_level(),
-                                                                         version_id.clone()));
+            let directory_listing = try!(directory_helper.get_by_version(parent_directory.get_key(), version_id.clone()));
             if let Some(file) = directory_listing.get_files().iter().find(|&entry| entry.get_name() == file.get_name()) {
                 if *file.get_metadata().get_modified_time() != modified_time {
                      modified_time = file.get_metadata().get_modified_time().clone();
@@ -112,12 +333,425 @@ impl FileHelper {
         Ok(versions)
     }
 
+    /// Returns the metadata a file had in the given version of its parent directory, without reading the file contents.
+    /// Returns NfsError::FileNotFound if the file is not present in that version
+    pub fn get_metadata_at_version(&self,
+                                   file_name    : &str,
+                                   directory_key: &::metadata::directory_key::DirectoryKey,
+                                   version      : ::routing::NameType) -> Result<::metadata::file_metadata::FileMetadata, ::errors::NfsError> {
+        let directory_helper = self.get_directory_helper();
+        let directory_listing = try!(directory_helper.get_by_version(directory_key, version));
+        let file = try!(directory_listing.find_file(&file_name.to_string()).ok_or(::errors::NfsError::FileNotFound));
+        Ok(file.get_metadata().clone())
+    }
+
+    /// Returns the SHA-512 hash of the content of the file, reading the whole content
+    pub fn content_hash(&self, file: &::file::File) -> Result<Vec<u8>, ::errors::NfsError> {
+        let mut reader = self.read(file);
+        let size = reader.size();
+        let content = try!(reader.read(0, size));
+        Ok(::sodiumoxide::crypto::hash::sha512::hash(&content).0.to_vec())
+    }
+
+    /// Returns the oldest version of the directory in which the file with the name passed has the content whose
+    /// SHA-512 hash is content_hash, as returned by `content_hash`, e.g. to find when some content first appeared.
+    /// The content of the file is only read for the versions in which its DataMap differs from the versions already
+    /// checked.
+    /// Returns None if the file never had the content
+    pub fn first_version_with_content(&self,
+                                      file_name    : &str,
+                                      directory_key: &::metadata::directory_key::DirectoryKey,
+                                      content_hash : &[u8]) -> Result<Option<::routing::NameType>, ::errors::NfsError> {
+        let directory_helper = self.get_directory_helper();
+        let mut checked_datamaps = Vec::<::self_encryption::datamap::DataMap>::new();
+        for version in try!(directory_helper.get_versions(directory_key.get_id(), directory_key.get_type_tag())) {
+            let directory_listing = try!(directory_helper.get_by_version(directory_key, version));
+            let file = match directory_listing.find_file(&file_name.to_string()) {
+                Some(file) => file,
+                None => continue,
+            };
+            if checked_datamaps.contains(file.get_datamap()) {
+                continue;
+            }
+            debug!("Hashing content of {:?} in version {:?} ...", file_name, version);
+            if try!(self.content_hash(file)) == content_hash {
+                return Ok(Some(version));
+            }
+            checked_datamaps.push(file.get_datamap().clone());
+        }
+        Ok(None)
+    }
+
+    /// Returns the versions of the parent directory which `forget_history` would prune for the file, i.e. every
+    /// directory version in which the file differs from its current state. Neither the network nor the directory is
+    /// modified, so the versions can be shown to the user to confirm before `forget_history` is invoked with them.
+    pub fn history_to_forget(&self,
+                             file_name       : &str,
+                             parent_directory: &::directory_listing::DirectoryListing) -> Result<Vec<::routing::NameType>, ::errors::NfsError> {
+        if !parent_directory.get_key().is_versioned() {
+            return Err(::errors::NfsError::ParameterIsNotValid);
+        }
+        let current_file = try!(parent_directory.find_file(&file_name.to_string()).ok_or(::errors::NfsError::FileNotFound));
+        let directory_helper = self.get_directory_helper();
+
+        let sdv_versions = try!(directory_helper.get_versions(parent_directory.get_key().get_id(), parent_directory.get_key().get_type_tag()));
+        let mut versions_to_prune = Vec::new();
+        for version_id in sdv_versions.iter().take(sdv_versions.len().saturating_sub(1)) {
+            let directory_listing = try!(directory_helper.get_by_version(parent_directory.get_key(), *version_id));
+            if let Some(file) = directory_listing.find_file(current_file.get_name()) {
+                if file != current_file {
+                    versions_to_prune.push(*version_id);
+                }
+            }
+        }
+        Ok(versions_to_prune)
+    }
+
+    /// Drops the historical states of a file, keeping only its current content.
+    /// As file versions live in the versions of the parent directory, the directory versions returned by
+    /// `history_to_forget` are pruned. This also removes the states that other files of the same directory had in those
+    /// versions, so the histories of the other files may be affected too. The operation can not be undone, so the
+    /// versions confirmed by the user, as returned by `history_to_forget`, have to be passed back.
+    /// Returns NfsError::ParameterIsNotValid, without pruning anything, if confirmed_versions are not the versions
+    /// `history_to_forget` returns for the latest state of the parent directory, e.g. if it was updated since.
+    /// Returns the latest DirectoryListing of the parent directory
+    pub fn forget_history(&self,
+                          file_name         : &str,
+                          parent_directory  : &mut ::directory_listing::DirectoryListing,
+                          confirmed_versions: &[::routing::NameType]) -> Result<::directory_listing::DirectoryListing, ::errors::NfsError> {
+        try!(self.check_writable());
+        let directory_helper = self.get_directory_helper();
+        let latest_directory = try!(directory_helper.get(parent_directory.get_key()));
+        let versions_to_prune = try!(self.history_to_forget(file_name, &latest_directory));
+        if &versions_to_prune[..] != confirmed_versions {
+            return Err(::errors::NfsError::ParameterIsNotValid);
+        }
+        debug!("Pruning {:?} versions holding older states of {:?} ...", versions_to_prune.len(), file_name);
+        if !versions_to_prune.is_empty() {
+            try!(directory_helper.prune_versions(parent_directory.get_key(), &versions_to_prune));
+        }
+        *parent_directory = try!(directory_helper.get(parent_directory.get_key()));
+        Ok(parent_directory.clone())
+    }
+
     /// Returns a reader for reading the file contents
     pub fn read<'a>(&self, file: &'a ::file::File) -> ::helper::reader::Reader<'a> {
-        ::helper::reader::Reader::new(self.client.clone(), file)
+        ::helper::reader::Reader::with_storage(self.client.clone(), self.storage.clone(), file)
+    }
+
+    /// Returns a reader through which the file contents can be read from several threads at once
+    pub fn read_shared(&self, file: ::file::File) -> ::helper::reader::SharedReader {
+        ::helper::reader::SharedReader::new(self.client.clone(), self.storage.clone(), file)
+    }
+
+    /// Returns a reader through which the contents of the file named file_name in the latest version of the parent
+    /// directory can be read from several threads at once, or None if the parent directory no longer exists in the
+    /// network or has no such file. Only failures to fetch or decode the parent directory are returned as errors, see
+    /// `DirectoryHelper::try_get`.
+    pub fn try_read(&self,
+                    file_name       : &str,
+                    parent_directory: &::directory_listing::DirectoryListing) -> Result<Option<::helper::reader::SharedReader>, ::errors::NfsError> {
+        let directory_helper = self.get_directory_helper();
+        Ok(try!(directory_helper.try_get(parent_directory.get_key()))
+               .and_then(|latest_directory| latest_directory.locate_file(file_name).map(|(_, file)| file.clone()))
+               .map(|file| self.read_shared(file)))
+    }
+
+    /// Returns a printable token through which the content of the file can be shared with other users, without sharing
+    /// the directory holding it. The token holds the DataMap and the size of the file, so anyone holding the token can
+    /// read the content; it should only be handed out for public content.
+    pub fn export_data_map_token(&self, file: &::file::File) -> Result<String, ::errors::NfsError> {
+        use ::rustc_serialize::hex::ToHex;
+        Ok(try!(::safe_core::utility::serialise(&(file.get_datamap().clone(), file.logical_size()))).to_hex())
+    }
+
+    /// Returns a reader for the content of a file shared through a token created by `export_data_map_token`.
+    /// The token must hold nothing but the serialised DataMap and size. The file read has no name or user metadata.
+    /// Returns NfsError::InvalidKeyFormat if the token is not a valid DataMap token
+    pub fn read_from_data_map_token(&self, token: &str) -> Result<::helper::reader::SharedReader, ::errors::NfsError> {
+        use ::rustc_serialize::hex::FromHex;
+        let serialised_token = try!(token.trim().from_hex().map_err(|_| ::errors::NfsError::InvalidKeyFormat));
+        let (datamap, size): (::self_encryption::datamap::DataMap, u64) =
+            try!(::safe_core::utility::deserialise(&serialised_token).map_err(|_| ::errors::NfsError::InvalidKeyFormat));
+        if try!(::safe_core::utility::serialise(&(datamap.clone(), size))) != serialised_token {
+            return Err(::errors::NfsError::InvalidKeyFormat);
+        }
+        let mut metadata = ::metadata::file_metadata::FileMetadata::new(String::new(), Vec::new());
+        metadata.set_size(size);
+        Ok(self.read_shared(try!(::file::File::new(metadata, datamap))))
+    }
+
+    /// Reads length bytes of the file contents from position, in the same way as `Reader::read`. The chunks holding the
+    /// range are read first, each waiting for up to the timeout, see `ChunkStorage::get_with_timeout`, and the range is
+    /// then decrypted from them. Nothing more is requested once a wait times out.
+    /// Returns NfsError::Timeout if a chunk could not be read within the timeout
+    pub fn read_with_timeout(&self,
+                             file    : &::file::File,
+                             position: u64,
+                             length  : u64,
+                             timeout : ::std::time::Duration) -> Result<Vec<u8>, ::errors::NfsError> {
+        let chunks = ::std::sync::Arc::new(::helper::chunk_storage::MemoryChunkStorage::new());
+        if let ::self_encryption::datamap::DataMap::Chunks(ref details) = *file.get_datamap() {
+            let mut sorted_details: Vec<&::self_encryption::datamap::ChunkDetails> = details.iter().collect();
+            sorted_details.sort_by(|lhs, rhs| lhs.chunk_num.cmp(&rhs.chunk_num));
+            let mut offset = 0;
+            for chunk in sorted_details {
+                if offset < position + length && position < offset + chunk.source_size {
+                    let content = try!(self.storage.get_with_timeout(chunk.hash.clone(), timeout));
+                    ::helper::chunk_storage::ChunkStorage::put(&*chunks, chunk.hash.clone(), content);
+                }
+                offset += chunk.source_size;
+            }
+        }
+        ::helper::reader::Reader::with_storage(self.client.clone(), chunks, file).read(position, length)
+    }
+
+    /// Creates a file in dest_listing holding a copy of the given byte range of the content of source, e.g. to extract a
+    /// part of a large file as a file of its own. The source is confirmed not to be stale against source_listing and is
+    /// copied SLICE_COPY_BUFFER_SIZE bytes at a time.
+    /// The dest_listing is only updated in place once the new file has been saved.
+    /// Returns NfsError::InvalidRangeSpecified if the range is not within the content of source.
+    /// Returns the saved DirectoryListing
+    pub fn create_from_slice(&self,
+                             name          : String,
+                             source        : &::file::File,
+                             range         : ::std::ops::Range<u64>,
+                             source_listing: &::directory_listing::DirectoryListing,
+                             dest_listing  : &mut ::directory_listing::DirectoryListing) -> Result<::directory_listing::DirectoryListing, ::errors::NfsError> {
+        try!(self.check_writable());
+        let mut reader = try!(self.read_verified(source, source_listing));
+        if range.start > range.end || range.end > reader.size() {
+            return Err(::errors::NfsError::InvalidRangeSpecified);
+        }
+        let mut writer = try!(self.create(name, Vec::new(), dest_listing.clone()));
+        try!(self.copy_range(&mut reader, range, &mut writer));
+        let (updated_listing, _) = try!(writer.close());
+        *dest_listing = updated_listing;
+        Ok(dest_listing.clone())
+    }
+
+    /// Creates a file named new_name in dest_listing with the user metadata of source and a copy of its content, which
+    /// is self-encrypted afresh to a new DataMap independent of the one of source, unlike sharing the DataMap the way
+    /// `Container::copy_blob` does. The source is confirmed not to be stale against source_listing and is copied
+    /// SLICE_COPY_BUFFER_SIZE bytes at a time, so the memory used does not grow with the size of the file.
+    /// The dest_listing is only updated in place once the new file has been saved.
+    /// Returns the saved DirectoryListing
+    pub fn copy_content(&self,
+                        source        : &::file::File,
+                        source_listing: &::directory_listing::DirectoryListing,
+                        new_name      : String,
+                        dest_listing  : &mut ::directory_listing::DirectoryListing) -> Result<::directory_listing::DirectoryListing, ::errors::NfsError> {
+        try!(self.check_writable());
+        let mut reader = try!(self.read_verified(source, source_listing));
+        let size = reader.size();
+        let mut writer = try!(self.create(new_name, source.get_metadata().get_user_metadata().clone(), dest_listing.clone()));
+        try!(self.copy_range(&mut reader, 0..size, &mut writer));
+        let (updated_listing, _) = try!(writer.close());
+        *dest_listing = updated_listing;
+        Ok(dest_listing.clone())
+    }
+
+    /// Copies the byte range of the content read by the reader to the start of the content of the writer,
+    /// SLICE_COPY_BUFFER_SIZE bytes at a time, uploading the chunks written after each buffer
+    fn copy_range(&self,
+                  reader: &mut ::helper::reader::Reader,
+                  range : ::std::ops::Range<u64>,
+                  writer: &mut ::helper::writer::Writer) -> Result<(), ::errors::NfsError> {
+        let mut position = range.start;
+        while position < range.end {
+            let length = ::std::cmp::min(SLICE_COPY_BUFFER_SIZE, range.end - position);
+            debug!("Copying {len} bytes of file content from offset of {pos} bytes ...", len = length, pos = position);
+            writer.write(&try!(reader.read(position, length)), position - range.start);
+            try!(writer.flush_chunks());
+            position += length;
+        }
+        Ok(())
+    }
+
+    /// Estimates the number of PUT requests needed to save a file with content of content_len bytes, so that the cost of
+    /// an upload can be shown or checked against a quota before writing. This is the number of chunks the content is
+    /// self-encrypted to, which is none for content small enough to be held inline in the DataMap, plus one for saving
+    /// the parent directory. No request is made to the network.
+    pub fn estimate_put_count(content_len: u64) -> usize {
+        let max_chunk_size = ::self_encryption::MAX_CHUNK_SIZE as u64;
+        let min_chunk_size = ::self_encryption::MIN_CHUNK_SIZE as u64;
+        let chunk_count = if content_len < 3 * min_chunk_size {
+            0
+        } else if content_len < 3 * max_chunk_size {
+            3
+        } else if content_len % max_chunk_size == 0 {
+            content_len / max_chunk_size
+        } else {
+            content_len / max_chunk_size + 1
+        };
+        chunk_count as usize + 1
+    }
+
+    /// Returns the location and size of each chunk holding the content of the file, in the order the chunks appear in
+    /// the file. Content held inline in the DataMap is reported as a single chunk named by the SHA-512 hash of the
+    /// content. An empty file has no chunks.
+    pub fn chunk_map(&self, file: &::file::File) -> Vec<::helper::chunk_info::ChunkInfo> {
+        match *file.get_datamap() {
+            ::self_encryption::datamap::DataMap::Chunks(ref chunks) => {
+                let mut sorted_chunks: Vec<&::self_encryption::datamap::ChunkDetails> = chunks.iter().collect();
+                sorted_chunks.sort_by(|lhs, rhs| lhs.chunk_num.cmp(&rhs.chunk_num));
+                let mut offset = 0;
+                sorted_chunks.into_iter().enumerate().map(|(index, chunk)| {
+                    let chunk_info = ::helper::chunk_info::ChunkInfo::new(index,
+                                                                          ::helper::chunk_info::chunk_name(&chunk.hash),
+                                                                          chunk.source_size,
+                                                                          offset);
+                    offset += chunk.source_size;
+                    chunk_info
+                }).collect()
+            },
+            ::self_encryption::datamap::DataMap::Content(ref content) => {
+                let name = ::routing::NameType::new(::sodiumoxide::crypto::hash::sha512::hash(content).0);
+                vec![::helper::chunk_info::ChunkInfo::new(0, name, content.len() as u64, 0)]
+            },
+            ::self_encryption::datamap::DataMap::None => Vec::new(),
+        }
+    }
+
+    /// Returns a reader for reading the file contents, after confirming against the latest state of the parent directory
+    /// in the network that the file is not stale.
+    /// Returns NfsError::StaleFileReference if the data-map of the file no longer matches the stored one
+    pub fn read_verified<'a>(&self,
+                             file            : &'a ::file::File,
+                             parent_directory: &::directory_listing::DirectoryListing) -> Result<::helper::reader::Reader<'a>, ::errors::NfsError> {
+        let directory_helper = self.get_directory_helper();
+        let latest_directory = try!(directory_helper.get(parent_directory.get_key()));
+        let stored_file = try!(latest_directory.find_file_by_id(file.get_id()).ok_or(::errors::NfsError::FileNotFound));
+        if *stored_file.get_datamap() != *file.get_datamap() {
+            return Err(::errors::NfsError::StaleFileReference);
+        }
+        Ok(::helper::reader::Reader::with_storage(self.client.clone(), self.storage.clone(), file))
+    }
+
+    /// Takes an advisory lock on the file for the owner, which expires after FILE_LOCK_EXPIRY_SECONDS.
+    /// The lock is recorded in the metadata of the file passed and saved in the parent directory, which is refreshed from
+    /// the network first. While the lock is held, `update_metadata` and `update_content` only accept the File holding
+    /// the lock. Locking a file already locked by the same owner renews the lock.
+    /// Returns NfsError::FileLocked if another owner holds a lock on the file which has not expired
+    pub fn lock(&self,
+                file            : &mut ::file::File,
+                owner_id        : String,
+                parent_directory: &mut ::directory_listing::DirectoryListing) -> Result<(), ::errors::NfsError> {
+        try!(self.check_writable());
+        try!(self.check_lock_owner(file, &owner_id, parent_directory));
+        debug!("Locking file for {:?} ...", owner_id);
+        file.get_mut_metadata().set_lock(Some(::metadata::file_lock::FileLock::new(owner_id, ::time::Duration::seconds(FILE_LOCK_EXPIRY_SECONDS))));
+        let _ = try!(self.save_metadata(file.clone(), parent_directory));
+        Ok(())
+    }
+
+    /// Releases the lock held on the file by the owner. Releasing a file which is not locked, or whose lock has expired,
+    /// succeeds as well.
+    /// Returns NfsError::FileLocked if another owner holds a lock on the file which has not expired
+    pub fn unlock(&self,
+                  file            : &mut ::file::File,
+                  owner_id        : &str,
+                  parent_directory: &mut ::directory_listing::DirectoryListing) -> Result<(), ::errors::NfsError> {
+        try!(self.check_writable());
+        try!(self.check_lock_owner(file, owner_id, parent_directory));
+        debug!("Unlocking file for {:?} ...", owner_id);
+        file.get_mut_metadata().set_lock(None);
+        let _ = try!(self.save_metadata(file.clone(), parent_directory));
+        Ok(())
+    }
+
+    /// Returns true if a lock which has not expired is held on the file
+    pub fn is_locked(&self, file: &::file::File) -> bool {
+        file.get_metadata().get_active_lock().is_some()
+    }
+
+    /// Refreshes the parent directory and checks that no owner other than owner_id holds an active lock on the file
+    fn check_lock_owner(&self,
+                        file            : &::file::File,
+                        owner_id        : &str,
+                        parent_directory: &mut ::directory_listing::DirectoryListing) -> Result<(), ::errors::NfsError> {
+        let directory_helper = self.get_directory_helper();
+        *parent_directory = try!(directory_helper.get(parent_directory.get_key()));
+        let stored_file = try!(parent_directory.find_file_by_id(file.get_id()).ok_or(::errors::NfsError::FileNotFound));
+        match stored_file.get_metadata().get_active_lock() {
+            Some(lock) if *lock.get_owner() != owner_id => Err(::errors::NfsError::FileLocked { owner: lock.get_owner().clone() }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Saves the file in the parent directory without any checks
+    /// Returns Option<parent_directory's parent>
+    fn save_metadata(&self,
+                     file            : ::file::File,
+                     parent_directory: &mut ::directory_listing::DirectoryListing) -> Result<Option<::directory_listing::DirectoryListing>, ::errors::NfsError> {
+        parent_directory.upsert_file(file);
+        let directory_helper = self.get_directory_helper();
+        directory_helper.update(&parent_directory)
+    }
+
+    /// Returns a DirectoryHelper holding the same shared key as this helper
+    fn get_directory_helper(&self) -> ::helper::directory_helper::DirectoryHelper {
+        ::helper::directory_helper::DirectoryHelper::from_parts(self.client.clone(), self.shared_key.clone(), self.read_only)
+            .with_chunk_storage(self.storage.clone())
+    }
+
+    /// Returns NfsError::ReadOnly if the helper is in read-only mode
+    fn check_writable(&self) -> Result<(), ::errors::NfsError> {
+        if self.read_only {
+            return Err(::errors::NfsError::ReadOnly);
+        }
+        Ok(())
+    }
+
+    /// Guesses the MIME type of the file from the magic number at the start of its content. Only the first
+    /// MIME_SNIFF_LENGTH bytes of the content are read.
+    /// PNG, JPEG, PDF, ZIP and GZIP content is recognised. Returns None for any other content.
+    pub fn sniff_mime(&self, file: &::file::File) -> Result<Option<String>, ::errors::NfsError> {
+        let head = try!(self.read(file).read_clamped(0, MIME_SNIFF_LENGTH));
+        Ok(MAGIC_NUMBERS.iter()
+                        .find(|&&(magic_number, _)| head.starts_with(magic_number))
+                        .map(|&(_, mime_type)| mime_type.to_string()))
     }
 }
 
+/// Number of seconds after which a lock taken on a file expires
+pub const FILE_LOCK_EXPIRY_SECONDS: i64 = 10 * 60;
+
+/// Checks that the file either holds the active lock of the stored file or that the stored file is not locked
+fn check_lock(file: &::file::File, parent_directory: &::directory_listing::DirectoryListing) -> Result<(), ::errors::NfsError> {
+    if let Some(stored_file) = parent_directory.find_file_by_id(file.get_id()) {
+        if let Some(lock) = stored_file.get_metadata().get_active_lock() {
+            if file.get_metadata().get_lock() != Some(lock) {
+                return Err(::errors::NfsError::FileLocked { owner: lock.get_owner().clone() });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Returns true if the content of the file is self-encrypted to a chunk with the hash passed
+fn uses_chunk(file: &::file::File, hash: &[u8]) -> bool {
+    match *file.get_datamap() {
+        ::self_encryption::datamap::DataMap::Chunks(ref chunks) => chunks.iter().any(|chunk| chunk.hash == hash),
+        _ => false,
+    }
+}
+
+/// Number of bytes of content copied at a time by `create_from_slice`
+const SLICE_COPY_BUFFER_SIZE: u64 = 1024 * 1024;
+
+/// Number of bytes read from the start of a file to guess its MIME type
+const MIME_SNIFF_LENGTH: u64 = 8;
+
+/// Magic numbers of the recognised content along with the MIME type of the content
+const MAGIC_NUMBERS: [(&'static [u8], &'static str); 5] = [
+    (b"\x89PNG\r\n\x1a\n",  "image/png"),
+    (b"\xff\xd8\xff",       "image/jpeg"),
+    (b"%PDF-",              "application/pdf"),
+    (b"PK\x03\x04",         "application/zip"),
+    (b"\x1f\x8b",           "application/gzip"),
+];
+
 #[cfg(test)]
 mod test {
     fn get_client() -> ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>> {
@@ -152,6 +786,7 @@ mod test {
         }
         {// update - full rewrite
             let file = eval_option!(directory.find_file(&file_name).map(|file| file.clone()), "File not found");
+            let stale_file = file.clone();
             let mut writer = eval_result!(file_helper.update_content(file, ::helper::writer::Mode::Overwrite, directory));
             writer.write(&vec![1u8; 50], 0);
             let (updated_directory, _) = eval_result!(writer.close());
@@ -160,6 +795,11 @@ mod test {
             let mut reader = file_helper.read(file);
             let size = reader.size();
             assert_eq!(eval_result!(reader.read(0, size)), vec![1u8; 50]);
+            assert!(file_helper.read_verified(file, &directory).is_ok());
+            match file_helper.read_verified(&stale_file, &directory) {
+                Err(::errors::NfsError::StaleFileReference) => (),
+                _ => panic!("Expected StaleFileReference"),
+            }
         }
         {// update - partial rewrite
             let file = eval_option!(directory.find_file(&file_name).map(|file| file.clone()), "File not found");
@@ -191,4 +831,776 @@ mod test {
             assert!(directory.find_file(&file_name).is_none());
         }
     }
+
+    #[test]
+    fn sniff_mime() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (mut directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        let contents: [(&str, &[u8], Option<&str>); 4] = [("image.png", b"\x89PNG\r\n\x1a\nIHDR", Some("image/png")),
+                                                          ("doc.pdf",   b"%PDF-1.4",                Some("application/pdf")),
+                                                          ("hello.txt", b"Hello World!",            None),
+                                                          ("empty",     b"",                        None)];
+        for &(file_name, content, _) in contents.iter() {
+            let mut writer = eval_result!(file_helper.create(file_name.to_string(), Vec::new(), directory));
+            writer.write(content, 0);
+            let (updated_directory, _) = eval_result!(writer.close());
+            directory = updated_directory;
+        }
+        for &(file_name, _, mime_type) in contents.iter() {
+            let file = eval_option!(directory.find_file(&file_name.to_string()), "File not found");
+            assert_eq!(eval_result!(file_helper.sniff_mime(file)), mime_type.map(|mime_type| mime_type.to_string()));
+        }
+    }
+
+    #[test]
+    fn lock_and_unlock() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        let file_name = "shared.txt".to_string();
+        let mut writer = eval_result!(file_helper.create(file_name.clone(), Vec::new(), directory));
+        writer.write("Hello World!".as_bytes(), 0);
+        let (mut directory, _) = eval_result!(writer.close());
+
+        let mut file = eval_option!(directory.find_file(&file_name).map(|file| file.clone()), "File not found");
+        let mut other_file = file.clone();
+        assert!(!file_helper.is_locked(&file));
+        eval_result!(file_helper.lock(&mut file, "alice".to_string(), &mut directory));
+        assert!(file_helper.is_locked(&file));
+        assert!(file_helper.is_locked(eval_option!(directory.find_file(&file_name), "File not found")));
+
+        // Another owner can neither lock nor update the file
+        match file_helper.lock(&mut other_file, "bob".to_string(), &mut directory) {
+            Err(::errors::NfsError::FileLocked { ref owner }) => assert_eq!(*owner, "alice".to_string()),
+            _ => panic!("Expected FileLocked"),
+        }
+        other_file.get_mut_metadata().set_user_metadata(vec![1u8; 10]);
+        match file_helper.update_metadata(other_file.clone(), &mut directory) {
+            Err(::errors::NfsError::FileLocked { ref owner }) => assert_eq!(*owner, "alice".to_string()),
+            _ => panic!("Expected FileLocked"),
+        }
+        match file_helper.unlock(&mut other_file, "bob", &mut directory) {
+            Err(::errors::NfsError::FileLocked { .. }) => (),
+            _ => panic!("Expected FileLocked"),
+        }
+
+        // The owner holding the lock can update the file
+        file.get_mut_metadata().set_user_metadata(vec![2u8; 10]);
+        let _ = eval_result!(file_helper.update_metadata(file.clone(), &mut directory));
+        let writer = eval_result!(file_helper.update_content(file.clone(), ::helper::writer::Mode::Modify, directory));
+        let (mut directory, _) = eval_result!(writer.close());
+        let mut file = eval_option!(directory.find_file(&file_name).map(|file| file.clone()), "File not found");
+        assert!(file_helper.is_locked(&file));
+
+        eval_result!(file_helper.unlock(&mut file, "alice", &mut directory));
+        assert!(!file_helper.is_locked(eval_option!(directory.find_file(&file_name), "File not found")));
+        let mut other_file = eval_option!(directory.find_file(&file_name).map(|file| file.clone()), "File not found");
+        eval_result!(file_helper.lock(&mut other_file, "bob".to_string(), &mut directory));
+
+        // An expired lock no longer has any effect
+        let mut expired_file = other_file.clone();
+        expired_file.get_mut_metadata().set_lock(Some(::metadata::file_lock::FileLock::new("bob".to_string(), ::time::Duration::seconds(-1))));
+        let _ = eval_result!(file_helper.save_metadata(expired_file.clone(), &mut directory));
+        assert!(!file_helper.is_locked(&expired_file));
+        eval_result!(file_helper.lock(&mut file, "alice".to_string(), &mut directory));
+    }
+
+    #[test]
+    fn set_name() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (mut directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        for name in ["a.txt", "b.txt"].iter() {
+            let mut writer = eval_result!(file_helper.create(name.to_string(), Vec::new(), directory));
+            writer.write(name.as_bytes(), 0);
+            let (updated_directory, _) = eval_result!(writer.close());
+            directory = updated_directory;
+        }
+        let datamap = eval_option!(directory.find_file(&"a.txt".to_string()), "File not found").get_datamap().clone();
+
+        match file_helper.set_name("a.txt", "b.txt".to_string(), &mut directory) {
+            Err(::errors::NfsError::FileAlreadyExistsWithSameName) => (),
+            _ => panic!("Expected FileAlreadyExistsWithSameName"),
+        }
+        assert!(directory.find_file(&"a.txt".to_string()).is_some());
+
+        let renamed_directory = eval_result!(file_helper.set_name("a.txt", "c.txt".to_string(), &mut directory));
+        assert_eq!(renamed_directory, directory);
+        assert!(directory.find_file(&"a.txt".to_string()).is_none());
+        let file = eval_option!(directory.find_file(&"c.txt".to_string()), "File not found");
+        assert_eq!(*file.get_datamap(), datamap);
+        assert_eq!(eval_result!(dir_helper.get(directory.get_key())), directory);
+    }
+
+    #[test]
+    fn delete_with_content() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (mut directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        let content = vec![5u8; 10 * 1024];
+        for name in ["a.txt", "b.txt"].iter() {
+            let mut writer = eval_result!(file_helper.create(name.to_string(), Vec::new(), directory));
+            writer.write(&content, 0);
+            let (updated_directory, _) = eval_result!(writer.close());
+            directory = updated_directory;
+        }
+
+        let _ = eval_result!(file_helper.delete_with_content("a.txt".to_string(), &mut directory));
+        assert!(directory.find_file(&"a.txt".to_string()).is_none());
+        // The chunks are shared with the remaining file, which must still be readable
+        let file = eval_option!(directory.find_file(&"b.txt".to_string()), "File not found");
+        let mut reader = file_helper.read(file);
+        let size = reader.size();
+        assert_eq!(eval_result!(reader.read(0, size)), content);
+
+        match file_helper.delete_with_content("a.txt".to_string(), &mut directory) {
+            Err(::errors::NfsError::FileNotFound) => (),
+            _ => panic!("Expected FileNotFound"),
+        }
+    }
+
+    #[test]
+    fn delete_with_content_keeps_copies() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let mut directories = Vec::new();
+        for name in ["Source", "Destination"].iter() {
+            let (directory, _) = eval_result!(dir_helper.create(name.to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+            directories.push(directory);
+        }
+        let mut destination = eval_option!(directories.pop(), "Directory not created");
+        let mut source = eval_option!(directories.pop(), "Directory not created");
+        let content = vec![7u8; 10 * 1024];
+
+        // A copy sharing the DataMap, as made by `Container::copy_blob`, keeps its chunks in the network
+        let file_helper = FileHelper::new(client.clone());
+        let mut writer = eval_result!(file_helper.create("shared.txt".to_string(), Vec::new(), source));
+        writer.write(&content, 0);
+        let (updated_source, _) = eval_result!(writer.close());
+        source = updated_source;
+        destination.upsert_file(eval_option!(source.find_file(&"shared.txt".to_string()), "File not found").clone());
+        let _ = eval_result!(dir_helper.update(&destination));
+        let _ = eval_result!(file_helper.delete_with_content("shared.txt".to_string(), &mut source));
+        {
+            let file = eval_option!(destination.find_file(&"shared.txt".to_string()), "File not found");
+            let mut reader = file_helper.read(file);
+            let size = reader.size();
+            assert_eq!(eval_result!(reader.read(0, size)), content);
+        }
+
+        // A copy self-encrypted afresh converges to the same chunks, which are kept until both files are deleted
+        let storage = ::std::sync::Arc::new(::helper::chunk_storage::MemoryChunkStorage::new());
+        let file_helper = FileHelper::with_storage(client.clone(), storage.clone());
+        let mut writer = eval_result!(file_helper.create("copied.txt".to_string(), Vec::new(), source));
+        writer.write(&content, 0);
+        let (updated_source, _) = eval_result!(writer.close());
+        source = updated_source;
+        let source_file = eval_option!(source.find_file(&"copied.txt".to_string()), "File not found").clone();
+        let _ = eval_result!(file_helper.copy_content(&source_file, &source, "copied.txt".to_string(), &mut destination));
+        let _ = eval_result!(file_helper.delete_with_content("copied.txt".to_string(), &mut source));
+        {
+            let file = eval_option!(destination.find_file(&"copied.txt".to_string()), "File not found");
+            let mut reader = file_helper.read(file);
+            let size = reader.size();
+            assert_eq!(eval_result!(reader.read(0, size)), content);
+        }
+        let _ = eval_result!(file_helper.delete_with_content("copied.txt".to_string(), &mut destination));
+        assert!(storage.is_empty());
+    }
+
+    #[test]
+    fn delete_with_distinct_content() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (mut directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+        let storage = ::std::sync::Arc::new(::helper::chunk_storage::MemoryChunkStorage::new());
+        let file_helper = FileHelper::with_storage(client.clone(), storage.clone());
+        for &(name, byte) in [("a.txt", 5u8), ("b.txt", 6u8)].iter() {
+            let mut writer = eval_result!(file_helper.create(name.to_string(), Vec::new(), directory));
+            writer.write(&vec![byte; 10 * 1024], 0);
+            let (updated_directory, _) = eval_result!(writer.close());
+            directory = updated_directory;
+        }
+        let chunk_names = |file: &::file::File| match *file.get_datamap() {
+            ::self_encryption::datamap::DataMap::Chunks(ref chunks) => chunks.iter().map(|chunk| chunk.hash.clone()).collect::<Vec<Vec<u8>>>(),
+            _ => panic!("Expected chunks"),
+        };
+        let deleted_chunks = chunk_names(eval_option!(directory.find_file(&"a.txt".to_string()), "File not found"));
+        let remaining_chunks = chunk_names(eval_option!(directory.find_file(&"b.txt".to_string()), "File not found"));
+
+        let _ = eval_result!(file_helper.delete_with_content("a.txt".to_string(), &mut directory));
+        for name in deleted_chunks {
+            assert!(::helper::chunk_storage::ChunkStorage::get(&*storage, name).is_empty());
+        }
+        for name in remaining_chunks {
+            assert!(!::helper::chunk_storage::ChunkStorage::get(&*storage, name).is_empty());
+        }
+        let file = eval_option!(directory.find_file(&"b.txt".to_string()), "File not found");
+        let mut reader = file_helper.read(file);
+        let size = reader.size();
+        assert_eq!(eval_result!(reader.read(0, size)), vec![6u8; 10 * 1024]);
+    }
+
+    #[test]
+    fn swap_names() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (mut directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        for name in ["a.txt", "a.txt.tmp"].iter() {
+            let mut writer = eval_result!(file_helper.create(name.to_string(), Vec::new(), directory));
+            writer.write(name.as_bytes(), 0);
+            let (updated_directory, _) = eval_result!(writer.close());
+            directory = updated_directory;
+        }
+
+        let swapped_directory = eval_result!(file_helper.swap_names("a.txt", "a.txt.tmp", &mut directory));
+        assert_eq!(swapped_directory, directory);
+        assert_eq!(eval_result!(dir_helper.get(directory.get_key())), directory);
+        let file = eval_option!(directory.find_file(&"a.txt".to_string()), "File not found");
+        let mut reader = file_helper.read(file);
+        let size = reader.size();
+        assert_eq!(eval_result!(reader.read(0, size)), "a.txt.tmp".as_bytes().to_vec());
+
+        match file_helper.swap_names("a.txt", "b.txt", &mut directory) {
+            Err(::errors::NfsError::FileNotFound) => (),
+            _ => panic!("Expected FileNotFound"),
+        }
+    }
+
+    #[test]
+    fn create_from_slice() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (mut directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        let data: Vec<u8> = (0..3 * 1024 * 1024).map(|index: u64| (index % 251) as u8).collect();
+        let mut writer = eval_result!(file_helper.create("archive.dat".to_string(), Vec::new(), directory));
+        writer.write(&data, 0);
+        let (updated_directory, _) = eval_result!(writer.close());
+        directory = updated_directory;
+        let source = eval_option!(directory.find_file(&"archive.dat".to_string()).map(|file| file.clone()), "File not found");
+
+        let source_listing = directory.clone();
+        let start = 1024 * 1024 - 10;
+        let end = 2 * 1024 * 1024 + 10;
+        let updated_directory = eval_result!(file_helper.create_from_slice("part.dat".to_string(),
+                                                                           &source,
+                                                                           start..end,
+                                                                           &source_listing,
+                                                                           &mut directory));
+        assert_eq!(updated_directory, directory);
+        let file = eval_option!(directory.find_file(&"part.dat".to_string()), "File not found");
+        let mut reader = file_helper.read(file);
+        let size = reader.size();
+        assert_eq!(size, end - start);
+        assert!(eval_result!(reader.read(0, size)) == &data[start as usize..end as usize]);
+
+        match file_helper.create_from_slice("invalid.dat".to_string(), &source, 0..data.len() as u64 + 1, &source_listing, &mut directory) {
+            Err(::errors::NfsError::InvalidRangeSpecified) => (),
+            _ => panic!("Expected InvalidRangeSpecified"),
+        }
+    }
+
+    #[test]
+    fn update_or_create() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        let file = eval_result!(::file::File::new(::metadata::file_metadata::FileMetadata::new("cache.dat".to_string(), vec![1u8]),
+                                                  ::self_encryption::datamap::DataMap::None));
+        let mut writer = eval_result!(file_helper.update_or_create(file, ::helper::writer::Mode::Modify, directory));
+        writer.write(&[1u8; 10], 0);
+        let (directory, _) = eval_result!(writer.close());
+        let file = eval_option!(directory.find_file(&"cache.dat".to_string()).map(|file| file.clone()), "File not found");
+        assert_eq!(*file.get_metadata().get_user_metadata(), vec![1u8]);
+
+        let mut writer = eval_result!(file_helper.update_or_create(file.clone(), ::helper::writer::Mode::Modify, directory));
+        writer.write(&[2u8; 5], 0);
+        let (directory, _) = eval_result!(writer.close());
+        let updated_file = eval_option!(directory.find_file(&"cache.dat".to_string()), "File not found");
+        assert_eq!(updated_file.get_id(), file.get_id());
+        let mut reader = file_helper.read(updated_file);
+        let size = reader.size();
+        let mut expected = vec![2u8; 5];
+        expected.extend(vec![1u8; 5]);
+        assert_eq!(eval_result!(reader.read(0, size)), expected);
+        assert_eq!(eval_result!(file_helper.get_versions(updated_file, &directory)).len(), 2);
+    }
+
+    #[test]
+    fn chunk_map() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let file_helper = FileHelper::new(client.clone());
+        let mut writer = eval_result!(file_helper.create("small.txt".to_string(), Vec::new(), directory));
+        writer.write("Hello World!".as_bytes(), 0);
+        let (directory, _) = eval_result!(writer.close());
+        let file = eval_option!(directory.find_file(&"small.txt".to_string()), "File not found");
+        let chunk_map = file_helper.chunk_map(file);
+        assert_eq!(chunk_map.len(), 1);
+        assert_eq!(chunk_map[0].get_size(), 12);
+
+        let data = vec![1u8; 5 * 1024 * 1024];
+        let mut writer = eval_result!(file_helper.create("large.dat".to_string(), Vec::new(), directory));
+        writer.write(&data, 0);
+        let (directory, _) = eval_result!(writer.close());
+        let file = eval_option!(directory.find_file(&"large.dat".to_string()), "File not found");
+        let chunk_map = file_helper.chunk_map(file);
+        assert!(chunk_map.len() > 1);
+        let mut offset = 0;
+        for (index, chunk_info) in chunk_map.iter().enumerate() {
+            assert_eq!(chunk_info.get_index(), index);
+            assert_eq!(chunk_info.get_offset(), offset);
+            offset += chunk_info.get_size();
+        }
+        assert_eq!(offset, data.len() as u64);
+    }
+
+    #[test]
+    fn estimate_put_count() {
+        let max_chunk_size = ::self_encryption::MAX_CHUNK_SIZE as u64;
+        let min_chunk_size = ::self_encryption::MIN_CHUNK_SIZE as u64;
+        assert_eq!(FileHelper::estimate_put_count(0), 1);
+        assert_eq!(FileHelper::estimate_put_count(3 * min_chunk_size - 1), 1);
+        assert_eq!(FileHelper::estimate_put_count(3 * min_chunk_size), 4);
+        assert_eq!(FileHelper::estimate_put_count(3 * max_chunk_size - 1), 4);
+        assert_eq!(FileHelper::estimate_put_count(5 * max_chunk_size), 6);
+        assert_eq!(FileHelper::estimate_put_count(5 * max_chunk_size + 1), 7);
+    }
+
+    #[test]
+    fn read_with_timeout() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let file_helper = FileHelper::new(client.clone());
+        let mut writer = eval_result!(file_helper.create("hello.txt".to_string(), Vec::new(), directory));
+        writer.write("Hello World!".as_bytes(), 0);
+        let (directory, _) = eval_result!(writer.close());
+        let file = eval_option!(directory.find_file(&"hello.txt".to_string()), "File not found");
+        let data = eval_result!(file_helper.read_with_timeout(file, 6, 5, ::std::time::Duration::from_secs(60)));
+        assert_eq!(data, "World".as_bytes().to_vec());
+
+        let content = (0..4 * ::self_encryption::MIN_CHUNK_SIZE).map(|index| (index % 251) as u8).collect::<Vec<u8>>();
+        let mut writer = eval_result!(file_helper.create("chunked.bin".to_string(), Vec::new(), directory));
+        writer.write(&content, 0);
+        let (directory, _) = eval_result!(writer.close());
+        let file = eval_option!(directory.find_file(&"chunked.bin".to_string()), "File not found");
+        let position = ::self_encryption::MIN_CHUNK_SIZE as usize + 10;
+        let data = eval_result!(file_helper.read_with_timeout(file, position as u64, 100, ::std::time::Duration::from_secs(60)));
+        assert_eq!(data, content[position..position + 100].to_vec());
+    }
+
+    #[test]
+    fn content_changed() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (mut directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+        let file_helper = FileHelper::new(client.clone());
+        for content in vec![vec![1u8; 12], vec![1u8; 4096]] {
+            let mut writer = eval_result!(file_helper.create("file.dat".to_string(), Vec::new(), directory));
+            writer.write(&content, 0);
+            let (updated_directory, _) = eval_result!(writer.close());
+            let original = eval_option!(updated_directory.find_file(&"file.dat".to_string()), "File not found").clone();
+
+            // Same content written again
+            let mut writer = eval_result!(file_helper.update_content(original.clone(), ::helper::writer::Mode::Overwrite, updated_directory));
+            writer.write(&content, 0);
+            let (updated_directory, _) = eval_result!(writer.close());
+            let rewritten = eval_option!(updated_directory.find_file(&"file.dat".to_string()), "File not found").clone();
+            assert!(!file_helper.content_changed(&original, &rewritten));
+
+            let mut updated_metadata = rewritten.clone();
+            updated_metadata.get_mut_metadata().set_user_metadata(vec![1u8; 10]);
+            assert!(!file_helper.content_changed(&original, &updated_metadata));
+
+            let mut writer = eval_result!(file_helper.update_content(rewritten, ::helper::writer::Mode::Modify, updated_directory));
+            writer.write(&[2u8], 5);
+            let (updated_directory, _) = eval_result!(writer.close());
+            let modified = eval_option!(updated_directory.find_file(&"file.dat".to_string()), "File not found").clone();
+            assert!(file_helper.content_changed(&original, &modified));
+
+            directory = updated_directory;
+            eval_result!(directory.remove_file(&"file.dat".to_string()));
+        }
+    }
+
+    #[test]
+    fn get_metadata_at_version() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let file_helper = FileHelper::new(client.clone());
+        let mut writer = eval_result!(file_helper.create("file.dat".to_string(), Vec::new(), directory));
+        writer.write(&[1u8; 100], 0);
+        let (mut directory, _) = eval_result!(writer.close());
+        let _ = eval_result!(file_helper.set_name("file.dat", "renamed.dat".to_string(), &mut directory));
+
+        let versions = eval_result!(dir_helper.get_versions(directory.get_key().get_id(), directory.get_key().get_type_tag()));
+        assert_eq!(versions.len(), 3);
+        let metadata = eval_result!(file_helper.get_metadata_at_version("file.dat", directory.get_key(), versions[1]));
+        assert_eq!(metadata.get_name(), "file.dat");
+        assert_eq!(metadata.get_size(), 100);
+        match file_helper.get_metadata_at_version("file.dat", directory.get_key(), versions[2]) {
+            Err(::errors::NfsError::FileNotFound) => (),
+            _ => panic!("Expected FileNotFound"),
+        }
+        let metadata = eval_result!(file_helper.get_metadata_at_version("renamed.dat", directory.get_key(), versions[2]));
+        assert_eq!(metadata.get_size(), 100);
+    }
+
+    #[test]
+    fn with_storage() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let storage = ::std::sync::Arc::new(::helper::chunk_storage::MemoryChunkStorage::new());
+        let file_helper = FileHelper::with_storage(client.clone(), storage.clone());
+        let content = (0..10 * 1024).map(|index| (index % 251) as u8).collect::<Vec<u8>>();
+        let mut writer = eval_result!(file_helper.create("file.dat".to_string(), Vec::new(), directory));
+        writer.write(&content, 0);
+        let (mut directory, _) = eval_result!(writer.close());
+        {
+            let file = eval_option!(directory.find_file(&"file.dat".to_string()), "File not found");
+            match *file.get_datamap() {
+                ::self_encryption::datamap::DataMap::Chunks(ref chunks) => assert_eq!(storage.len(), chunks.len()),
+                _ => panic!("Expected chunks"),
+            }
+
+            let mut reader = file_helper.read(file);
+            assert_eq!(eval_result!(reader.read(0, content.len() as u64)), content);
+        }
+
+        let _ = eval_result!(file_helper.delete_with_content("file.dat".to_string(), &mut directory));
+        assert!(storage.is_empty());
+    }
+
+    #[test]
+    fn compact() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let file_helper = FileHelper::new(client.clone());
+        let mut content = vec![1u8; 10 * 1024];
+        let mut writer = eval_result!(file_helper.create("edited.dat".to_string(), vec![7u8; 4], directory));
+        writer.write(&content, 0);
+        let (mut directory, _) = eval_result!(writer.close());
+        for position in vec![10, 3000, 9000] {
+            let file = eval_option!(directory.find_file(&"edited.dat".to_string()), "File not found").clone();
+            let mut writer = eval_result!(file_helper.update_content(file, ::helper::writer::Mode::Modify, directory));
+            writer.write(&[2u8; 5], position);
+            for byte in content.iter_mut().skip(position as usize).take(5) {
+                *byte = 2u8;
+            }
+            let (updated_directory, _) = eval_result!(writer.close());
+            directory = updated_directory;
+        }
+        let edited = eval_option!(directory.find_file(&"edited.dat".to_string()), "File not found").clone();
+
+        let compacted_directory = eval_result!(file_helper.compact(edited.clone(), &mut directory));
+        assert_eq!(compacted_directory, directory);
+        assert_eq!(eval_result!(dir_helper.get(directory.get_key())), directory);
+        let compacted = eval_option!(directory.find_file(&"edited.dat".to_string()), "File not found").clone();
+        assert_eq!(compacted.get_metadata(), edited.get_metadata());
+        let mut reader = file_helper.read(&compacted);
+        assert_eq!(eval_result!(reader.read(0, content.len() as u64)), content);
+
+        // Same DataMap as the content written at once
+        let mut writer = eval_result!(file_helper.create("written.dat".to_string(), Vec::new(), directory));
+        writer.write(&content, 0);
+        let (directory, _) = eval_result!(writer.close());
+        let written = eval_option!(directory.find_file(&"written.dat".to_string()), "File not found");
+        assert_eq!(compacted.get_datamap(), written.get_datamap());
+    }
+
+    #[test]
+    fn first_version_with_content() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let file_helper = FileHelper::new(client.clone());
+        let mut writer = eval_result!(file_helper.create("file.dat".to_string(), Vec::new(), directory));
+        writer.write(&[1u8; 100], 0);
+        let (directory, _) = eval_result!(writer.close());
+        let first_file = eval_option!(directory.find_file(&"file.dat".to_string()), "File not found").clone();
+        let first_hash = eval_result!(file_helper.content_hash(&first_file));
+        assert_eq!(first_hash, ::sodiumoxide::crypto::hash::sha512::hash(&[1u8; 100]).0.to_vec());
+
+        let mut writer = eval_result!(file_helper.update_content(first_file, ::helper::writer::Mode::Overwrite, directory));
+        writer.write(&[2u8; 100], 0);
+        let (directory, _) = eval_result!(writer.close());
+        let second_file = eval_option!(directory.find_file(&"file.dat".to_string()), "File not found").clone();
+        let second_hash = eval_result!(file_helper.content_hash(&second_file));
+
+        let versions = eval_result!(dir_helper.get_versions(directory.get_key().get_id(), directory.get_key().get_type_tag()));
+        assert_eq!(versions.len(), 3);
+        assert_eq!(eval_result!(file_helper.first_version_with_content("file.dat", directory.get_key(), &first_hash)),
+                   Some(versions[1]));
+        assert_eq!(eval_result!(file_helper.first_version_with_content("file.dat", directory.get_key(), &second_hash)),
+                   Some(versions[2]));
+        assert_eq!(eval_result!(file_helper.first_version_with_content("file.dat", directory.get_key(), &[0u8; 64])), None);
+        assert_eq!(eval_result!(file_helper.first_version_with_content("other.dat", directory.get_key(), &first_hash)), None);
+    }
+
+    #[test]
+    fn create_unique() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (mut directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+        let file_helper = FileHelper::new(client.clone());
+        for expected_name in vec!["upload.dat", "upload.dat (1)"] {
+            let mut writer = eval_result!(file_helper.create_unique("upload.dat", Vec::new(), directory));
+            assert_eq!(writer.get_file_name(), expected_name);
+            writer.write(&[1u8; 10], 0);
+            let (updated_directory, _) = eval_result!(writer.close());
+            directory = updated_directory;
+        }
+        assert_eq!(directory.get_files().len(), 2);
+    }
+
+    #[test]
+    fn set_name_changing_case() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let file_helper = FileHelper::new(client.clone());
+        let mut writer = eval_result!(file_helper.create("Readme".to_string(), Vec::new(), directory));
+        writer.write(&[1u8; 10], 0);
+        let (mut directory, _) = eval_result!(writer.close());
+        let file_id = eval_option!(directory.find_file(&"Readme".to_string()), "File not found").get_id().clone();
+
+        let _ = eval_result!(file_helper.set_name("Readme", "README".to_string(), &mut directory));
+        let directory = eval_result!(dir_helper.get(directory.get_key()));
+        assert!(directory.find_file(&"Readme".to_string()).is_none());
+        let file = eval_option!(directory.find_file(&"README".to_string()), "File not found");
+        assert_eq!(*file.get_name(), "README");
+        assert_eq!(*file.get_id(), file_id);
+        assert_eq!(directory.get_files().len(), 1);
+    }
+
+    #[test]
+    fn copy_content() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (mut source_directory, _) = eval_result!(dir_helper.create("Public".to_string(),
+                                                                       ::UNVERSIONED_DIRECTORY_LISTING_TAG,
+                                                                       Vec::new(),
+                                                                       false,
+                                                                       ::AccessLevel::Public,
+                                                                       None));
+        let (mut dest_directory, _) = eval_result!(dir_helper.create("Private".to_string(),
+                                                                     ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                     Vec::new(),
+                                                                     true,
+                                                                     ::AccessLevel::Private,
+                                                                     None));
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        let data: Vec<u8> = (0..3 * 1024 * 1024 + 10).map(|index: u64| (index % 251) as u8).collect();
+        let mut writer = eval_result!(file_helper.create("source.dat".to_string(), vec![1u8, 2, 3], source_directory));
+        writer.write(&data, 0);
+        let (updated_directory, _) = eval_result!(writer.close());
+        source_directory = updated_directory;
+        let source = eval_option!(source_directory.find_file(&"source.dat".to_string()).map(|file| file.clone()), "File not found");
+
+        let updated_directory = eval_result!(file_helper.copy_content(&source,
+                                                                      &source_directory,
+                                                                      "copy.dat".to_string(),
+                                                                      &mut dest_directory));
+        assert_eq!(updated_directory, dest_directory);
+        let copy = eval_option!(dest_directory.find_file(&"copy.dat".to_string()), "File not found");
+        assert_eq!(copy.get_metadata().get_user_metadata(), &vec![1u8, 2, 3]);
+        assert!(copy.get_datamap() != source.get_datamap());
+        let mut reader = file_helper.read(copy);
+        let size = reader.size();
+        assert_eq!(size, data.len() as u64);
+        assert!(eval_result!(reader.read(0, size)) == data);
+    }
+
+    #[test]
+    fn try_read() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        let mut writer = eval_result!(file_helper.create("file.txt".to_string(), Vec::new(), directory.clone()));
+        writer.write(&[1u8, 2, 3], 0);
+        let _ = eval_result!(writer.close());
+
+        // The stale parent directory passed is refreshed from the network
+        let reader = eval_option!(eval_result!(file_helper.try_read("file.txt", &directory)), "File not found");
+        assert_eq!(eval_result!(reader.read(0, reader.size())), vec![1u8, 2, 3]);
+        assert!(eval_result!(file_helper.try_read("missing.txt", &directory)).is_none());
+    }
+
+    #[test]
+    fn data_map_token() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let directory = eval_result!(dir_helper.create_public("Public".to_string(), Vec::new(), None));
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        let data: Vec<u8> = (0..2 * 1024 * 1024).map(|index: u64| (index % 251) as u8).collect();
+        let mut writer = eval_result!(file_helper.create("shared.dat".to_string(), Vec::new(), directory));
+        writer.write(&data, 0);
+        let (directory, _) = eval_result!(writer.close());
+        let file = eval_option!(directory.find_file(&"shared.dat".to_string()), "File not found");
+
+        let token = eval_result!(file_helper.export_data_map_token(file));
+        let other_helper = ::helper::file_helper::FileHelper::new(get_client());
+        let reader = eval_result!(other_helper.read_from_data_map_token(&token));
+        assert_eq!(reader.size(), data.len() as u64);
+        assert!(eval_result!(reader.read(0, reader.size())) == data);
+
+        for invalid_token in &["not hex".to_string(), "abcd".to_string(), format!("{}00", token)] {
+            match file_helper.read_from_data_map_token(invalid_token) {
+                Err(::errors::NfsError::InvalidKeyFormat) => (),
+                _ => panic!("Expected InvalidKeyFormat"),
+            }
+        }
+    }
+
+    #[test]
+    fn forget_history() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (mut directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                                ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                                Vec::new(),
+                                                                true,
+                                                                ::AccessLevel::Private,
+                                                                None));
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        let file_name = "hello.txt".to_string();
+        let mut writer = eval_result!(file_helper.create(file_name.clone(), Vec::new(), directory));
+        writer.write(&vec![0u8; 100], 0);
+        let (updated_directory, _) = eval_result!(writer.close());
+        directory = updated_directory;
+        for content in 1u8..3 {
+            let file = eval_option!(directory.find_file(&file_name).map(|file| file.clone()), "File not found");
+            let mut writer = eval_result!(file_helper.update_content(file, ::helper::writer::Mode::Overwrite, directory));
+            writer.write(&vec![content; 50], 0);
+            let (updated_directory, _) = eval_result!(writer.close());
+            directory = updated_directory;
+        }
+        let file = eval_option!(directory.find_file(&file_name).map(|file| file.clone()), "File not found");
+        assert_eq!(eval_result!(file_helper.get_versions(&file, &directory)).len(), 3);
+
+        let versions_to_forget = eval_result!(file_helper.history_to_forget(&file_name, &directory));
+        assert_eq!(versions_to_forget.len(), 2);
+        assert_eq!(eval_result!(file_helper.get_versions(&file, &directory)).len(), 3);
+
+        // Versions which were not all confirmed leave the history untouched
+        match file_helper.forget_history(&file_name, &mut directory, &versions_to_forget[1..]) {
+            Err(::errors::NfsError::ParameterIsNotValid) => (),
+            _ => panic!("Expected ParameterIsNotValid"),
+        }
+        assert_eq!(eval_result!(file_helper.get_versions(&file, &directory)).len(), 3);
+
+        let _ = eval_result!(file_helper.forget_history(&file_name, &mut directory, &versions_to_forget));
+        let file = eval_option!(directory.find_file(&file_name).map(|file| file.clone()), "File not found");
+        assert_eq!(eval_result!(file_helper.get_versions(&file, &directory)).len(), 1);
+        assert!(eval_result!(file_helper.history_to_forget(&file_name, &directory)).is_empty());
+    }
 }
@@ -23,3 +23,20 @@ pub mod reader;
 pub mod file_helper;
 /// DirectoryHelper provides functions for CRUD on DirectoryListing
 pub mod directory_helper;
+/// VersionIterator walks the history of a versioned directory
+pub mod version_iterator;
+/// ValidationReport lists the problems found in a directory tree
+pub mod validation_report;
+/// SyncPlan describes the actions needed to mirror a local directory tree in the network
+pub mod sync_plan;
+/// BufferedFile holds the content of a small file in memory until it is committed
+pub mod buffered_file;
+/// ChunkInfo describes the location and size of a chunk of a file
+pub mod chunk_info;
+/// EntryPager pages through the entries of a directory
+pub mod entry_pager;
+/// ChunkStorage abstracts where the chunks of file contents are kept
+pub mod chunk_storage;
+
+mod tar;
+mod timeout;
@@ -15,41 +15,847 @@
 // Please review the Licences for the specific language governing permissions and limitations
 // relating to use of the SAFE Network Software.
 
+/// Number of bytes of the file read at a time while iterating over its lines
+const LINE_BUFFER_SIZE: u64 = 64 * 1024;
+/// Maximum number of chunks held by the cache shared by the reads of a SharedReader
+pub const SHARED_READER_CACHED_CHUNKS: usize = 8;
+
 /// Reader is used to read contents of a File. It can read in chunks if the file happens to be very
 /// large
 #[allow(dead_code)]
 pub struct Reader<'a> {
     client        : ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
-    self_encryptor: ::self_encryption::SelfEncryptor<::safe_core::SelfEncryptionStorage>,
+    storage       : ::std::sync::Arc<ReadAheadStorage>,
+    self_encryptor: ::self_encryption::SelfEncryptor<ReadAheadStorage>,
     file          : &'a ::file::File,
+    read_ahead    : usize,
+    prefetcher    : Option<::std::sync::mpsc::SyncSender<Vec<u8>>>,
+    position      : u64,
 }
 
 impl<'a> Reader<'a> {
     /// Create a new instance of Reader
     pub fn new(client: ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
                file  : &'a ::file::File) -> Reader {
-        let se_storage = ::safe_core::SelfEncryptionStorage::new(client.clone());
+        let storage = ::std::sync::Arc::new(::helper::chunk_storage::NetworkChunkStorage::new(client.clone()));
+        Reader::with_storage(client, storage, file)
+    }
+
+    /// Create a new instance of Reader fetching the chunks of the file from the storage passed
+    pub fn with_storage(client : ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
+                        storage: ::std::sync::Arc<::helper::chunk_storage::ChunkStorage>,
+                        file   : &'a ::file::File) -> Reader {
+        let se_storage = ::std::sync::Arc::new(ReadAheadStorage {
+            storage: storage,
+            cache  : ::std::sync::Mutex::new(ReadAheadCache {
+                window: Vec::new(),
+                chunks: ::std::collections::HashMap::new(),
+            }),
+        });
 
         Reader {
             client        : client.clone(),
+            storage       : se_storage.clone(),
             self_encryptor: ::self_encryption::SelfEncryptor::new(se_storage, file.get_datamap().clone()),
             file          : file,
+            read_ahead    : 0,
+            prefetcher    : None,
+            position      : 0,
+        }
+    }
+
+    /// Enables read-ahead for sequential reads. After each read, the chunks following the last chunk read are fetched
+    /// in the background, up to the given number of chunks, so that a subsequent read of them does not wait on the
+    /// network. A chunk which fails to be prefetched is simply fetched again if it is read, so any error only surfaces
+    /// for reads of that chunk.
+    /// The chunks are fetched one at a time by a single background thread, which ends once the Reader is dropped. At
+    /// most the given number of prefetched chunks are held: a chunk is dropped once it has been read, or once a read
+    /// elsewhere in the file moves it out of the chunks following the last chunk read.
+    pub fn with_read_ahead(mut self, chunks: usize) -> Reader<'a> {
+        self.read_ahead = chunks;
+        self
+    }
+
+    /// Positions the reader at offset before any read, so that a download interrupted after offset bytes is resumed
+    /// with `read_next` without reading the start of the file again.
+    /// Returns NfsError::InvalidRangeSpecified if offset is beyond the end of the file
+    pub fn starting_at(mut self, offset: u64) -> Result<Reader<'a>, ::errors::NfsError> {
+        try!(self.seek_to(offset));
+        Ok(self)
+    }
+
+    /// Returns the offset following the last byte read by `read` or `read_next`, or the offset last sought to
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Moves the position from which `read_next` reads to offset.
+    /// Returns NfsError::InvalidRangeSpecified if offset is beyond the end of the file
+    pub fn seek_to(&mut self, offset: u64) -> Result<(), ::errors::NfsError> {
+        if offset > self.size() {
+            return Err(::errors::NfsError::InvalidRangeSpecified);
+        }
+        self.position = offset;
+        Ok(())
+    }
+
+    /// Read up to length bytes of the file/blob from the current position, moving the position past the data read.
+    /// Fewer bytes are returned at the end of the file, and none once the position is at the end of the file
+    pub fn read_next(&mut self, length: u64) -> Result<Vec<u8>, ::errors::NfsError> {
+        let position = self.position;
+        self.read_clamped(position, length)
+    }
+
+    /// Read up to length bytes of the file/blob from position, in the same way as `read` except that a range reaching
+    /// beyond the end of the file is cut short at the end of the file instead of being rejected. Fewer bytes than
+    /// requested are returned at the end of the file, and none from a position at or beyond the end of the file
+    pub fn read_clamped(&mut self, position: u64, length: u64) -> Result<Vec<u8>, ::errors::NfsError> {
+        let size = self.size();
+        if position >= size {
+            return Ok(Vec::new());
         }
+        self.read(position, ::std::cmp::min(length, size - position))
     }
 
     /// Returns the total size of the file/blob
     pub fn size(&self) -> u64 {
         debug!("Retrieving file length ...");
-        self.self_encryptor.len()
+        ::std::cmp::max(self.self_encryptor.len(), self.file.logical_size())
     }
-    
+
     /// Read data from file/blob
+    /// Holes in the file, i.e. regions beyond the content held by the data-map, read as zeros
+    /// without fetching any chunks from the network
     pub fn read(&mut self,  position: u64, length: u64) -> Result<Vec<u8>, ::errors::NfsError> {
         if (position + length) > self.size() {
             Err(::errors::NfsError::InvalidRangeSpecified)
         } else {
             debug!("Reading {len} bytes of data from file starting at offset of {pos} bytes ...", len = length, pos = position);
-            Ok(self.self_encryptor.read(position, length))
+            let stored_length = self.self_encryptor.len();
+            let mut data = if position < stored_length {
+                self.self_encryptor.read(position, ::std::cmp::min(length, stored_length - position))
+            } else {
+                Vec::new()
+            };
+            let hole_length = length as usize - data.len();
+            data.extend(::std::iter::repeat(0u8).take(hole_length));
+            if self.read_ahead != 0 && length != 0 {
+                self.prefetch_after(position + length - 1);
+            }
+            self.position = position + length;
+            Ok(data)
+        }
+    }
+
+    /// Read several byte ranges of the file/blob in one call, returning the data of each range in the order the ranges
+    /// are passed. Every chunk overlapped by the ranges is fetched and decrypted only once, however many of the ranges
+    /// it holds data for, so scattered records sharing chunks are read without downloading a chunk repeatedly.
+    /// Holes in the file read as zeros, as for `read`.
+    /// Returns NfsError::InvalidRangeSpecified, before reading anything, if any range is not within the file
+    pub fn read_ranges(&mut self, ranges: &[::std::ops::Range<u64>]) -> Result<Vec<Vec<u8>>, ::errors::NfsError> {
+        let size = self.size();
+        if ranges.iter().any(|range| range.start > range.end || range.end > size) {
+            return Err(::errors::NfsError::InvalidRangeSpecified);
+        }
+        let file = self.file;
+        let chunks = match *file.get_datamap() {
+            ::self_encryption::datamap::DataMap::Chunks(ref chunks) => chunk_ranges(chunks),
+            _ => return ranges.iter().map(|range| self.read(range.start, range.end - range.start)).collect(),
+        };
+        let mut chunk_contents = ::std::collections::HashMap::new();
+        let mut ranges_data = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            debug!("Reading {len} bytes of data from file starting at offset of {pos} bytes ...",
+                   len = range.end - range.start,
+                   pos = range.start);
+            let mut data = Vec::with_capacity((range.end - range.start) as usize);
+            for (chunk_index, &(offset, length, _)) in chunks.iter().enumerate() {
+                if offset >= range.end || offset + length <= range.start {
+                    continue;
+                }
+                if !chunk_contents.contains_key(&chunk_index) {
+                    debug!("Reading chunk {:?} of {len} bytes starting at offset of {pos} bytes ...", chunk_index, len = length, pos = offset);
+                    let _ = chunk_contents.insert(chunk_index, self.self_encryptor.read(offset, length));
+                }
+                let content = &chunk_contents[&chunk_index];
+                let start = (::std::cmp::max(range.start, offset) - offset) as usize;
+                let end = (::std::cmp::min(range.end, offset + length) - offset) as usize;
+                data.extend(content[start..end].iter().cloned());
+            }
+            let hole_length = (range.end - range.start) as usize - data.len();
+            data.extend(::std::iter::repeat(0u8).take(hole_length));
+            ranges_data.push(data);
+        }
+        Ok(ranges_data)
+    }
+
+    /// Read the decrypted content of a single self-encryption chunk of the file/blob, with the chunks ordered as they
+    /// appear in the file. A file whose content is held inline in the data-map is treated as a single chunk.
+    /// Returns NfsError::InvalidRangeSpecified if the file has no chunk at chunk_index
+    pub fn read_chunk(&mut self, chunk_index: usize) -> Result<Vec<u8>, ::errors::NfsError> {
+        let (position, length) = match *self.file.get_datamap() {
+            ::self_encryption::datamap::DataMap::Chunks(ref chunks) => {
+                let chunk_ranges = chunk_ranges(chunks);
+                if chunk_index >= chunk_ranges.len() {
+                    return Err(::errors::NfsError::InvalidRangeSpecified);
+                }
+                (chunk_ranges[chunk_index].0, chunk_ranges[chunk_index].1)
+            },
+            ::self_encryption::datamap::DataMap::Content(ref content) if chunk_index == 0 => (0, content.len() as u64),
+            _ => return Err(::errors::NfsError::InvalidRangeSpecified),
+        };
+        debug!("Reading chunk {:?} of {len} bytes starting at offset of {pos} bytes ...", chunk_index, len = length, pos = position);
+        Ok(self.self_encryptor.read(position, length))
+    }
+
+    /// Read the complete content of the file/blob as UTF-8 text.
+    /// If max_size is specified and the file is larger, NfsError::InvalidRangeSpecified is returned without reading.
+    /// Returns NfsError::InvalidUtf8 if the content is not valid UTF-8.
+    pub fn read_to_string(&mut self, max_size: Option<u64>) -> Result<String, ::errors::NfsError> {
+        let size = self.size();
+        if max_size.map_or(false, |max_size| size > max_size) {
+            return Err(::errors::NfsError::InvalidRangeSpecified);
+        }
+        let data = try!(self.read(0, size));
+        String::from_utf8(data).map_err(|_| ::errors::NfsError::InvalidUtf8)
+    }
+
+    /// Returns an iterator over the lines of the file. The file is read LINE_BUFFER_SIZE bytes at a time as the lines are
+    /// consumed, so a large file is never held in memory as a whole. Lines are terminated by either `\n` or `\r\n`,
+    /// and the terminator is not part of the line returned.
+    /// A line which is not valid UTF-8 is returned as NfsError::InvalidUtf8 and the iteration continues with the next line
+    pub fn lines<'r>(&'r mut self) -> LineIterator<'r, 'a> {
+        LineIterator {
+            reader  : self,
+            position: 0,
+            buffer  : Vec::new(),
+            finished: false,
+        }
+    }
+
+    /// Starts fetching, in the background, the chunks following the chunk holding the byte at position
+    fn prefetch_after(&mut self, position: u64) {
+        let chunks = match *self.file.get_datamap() {
+            ::self_encryption::datamap::DataMap::Chunks(ref chunks) => chunk_ranges(chunks),
+            _ => return,
+        };
+        let current_chunk = match chunks.iter().position(|&(offset, size, _)| position < offset + size) {
+            Some(index) => index,
+            None => return,
+        };
+        let window_end = ::std::cmp::min(current_chunk + 1 + self.read_ahead, chunks.len());
+        let window: Vec<Vec<u8>> = chunks[current_chunk + 1..window_end].iter().map(|&(_, _, ref name)| name.clone()).collect();
+        let requested = self.storage.set_window(window);
+        if requested.is_empty() {
+            return;
+        }
+        if self.prefetcher.is_none() {
+            let (sender, receiver) = ::std::sync::mpsc::sync_channel::<Vec<u8>>(self.read_ahead);
+            let storage = self.storage.clone();
+            let _ = ::std::thread::spawn(move || {
+                for name in receiver.iter() {
+                    storage.prefetch(name);
+                }
+            });
+            self.prefetcher = Some(sender);
+        }
+        if let Some(ref prefetcher) = self.prefetcher {
+            for name in requested {
+                debug!("Prefetching chunk ...");
+                if prefetcher.try_send(name.clone()).is_err() {
+                    // The chunk is fetched when it is read instead
+                    self.storage.forget(&name);
+                }
+            }
+        }
+    }
+}
+
+/// SharedReader reads the contents of a File from several threads at once, e.g. to download the disjoint ranges of a
+/// large file in parallel. Each read fetches only the chunks overlapped by its range. The chunks fetched are kept in a
+/// cache shared by all the reads, holding up to SHARED_READER_CACHED_CHUNKS chunks, so that reads of neighbouring
+/// ranges do not fetch the chunks they share again.
+pub struct SharedReader {
+    client : ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
+    storage: ::std::sync::Arc<SharedChunkCache>,
+    file   : ::file::File,
+}
+
+impl SharedReader {
+    /// Create a new instance of SharedReader fetching the chunks of the file from the storage passed
+    pub fn new(client : ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
+               storage: ::std::sync::Arc<::helper::chunk_storage::ChunkStorage>,
+               file   : ::file::File) -> SharedReader {
+        SharedReader {
+            client : client,
+            storage: ::std::sync::Arc::new(SharedChunkCache {
+                storage: storage,
+                cache  : ::std::sync::Mutex::new(CachedChunks {
+                    order : ::std::collections::VecDeque::new(),
+                    chunks: ::std::collections::HashMap::new(),
+                }),
+            }),
+            file   : file,
+        }
+    }
+
+    /// Returns the total size of the file/blob
+    pub fn size(&self) -> u64 {
+        self.file.logical_size()
+    }
+
+    /// Read data from file/blob, in the same way as `Reader::read`
+    pub fn read(&self, position: u64, length: u64) -> Result<Vec<u8>, ::errors::NfsError> {
+        Reader::with_storage(self.client.clone(), self.storage.clone(), &self.file).read(position, length)
+    }
+}
+
+/// ChunkStorage of a SharedReader, which keeps the chunks fetched from the chunk storage for all the reads
+struct SharedChunkCache {
+    storage: ::std::sync::Arc<::helper::chunk_storage::ChunkStorage>,
+    cache  : ::std::sync::Mutex<CachedChunks>,
+}
+
+/// Chunks held by a SharedChunkCache, along with the order they were cached in so that the oldest is evicted first
+struct CachedChunks {
+    order : ::std::collections::VecDeque<Vec<u8>>,
+    chunks: ::std::collections::HashMap<Vec<u8>, Vec<u8>>,
+}
+
+impl ::helper::chunk_storage::ChunkStorage for SharedChunkCache {
+    fn get(&self, name: Vec<u8>) -> Vec<u8> {
+        if let Some(content) = eval_result!(self.cache.lock()).chunks.get(&name) {
+            return content.clone();
+        }
+        // The lock is not held while fetching, so that reads of other chunks are not held up
+        let content = self.storage.get(name.clone());
+        if !content.is_empty() {
+            let mut cache = eval_result!(self.cache.lock());
+            if cache.chunks.insert(name.clone(), content.clone()).is_none() {
+                cache.order.push_back(name);
+            }
+            while cache.order.len() > SHARED_READER_CACHED_CHUNKS {
+                if let Some(evicted) = cache.order.pop_front() {
+                    let _ = cache.chunks.remove(&evicted);
+                }
+            }
+        }
+        content
+    }
+
+    fn put(&self, name: Vec<u8>, data: Vec<u8>) {
+        self.storage.put(name, data)
+    }
+
+    fn delete(&self, name: Vec<u8>) {
+        {
+            let mut cache = eval_result!(self.cache.lock());
+            if cache.chunks.remove(&name).is_some() {
+                cache.order.retain(|cached_name| *cached_name != name);
+            }
+        }
+        self.storage.delete(name)
+    }
+}
+
+/// Iterates over the lines of a file, see `Reader::lines`
+pub struct LineIterator<'r, 'a: 'r> {
+    reader  : &'r mut Reader<'a>,
+    position: u64,
+    buffer  : Vec<u8>,
+    finished: bool,
+}
+
+impl<'r, 'a> Iterator for LineIterator<'r, 'a> {
+    type Item = Result<String, ::errors::NfsError>;
+
+    fn next(&mut self) -> Option<Result<String, ::errors::NfsError>> {
+        if self.finished {
+            return None;
+        }
+        loop {
+            if let Some(index) = self.buffer.iter().position(|byte| *byte == b'\n') {
+                let remaining = self.buffer.split_off(index + 1);
+                let mut line = ::std::mem::replace(&mut self.buffer, remaining);
+                let _ = line.pop();
+                if line.last() == Some(&b'\r') {
+                    let _ = line.pop();
+                }
+                return Some(String::from_utf8(line).map_err(|_| ::errors::NfsError::InvalidUtf8));
+            }
+            let size = self.reader.size();
+            if self.position >= size {
+                self.finished = true;
+                if self.buffer.is_empty() {
+                    return None;
+                }
+                let line = ::std::mem::replace(&mut self.buffer, Vec::new());
+                return Some(String::from_utf8(line).map_err(|_| ::errors::NfsError::InvalidUtf8));
+            }
+            let length = ::std::cmp::min(LINE_BUFFER_SIZE, size - self.position);
+            debug!("Reading {len} bytes of lines starting at offset of {pos} bytes ...", len = length, pos = self.position);
+            match self.reader.read(self.position, length) {
+                Ok(data) => {
+                    self.buffer.extend(data);
+                    self.position += length;
+                },
+                Err(error) => {
+                    self.finished = true;
+                    return Some(Err(error));
+                },
+            }
+        }
+    }
+}
+
+/// Storage for the self-encryptor of a Reader, which serves chunks prefetched by read-ahead from a cache before falling
+/// back to the chunk storage
+struct ReadAheadStorage {
+    storage: ::std::sync::Arc<::helper::chunk_storage::ChunkStorage>,
+    cache  : ::std::sync::Mutex<ReadAheadCache>,
+}
+
+/// Chunks prefetched by read-ahead. Only the chunks of the window, those following the last chunk read, are held.
+struct ReadAheadCache {
+    window: Vec<Vec<u8>>,
+    chunks: ::std::collections::HashMap<Vec<u8>, Vec<u8>>,
+}
+
+impl ReadAheadStorage {
+    /// Sets the chunks to be prefetched, dropping the cached chunks which are no longer part of them.
+    /// Returns the names of the chunks which were not part of the previous window and so have to be fetched
+    fn set_window(&self, window: Vec<Vec<u8>>) -> Vec<Vec<u8>> {
+        let mut cache = eval_result!(self.cache.lock());
+        let requested = window.iter().filter(|name| !cache.window.contains(name)).cloned().collect();
+        let dropped_chunks: Vec<Vec<u8>> = cache.chunks.keys().filter(|name| !window.contains(name)).cloned().collect();
+        for name in dropped_chunks {
+            let _ = cache.chunks.remove(&name);
+        }
+        cache.window = window;
+        requested
+    }
+
+    /// Removes the chunk from the window, so that it is fetched again if a later window holds it
+    fn forget(&self, name: &[u8]) {
+        eval_result!(self.cache.lock()).window.retain(|window_name| &window_name[..] != name);
+    }
+
+    /// Fetches the chunk from the network into the cache, unless it has left the window since it was requested. A chunk
+    /// which can not be fetched is not cached, so that a read of it fetches it again and reports the failure then.
+    fn prefetch(&self, name: Vec<u8>) {
+        if !eval_result!(self.cache.lock()).window.contains(&name) {
+            return;
+        }
+        let content = self.storage.get(name.clone());
+        if content.is_empty() {
+            return;
+        }
+        let mut cache = eval_result!(self.cache.lock());
+        if cache.window.contains(&name) {
+            let _ = cache.chunks.insert(name, content);
+        }
+    }
+
+    /// Returns the number of chunks held in the cache
+    #[cfg(test)]
+    fn cached_chunks(&self) -> usize {
+        eval_result!(self.cache.lock()).chunks.len()
+    }
+}
+
+impl ::self_encryption::Storage for ReadAheadStorage {
+    fn get(&self, name: Vec<u8>) -> Vec<u8> {
+        let cached_content = {
+            let mut cache = eval_result!(self.cache.lock());
+            // A chunk which has been read is not kept around, nor prefetched again by a late request
+            cache.window.retain(|window_name| *window_name != name);
+            cache.chunks.remove(&name)
+        };
+        match cached_content {
+            Some(content) => content,
+            None => self.storage.get(name),
+        }
+    }
+
+    fn put(&self, name: Vec<u8>, data: Vec<u8>) {
+        self.storage.put(name, data)
+    }
+}
+
+/// Returns the (offset, size, name) of each chunk in the order the chunks appear in the file
+fn chunk_ranges(chunks: &[::self_encryption::datamap::ChunkDetails]) -> Vec<(u64, u64, Vec<u8>)> {
+    let mut sorted_chunks: Vec<&::self_encryption::datamap::ChunkDetails> = chunks.iter().collect();
+    sorted_chunks.sort_by(|lhs, rhs| lhs.chunk_num.cmp(&rhs.chunk_num));
+    let mut offset = 0;
+    sorted_chunks.into_iter().map(|chunk| {
+        let range = (offset, chunk.source_size, chunk.hash.clone());
+        offset += chunk.source_size;
+        range
+    }).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn get_client() -> ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>> {
+        let test_client = eval_result!(::safe_core::utility::test_utils::get_client());
+        ::std::sync::Arc::new(::std::sync::Mutex::new(test_client))
+    }
+
+    #[test]
+    fn read_holes_as_zeros() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        let offset = 1024 * 1024;
+        let mut writer = eval_result!(file_helper.create("sparse.txt".to_string(), Vec::new(), directory));
+        writer.write(&[1u8; 10], offset);
+        let (directory, _) = eval_result!(writer.close());
+
+        let file = eval_option!(directory.find_file(&"sparse.txt".to_string()), "File not found");
+        let mut reader = Reader::new(client.clone(), file);
+        assert_eq!(reader.size(), offset + 10);
+        assert_eq!(eval_result!(reader.read(0, offset)), vec![0u8; offset as usize]);
+        assert_eq!(eval_result!(reader.read(offset, 10)), vec![1u8; 10]);
+
+        // Trailing hole beyond the stored content
+        let mut file = file.clone();
+        file.get_mut_metadata().set_size(offset + 20);
+        let mut reader = Reader::new(client.clone(), &file);
+        assert_eq!(reader.size(), offset + 20);
+        let mut expected = vec![1u8; 10];
+        expected.extend(vec![0u8; 10]);
+        assert_eq!(eval_result!(reader.read(offset, 20)), expected);
+        assert!(reader.read(offset, 21).is_err());
+    }
+
+    #[test]
+    fn read_to_string() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        let mut writer = eval_result!(file_helper.create("text.txt".to_string(), Vec::new(), directory));
+        writer.write("Hello World!".as_bytes(), 0);
+        let (directory, _) = eval_result!(writer.close());
+        let mut writer = eval_result!(file_helper.create("binary.dat".to_string(), Vec::new(), directory));
+        writer.write(&[0xffu8, 0xfe, 0xfd], 0);
+        let (directory, _) = eval_result!(writer.close());
+
+        let file = eval_option!(directory.find_file(&"text.txt".to_string()), "File not found");
+        let mut reader = Reader::new(client.clone(), file);
+        assert_eq!(eval_result!(reader.read_to_string(None)), "Hello World!".to_string());
+        assert_eq!(eval_result!(reader.read_to_string(Some(12))), "Hello World!".to_string());
+        assert!(reader.read_to_string(Some(11)).is_err());
+
+        let file = eval_option!(directory.find_file(&"binary.dat".to_string()), "File not found");
+        let mut reader = Reader::new(client.clone(), file);
+        match reader.read_to_string(None) {
+            Err(::errors::NfsError::InvalidUtf8) => (),
+            _ => panic!("Expected InvalidUtf8"),
+        }
+    }
+
+    #[test]
+    fn read_chunk() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        let data: Vec<u8> = (0..3 * 1024 * 1024).map(|index| (index % 251) as u8).collect();
+        let mut writer = eval_result!(file_helper.create("chunked.dat".to_string(), Vec::new(), directory));
+        writer.write(&data[..], 0);
+        let (directory, _) = eval_result!(writer.close());
+
+        let file = eval_option!(directory.find_file(&"chunked.dat".to_string()), "File not found");
+        let mut reader = Reader::new(client.clone(), file);
+        let mut chunk_index = 0;
+        let mut content = Vec::new();
+        while let Ok(chunk) = reader.read_chunk(chunk_index) {
+            content.extend(chunk);
+            chunk_index += 1;
+        }
+        assert!(chunk_index > 1);
+        assert_eq!(content, data);
+    }
+
+    #[test]
+    fn read_ahead() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        let data: Vec<u8> = (0..5 * 1024 * 1024).map(|index: u64| (index % 251) as u8).collect();
+        let mut writer = eval_result!(file_helper.create("large.txt".to_string(), Vec::new(), directory));
+        writer.write(&data, 0);
+        let (directory, _) = eval_result!(writer.close());
+        let file = eval_option!(directory.find_file(&"large.txt".to_string()), "File not found");
+
+        let block_size = 256 * 1024;
+        let read_ahead = 2;
+        let mut reader = Reader::new(client.clone(), file).with_read_ahead(read_ahead);
+        let mut content = Vec::with_capacity(data.len());
+        let mut position = 0;
+        while position < reader.size() {
+            let length = ::std::cmp::min(block_size, reader.size() - position);
+            content.extend(eval_result!(reader.read(position, length)));
+            position += length;
+            assert!(reader.storage.cached_chunks() <= read_ahead);
+        }
+        assert!(content == data);
+        // Every prefetched chunk has been read or dropped once the end of the file is reached
+        assert_eq!(reader.storage.cached_chunks(), 0);
+
+        // Chunks prefetched ahead of a read are dropped once a read elsewhere in the file moves away from them
+        let mut reader = Reader::new(client.clone(), file).with_read_ahead(read_ahead);
+        let _ = eval_result!(reader.read(0, block_size));
+        let size = reader.size();
+        let _ = eval_result!(reader.read(size - block_size, block_size));
+        assert_eq!(reader.storage.cached_chunks(), 0);
+    }
+
+    #[test]
+    fn lines() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        // A line longer than the buffer, so that it spans several reads
+        let long_line: String = ::std::iter::repeat('x').take(super::LINE_BUFFER_SIZE as usize + 10).collect();
+        let mut data = Vec::new();
+        data.extend(b"first\r\n".iter().cloned());
+        data.extend(long_line.as_bytes().iter().cloned());
+        data.extend(b"\n\xff\xfe\n\nlast".iter().cloned());
+        let mut writer = eval_result!(file_helper.create("log.txt".to_string(), Vec::new(), directory));
+        writer.write(&data, 0);
+        let (directory, _) = eval_result!(writer.close());
+
+        let file = eval_option!(directory.find_file(&"log.txt".to_string()), "File not found");
+        let mut reader = Reader::new(client.clone(), file);
+        let mut lines = reader.lines();
+        assert_eq!(eval_option!(lines.next(), "Line not found").ok(), Some("first".to_string()));
+        assert_eq!(eval_option!(lines.next(), "Line not found").ok(), Some(long_line));
+        match lines.next() {
+            Some(Err(::errors::NfsError::InvalidUtf8)) => (),
+            _ => panic!("Expected InvalidUtf8"),
+        }
+        assert_eq!(eval_option!(lines.next(), "Line not found").ok(), Some("".to_string()));
+        assert_eq!(eval_option!(lines.next(), "Line not found").ok(), Some("last".to_string()));
+        assert!(lines.next().is_none());
+    }
+
+    #[test]
+    fn read_ranges() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        let data: Vec<u8> = (0..5 * 1024 * 1024).map(|index: u64| (index % 251) as u8).collect();
+        let mut writer = eval_result!(file_helper.create("records.dat".to_string(), Vec::new(), directory));
+        writer.write(&data, 0);
+        let (directory, _) = eval_result!(writer.close());
+
+        let file = eval_option!(directory.find_file(&"records.dat".to_string()), "File not found");
+        let mut reader = Reader::new(client.clone(), file);
+        let ranges = vec![10..20, 1024 * 1024 - 5..1024 * 1024 + 5, 15..1000, 3 * 1024 * 1024..3 * 1024 * 1024, 5 * 1024 * 1024 - 1..5 * 1024 * 1024];
+        let ranges_data = eval_result!(reader.read_ranges(&ranges));
+        assert_eq!(ranges_data.len(), ranges.len());
+        for (range, range_data) in ranges.iter().zip(ranges_data.iter()) {
+            assert_eq!(*range_data, data[range.start as usize..range.end as usize].to_vec());
+        }
+
+        match reader.read_ranges(&[0..10, 5 * 1024 * 1024..5 * 1024 * 1024 + 1]) {
+            Err(::errors::NfsError::InvalidRangeSpecified) => (),
+            _ => panic!("Expected InvalidRangeSpecified"),
+        }
+    }
+
+    #[test]
+    fn resume_from_position() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        let content = (0..5000).map(|index| (index % 251) as u8).collect::<Vec<u8>>();
+        let mut writer = eval_result!(file_helper.create("file.dat".to_string(), Vec::new(), directory));
+        writer.write(&content, 0);
+        let (directory, _) = eval_result!(writer.close());
+        let file = eval_option!(directory.find_file(&"file.dat".to_string()), "File not found");
+
+        let mut reader = Reader::new(client.clone(), file);
+        assert_eq!(eval_result!(reader.read_next(1000)), &content[..1000]);
+        assert_eq!(reader.position(), 1000);
+        let _ = eval_result!(reader.read(2000, 10));
+        assert_eq!(reader.position(), 2010);
+
+        // Resume an interrupted download from the saved position
+        let mut reader = eval_result!(Reader::new(client.clone(), file).starting_at(1000));
+        assert_eq!(reader.position(), 1000);
+        assert_eq!(eval_result!(reader.read_next(3000)), &content[1000..4000]);
+        assert_eq!(eval_result!(reader.read_next(3000)), &content[4000..]);
+        assert_eq!(reader.position(), 5000);
+        assert!(eval_result!(reader.read_next(3000)).is_empty());
+
+        eval_result!(reader.seek_to(10));
+        assert_eq!(eval_result!(reader.read_next(10)), &content[10..20]);
+        match reader.seek_to(5001) {
+            Err(::errors::NfsError::InvalidRangeSpecified) => (),
+            _ => panic!("Expected InvalidRangeSpecified"),
+        }
+        assert_eq!(reader.position(), 20);
+        assert!(Reader::new(client.clone(), file).starting_at(5001).is_err());
+    }
+
+    #[test]
+    fn read_from_several_threads() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        let content = (0..4 * 1024 * 1024).map(|index| (index % 251) as u8).collect::<Vec<u8>>();
+        let mut writer = eval_result!(file_helper.create("large.dat".to_string(), Vec::new(), directory));
+        writer.write(&content, 0);
+        let (directory, _) = eval_result!(writer.close());
+        let file = eval_option!(directory.find_file(&"large.dat".to_string()), "File not found").clone();
+
+        let reader = ::std::sync::Arc::new(file_helper.read_shared(file));
+        let part_length = reader.size() / 4;
+        let reads: Vec<::std::thread::JoinHandle<Result<Vec<u8>, ::errors::NfsError>>> = (0..4).map(|part| {
+            let reader = reader.clone();
+            ::std::thread::spawn(move || reader.read(part * part_length, part_length))
+        }).collect();
+        let mut read_content = Vec::with_capacity(content.len());
+        for read in reads {
+            match read.join() {
+                Ok(data) => read_content.extend(eval_result!(data)),
+                Err(_) => panic!("Read thread panicked"),
+            }
+        }
+        assert_eq!(read_content, content);
+    }
+
+    /// ChunkStorage counting the chunks fetched from the storage it wraps
+    struct CountingChunkStorage {
+        storage: ::helper::chunk_storage::MemoryChunkStorage,
+        gets   : ::std::sync::atomic::AtomicUsize,
+    }
+
+    impl ::helper::chunk_storage::ChunkStorage for CountingChunkStorage {
+        fn get(&self, name: Vec<u8>) -> Vec<u8> {
+            let _ = self.gets.fetch_add(1, ::std::sync::atomic::Ordering::SeqCst);
+            self.storage.get(name)
+        }
+
+        fn put(&self, name: Vec<u8>, data: Vec<u8>) {
+            self.storage.put(name, data)
+        }
+
+        fn delete(&self, name: Vec<u8>) {
+            self.storage.delete(name)
+        }
+    }
+
+    #[test]
+    fn shared_reader_caches_chunks() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let storage = ::std::sync::Arc::new(CountingChunkStorage {
+            storage: ::helper::chunk_storage::MemoryChunkStorage::new(),
+            gets   : ::std::sync::atomic::AtomicUsize::new(0),
+        });
+        let file_helper = ::helper::file_helper::FileHelper::with_storage(client.clone(), storage.clone());
+        let content = (0..4 * 1024 * 1024).map(|index| (index % 251) as u8).collect::<Vec<u8>>();
+        let mut writer = eval_result!(file_helper.create("large.dat".to_string(), Vec::new(), directory));
+        writer.write(&content, 0);
+        let (directory, _) = eval_result!(writer.close());
+        let file = eval_option!(directory.find_file(&"large.dat".to_string()), "File not found").clone();
+
+        let reader = file_helper.read_shared(file);
+        let size = reader.size();
+        let gets_before_reads = storage.gets.load(::std::sync::atomic::Ordering::SeqCst);
+        assert_eq!(eval_result!(reader.read(0, size)), content);
+        let gets_after_first_read = storage.gets.load(::std::sync::atomic::Ordering::SeqCst);
+        assert!(gets_after_first_read > gets_before_reads);
+
+        // The chunks are served from the cache shared by the reads
+        assert_eq!(eval_result!(reader.read(0, size / 2)), content[..(size / 2) as usize].to_vec());
+        assert_eq!(eval_result!(reader.read(size / 2, size - size / 2)), content[(size / 2) as usize..].to_vec());
+        assert_eq!(storage.gets.load(::std::sync::atomic::Ordering::SeqCst), gets_after_first_read);
+    }
+
+    #[test]
+    fn read_clamped() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let file_helper = ::helper::file_helper::FileHelper::new(client.clone());
+        let content = (0..100).collect::<Vec<u8>>();
+        let mut writer = eval_result!(file_helper.create("file.dat".to_string(), Vec::new(), directory));
+        writer.write(&content, 0);
+        let (directory, _) = eval_result!(writer.close());
+        let file = eval_option!(directory.find_file(&"file.dat".to_string()), "File not found");
+
+        let mut reader = Reader::new(client.clone(), file);
+        assert_eq!(eval_result!(reader.read_clamped(10, 20)), &content[10..30]);
+        assert_eq!(eval_result!(reader.read_clamped(90, 20)), &content[90..]);
+        assert!(eval_result!(reader.read_clamped(100, 20)).is_empty());
+        assert!(eval_result!(reader.read_clamped(150, 20)).is_empty());
+        match reader.read(90, 20) {
+            Err(::errors::NfsError::InvalidRangeSpecified) => (),
+            _ => panic!("Expected InvalidRangeSpecified"),
         }
     }
 }
@@ -0,0 +1,124 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+/// Action needed to bring an entry of a SAFE directory in line with the local filesystem
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SyncAction {
+    /// The local directory does not exist in the SAFE directory and has to be created
+    CreateDirectory,
+    /// The local file does not exist in the SAFE directory and has to be uploaded
+    Upload,
+    /// The local file differs from the file in the SAFE directory and its content has to be replaced
+    Update,
+    /// The file no longer exists locally and has to be deleted from the SAFE directory
+    DeleteFile,
+    /// The directory no longer exists locally and has to be deleted from the SAFE directory
+    DeleteDirectory,
+}
+
+/// Entry of a SyncPlan
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SyncEntry {
+    /// Path of the entry relative to the root of the sync, with the components separated by `/`
+    pub path: String,
+    /// Action to be performed for the entry
+    pub action: SyncAction,
+}
+
+/// Plan of the actions needed to make a SAFE directory tree mirror a local directory tree.
+/// The entries are ordered so that they can be applied one after the other, with a directory always being created
+/// before the entries nested under it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SyncPlan {
+    entries: Vec<SyncEntry>,
+}
+
+impl SyncPlan {
+    /// Create an empty SyncPlan
+    pub fn new() -> SyncPlan {
+        SyncPlan {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Get the entries of the plan
+    pub fn get_entries(&self) -> &Vec<SyncEntry> {
+        &self.entries
+    }
+
+    /// Returns true if there is nothing to sync
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds an entry at the end of the plan
+    pub fn push(&mut self, path: String, action: SyncAction) {
+        self.entries.push(SyncEntry {
+            path: path,
+            action: action,
+        });
+    }
+}
+
+impl IntoIterator for SyncPlan {
+    type Item = SyncEntry;
+    type IntoIter = ::std::vec::IntoIter<SyncEntry>;
+
+    fn into_iter(self) -> ::std::vec::IntoIter<SyncEntry> {
+        self.entries.into_iter()
+    }
+}
+
+/// Outcome of applying a SyncPlan
+#[derive(Debug)]
+pub struct SyncReport {
+    succeeded: Vec<SyncEntry>,
+    failed   : Vec<(SyncEntry, ::errors::NfsError)>,
+}
+
+impl SyncReport {
+    /// Create an empty SyncReport
+    pub fn new() -> SyncReport {
+        SyncReport {
+            succeeded: Vec::new(),
+            failed   : Vec::new(),
+        }
+    }
+
+    /// Get the entries which were applied successfully
+    pub fn get_succeeded(&self) -> &Vec<SyncEntry> {
+        &self.succeeded
+    }
+
+    /// Get the entries which could not be applied, along with the error for each of them
+    pub fn get_failed(&self) -> &Vec<(SyncEntry, ::errors::NfsError)> {
+        &self.failed
+    }
+
+    /// Returns true if every entry was applied successfully
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    /// Records the outcome of applying an entry
+    pub fn record(&mut self, entry: SyncEntry, result: Result<(), ::errors::NfsError>) {
+        match result {
+            Ok(()) => self.succeeded.push(entry),
+            Err(error) => self.failed.push((entry, error)),
+        }
+    }
+}
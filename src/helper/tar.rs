@@ -0,0 +1,240 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+const BLOCK_SIZE: usize = 512;
+const NAME_LENGTH: usize = 100;
+const PREFIX_LENGTH: usize = 155;
+/// Largest value which fits the 11 octal digits of the size and mtime fields of a header, i.e. 8 GiB - 1 for the size
+const MAX_OCTAL_VALUE: u64 = 0o77777777777;
+
+/// Kind of an entry read from an archive
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum EntryKind {
+    /// Directory entry
+    Directory,
+    /// Regular file entry
+    File,
+    /// Any other kind of entry, like links or devices, which is skipped when importing
+    Unsupported,
+}
+
+/// Header of an entry read from an archive
+#[derive(Debug)]
+pub struct Header {
+    /// Path of the entry, with the components separated by `/`
+    pub path: String,
+    /// Size of the content of the entry
+    pub size: u64,
+    /// Kind of the entry
+    pub kind: EntryKind,
+}
+
+/// Writes the ustar header for an entry of the archive.
+/// Returns an error of kind InvalidInput if the size does not fit the header, i.e. for content of 8 GiB or more. An
+/// mtime beyond the range of the header is clamped to it.
+pub fn write_header<W: ::std::io::Write>(out         : &mut W,
+                                         path        : &str,
+                                         size        : u64,
+                                         mtime       : i64,
+                                         is_directory: bool) -> ::std::io::Result<()> {
+    let mut header = [0u8; BLOCK_SIZE];
+    let (prefix, name) = try!(split_path(path));
+    copy_into(&mut header[0..NAME_LENGTH], name.as_bytes());
+    copy_into(&mut header[100..108], if is_directory { b"0000755\0" } else { b"0000644\0" });
+    copy_into(&mut header[108..116], b"0000000\0");
+    copy_into(&mut header[116..124], b"0000000\0");
+    if size > MAX_OCTAL_VALUE {
+        return Err(::std::io::Error::new(::std::io::ErrorKind::InvalidInput, format!("Entry too large for a tar archive: {}", path)));
+    }
+    let mtime = ::std::cmp::min(::std::cmp::max(mtime, 0) as u64, MAX_OCTAL_VALUE);
+    copy_into(&mut header[124..136], format!("{:011o}\0", size).as_bytes());
+    copy_into(&mut header[136..148], format!("{:011o}\0", mtime).as_bytes());
+    header[156] = if is_directory { b'5' } else { b'0' };
+    copy_into(&mut header[257..263], b"ustar\0");
+    copy_into(&mut header[263..265], b"00");
+    copy_into(&mut header[345..345 + PREFIX_LENGTH], prefix.as_bytes());
+
+    // The checksum is computed with the checksum field itself filled with spaces
+    copy_into(&mut header[148..156], b"        ");
+    let checksum = header.iter().fold(0u32, |sum, byte| sum + *byte as u32);
+    copy_into(&mut header[148..156], format!("{:06o}\0 ", checksum).as_bytes());
+
+    out.write_all(&header)
+}
+
+/// Pads the content of an entry of the given size up to the block boundary
+pub fn write_padding<W: ::std::io::Write>(out: &mut W, size: u64) -> ::std::io::Result<()> {
+    let remainder = (size % BLOCK_SIZE as u64) as usize;
+    if remainder == 0 {
+        Ok(())
+    } else {
+        out.write_all(&[0u8; BLOCK_SIZE][remainder..])
+    }
+}
+
+/// Writes the two empty blocks marking the end of the archive
+pub fn write_end<W: ::std::io::Write>(out: &mut W) -> ::std::io::Result<()> {
+    out.write_all(&[0u8; 2 * BLOCK_SIZE])
+}
+
+/// Reads the header of the next entry of the archive
+/// Returns None once the end of the archive is reached
+pub fn read_header<R: ::std::io::Read>(input: &mut R) -> ::std::io::Result<Option<Header>> {
+    let mut header = [0u8; BLOCK_SIZE];
+    if !try!(read_block(input, &mut header)) || header.iter().all(|byte| *byte == 0) {
+        return Ok(None);
+    }
+
+    let stored_checksum = try!(parse_octal(&header[148..156]));
+    let checksum = header[..148].iter().chain(header[156..].iter()).fold(8 * b' ' as u64, |sum, byte| sum + *byte as u64);
+    if stored_checksum != checksum {
+        return Err(invalid_data("Checksum mismatch in tar header".to_string()));
+    }
+
+    let name = try!(parse_string(&header[0..NAME_LENGTH]));
+    let path = if &header[257..262] == b"ustar" && header[345] != 0 {
+        format!("{}/{}", try!(parse_string(&header[345..345 + PREFIX_LENGTH])), name)
+    } else {
+        name
+    };
+    let kind = match header[156] {
+        b'5' => EntryKind::Directory,
+        b'0' | 0 if path.ends_with('/') => EntryKind::Directory,
+        b'0' | 0 => EntryKind::File,
+        _ => EntryKind::Unsupported,
+    };
+
+    Ok(Some(Header {
+        path: path,
+        size: try!(parse_octal(&header[124..136])),
+        kind: kind,
+    }))
+}
+
+/// Reads and discards the content of an entry of the given size along with its padding
+pub fn skip_content<R: ::std::io::Read>(input: &mut R, size: u64) -> ::std::io::Result<()> {
+    let mut block = [0u8; BLOCK_SIZE];
+    for _ in 0..(size + BLOCK_SIZE as u64 - 1) / BLOCK_SIZE as u64 {
+        if !try!(read_block(input, &mut block)) {
+            return Err(invalid_data("Unexpected end of tar archive".to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// Reads and discards the padding following the content of an entry of the given size
+pub fn skip_padding<R: ::std::io::Read>(input: &mut R, size: u64) -> ::std::io::Result<()> {
+    let remainder = (size % BLOCK_SIZE as u64) as usize;
+    if remainder == 0 {
+        Ok(())
+    } else {
+        let mut padding = [0u8; BLOCK_SIZE];
+        read_exact(input, &mut padding[remainder..])
+    }
+}
+
+/// Fills the buffer from the input, failing if the input ends before the buffer is full
+pub fn read_exact<R: ::std::io::Read>(input: &mut R, buffer: &mut [u8]) -> ::std::io::Result<()> {
+    if try!(read_block(input, buffer)) {
+        Ok(())
+    } else {
+        Err(invalid_data("Unexpected end of tar archive".to_string()))
+    }
+}
+
+/// Fills the buffer from the input
+/// Returns false if the input ended before anything was read
+fn read_block<R: ::std::io::Read>(input: &mut R, buffer: &mut [u8]) -> ::std::io::Result<bool> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match input.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(ref error) if error.kind() == ::std::io::ErrorKind::Interrupted => (),
+            Err(error) => return Err(error),
+        }
+    }
+    if filled == 0 {
+        Ok(false)
+    } else if filled == buffer.len() {
+        Ok(true)
+    } else {
+        Err(invalid_data("Unexpected end of tar archive".to_string()))
+    }
+}
+
+fn parse_string(field: &[u8]) -> ::std::io::Result<String> {
+    let end = field.iter().position(|byte| *byte == 0).unwrap_or(field.len());
+    ::std::str::from_utf8(&field[..end]).map(|value| value.to_string())
+                                        .map_err(|_| invalid_data("Invalid UTF-8 in tar header".to_string()))
+}
+
+fn parse_octal(field: &[u8]) -> ::std::io::Result<u64> {
+    let value = try!(parse_string(field));
+    let value = value.trim_matches(|character| character == ' ' || character == '\0');
+    if value.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(value, 8).map_err(|_| invalid_data(format!("Invalid number in tar header: {:?}", value)))
+}
+
+fn invalid_data(message: String) -> ::std::io::Error {
+    ::std::io::Error::new(::std::io::ErrorKind::InvalidData, message)
+}
+
+/// Splits a path into the prefix and name fields of a ustar header
+fn split_path(path: &str) -> ::std::io::Result<(&str, &str)> {
+    if path.len() <= NAME_LENGTH {
+        return Ok(("", path));
+    }
+    // The prefix must end at a separator and the remaining name must fit in the name field
+    let trimmed_path = path.trim_right_matches('/');
+    for (index, _) in trimmed_path.char_indices().filter(|&(_, character)| character == '/') {
+        if index <= PREFIX_LENGTH && path.len() - index - 1 <= NAME_LENGTH {
+            return Ok((&path[..index], &path[index + 1..]));
+        }
+    }
+    Err(::std::io::Error::new(::std::io::ErrorKind::InvalidInput, format!("Path too long for a tar archive: {}", path)))
+}
+
+fn copy_into(destination: &mut [u8], source: &[u8]) {
+    for (dst, src) in destination.iter_mut().zip(source) {
+        *dst = *src;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn header_limits() {
+        let mut archive = Vec::new();
+        eval_result!(write_header(&mut archive, "large.dat", 8 * 1024 * 1024 * 1024 - 1, ::std::i64::MAX, false));
+        assert_eq!(archive.len(), 512);
+        let header = eval_option!(eval_result!(read_header(&mut &archive[..])), "Header not read");
+        assert_eq!(header.path, "large.dat".to_string());
+        assert_eq!(header.size, 8 * 1024 * 1024 * 1024 - 1);
+
+        let mut archive = Vec::new();
+        match write_header(&mut archive, "too_large.dat", 8 * 1024 * 1024 * 1024, 0, false) {
+            Err(ref error) if error.kind() == ::std::io::ErrorKind::InvalidInput => (),
+            _ => panic!("Expected InvalidInput"),
+        }
+        assert!(archive.is_empty());
+    }
+}
@@ -0,0 +1,66 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+/// Waits for the response to a request made to the network, for up to the timeout if one is passed. The client is not
+/// locked while waiting. safe_core offers no wait bounded by a timeout of its own, so the wait runs on a thread holding
+/// the response getter alone, through `run_with_timeout`; after a timeout that thread ends as soon as the network
+/// responds to the request, without making any further request.
+/// Returns NfsError::Timeout if the network has not responded within the timeout
+pub fn get_response(response_getter: ::safe_core::client::response_getter::ResponseGetter,
+                    timeout        : Option<::std::time::Duration>) -> Result<::routing::data::Data, ::errors::NfsError> {
+    match timeout {
+        Some(timeout) => run_with_timeout(move || Ok(try!(response_getter.get())), timeout),
+        None => Ok(try!(response_getter.get())),
+    }
+}
+
+/// Runs the operation on a thread of its own and waits for it for up to the timeout.
+/// Returns NfsError::Timeout if the operation has not completed by then. The operation is not cancelled, it runs to
+/// completion in the background and its result is then discarded, so it should be a single wait rather than a series
+/// of requests, see `get_response`.
+pub fn run_with_timeout<T, F>(operation: F, timeout: ::std::time::Duration) -> Result<T, ::errors::NfsError>
+        where T: Send + 'static, F: FnOnce() -> Result<T, ::errors::NfsError> + Send + 'static {
+    let (sender, receiver) = ::std::sync::mpsc::channel();
+    let _ = ::std::thread::spawn(move || {
+        let _ = sender.send(operation());
+    });
+    match receiver.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(::std::sync::mpsc::RecvTimeoutError::Timeout) => Err(::errors::NfsError::Timeout),
+        Err(::std::sync::mpsc::RecvTimeoutError::Disconnected) => Err(::errors::NfsError::from("Operation ended without a result")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn timeout() {
+        let result = run_with_timeout(|| Ok(7), ::std::time::Duration::from_millis(1000));
+        assert_eq!(eval_result!(result), 7);
+
+        let result = run_with_timeout(|| {
+            ::std::thread::sleep(::std::time::Duration::from_millis(1000));
+            Ok(7)
+        }, ::std::time::Duration::from_millis(10));
+        match result {
+            Err(::errors::NfsError::Timeout) => (),
+            _ => panic!("Expected Timeout"),
+        }
+    }
+}
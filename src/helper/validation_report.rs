@@ -0,0 +1,60 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+/// Problems found while validating a directory tree.
+/// Each problem is recorded with the path of the entry, starting with the name of the validated root directory and with
+/// the components separated by `/`, along with the error met while checking the entry.
+#[derive(Debug)]
+pub struct ValidationReport {
+    unreachable_directories: Vec<(String, ::errors::NfsError)>,
+    broken_files           : Vec<(String, ::errors::NfsError)>,
+}
+
+impl ValidationReport {
+    /// Create an empty ValidationReport
+    pub fn new() -> ValidationReport {
+        ValidationReport {
+            unreachable_directories: Vec::new(),
+            broken_files           : Vec::new(),
+        }
+    }
+
+    /// Get the sub directories which could not be fetched from the network
+    pub fn get_unreachable_directories(&self) -> &Vec<(String, ::errors::NfsError)> {
+        &self.unreachable_directories
+    }
+
+    /// Get the files whose content could not be fetched from the network
+    pub fn get_broken_files(&self) -> &Vec<(String, ::errors::NfsError)> {
+        &self.broken_files
+    }
+
+    /// Returns true if no problem was found
+    pub fn is_healthy(&self) -> bool {
+        self.unreachable_directories.is_empty() && self.broken_files.is_empty()
+    }
+
+    /// Records a sub directory which could not be fetched
+    pub fn add_unreachable_directory(&mut self, path: String, error: ::errors::NfsError) {
+        self.unreachable_directories.push((path, error));
+    }
+
+    /// Records a file whose content could not be fetched
+    pub fn add_broken_file(&mut self, path: String, error: ::errors::NfsError) {
+        self.broken_files.push((path, error));
+    }
+}
@@ -0,0 +1,63 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+/// Iterates over the versions of a versioned directory, from the latest to the oldest.
+/// The DirectoryListing of a version is only fetched from the network when it is resolved, so the history of a
+/// directory can be searched without holding every version of it in memory.
+pub struct VersionIterator {
+    client       : ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
+    directory_key: ::metadata::directory_key::DirectoryKey,
+    versions     : ::std::iter::Rev<::std::vec::IntoIter<::routing::NameType>>,
+    shared_key   : Option<::sodiumoxide::crypto::secretbox::Key>,
+}
+
+impl VersionIterator {
+    /// Create a new VersionIterator over the versions of the directory, which are expected in the order they were stored
+    pub fn new(client       : ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
+               directory_key: ::metadata::directory_key::DirectoryKey,
+               versions     : Vec<::routing::NameType>) -> VersionIterator {
+        VersionIterator {
+            client       : client,
+            directory_key: directory_key,
+            versions     : versions.into_iter().rev(),
+            shared_key   : None,
+        }
+    }
+
+    /// Sets the shared key used to resolve the versions of a Shared directory
+    pub fn set_shared_key(&mut self, shared_key: Option<::sodiumoxide::crypto::secretbox::Key>) {
+        self.shared_key = shared_key;
+    }
+
+    /// Returns the DirectoryListing of the directory as it was at the given version
+    pub fn resolve(&self, version: ::routing::NameType) -> Result<::directory_listing::DirectoryListing, ::errors::NfsError> {
+        let directory_helper = ::helper::directory_helper::DirectoryHelper::from_parts(self.client.clone(), self.shared_key.clone(), true);
+        directory_helper.get_by_version(&self.directory_key, version)
+    }
+}
+
+impl Iterator for VersionIterator {
+    type Item = ::routing::NameType;
+
+    fn next(&mut self) -> Option<::routing::NameType> {
+        self.versions.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.versions.size_hint()
+    }
+}
@@ -25,11 +25,17 @@ pub enum Mode {
 
 /// Writer is used to write contents to a File and especially in chunks if the file happens to be
 /// too large
+/// Chunks which the network already holds for the file are not uploaded again, so that an edit of a part of an existing
+/// file, in either mode, only uploads the chunks whose content changed.
 pub struct Writer {
-    client          : ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
-    file            : ::file::File,
-    parent_directory: ::directory_listing::DirectoryListing,
-    self_encryptor  : ::self_encryption::SelfEncryptor<::safe_core::SelfEncryptionStorage>,
+    client            : ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
+    file              : ::file::File,
+    parent_directory  : ::directory_listing::DirectoryListing,
+    storage           : ::std::sync::Arc<ExistingChunkStorage>,
+    self_encryptor    : ::self_encryption::SelfEncryptor<ExistingChunkStorage>,
+    finalised         : bool,
+    shared_key        : Option<::sodiumoxide::crypto::secretbox::Key>,
+    keep_modified_time: bool,
 }
 
 impl Writer {
@@ -38,46 +44,265 @@ impl Writer {
                mode            : Mode,
                parent_directory: ::directory_listing::DirectoryListing,
                file            : ::file::File) -> Writer {
+        let storage = ::std::sync::Arc::new(::helper::chunk_storage::NetworkChunkStorage::new(client.clone()));
+        Writer::with_storage(client, storage, mode, parent_directory, file)
+    }
+
+    /// Create new instance of Writer storing the chunks of the file in the storage passed
+    pub fn with_storage(client          : ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>>,
+                        chunk_storage   : ::std::sync::Arc<::helper::chunk_storage::ChunkStorage>,
+                        mode            : Mode,
+                        parent_directory: ::directory_listing::DirectoryListing,
+                        file            : ::file::File) -> Writer {
         let datamap = match mode {
                 Mode::Modify    => file.get_datamap().clone(),
                 Mode::Overwrite => ::self_encryption::datamap::DataMap::None,
         };
+        let existing_chunks = match *file.get_datamap() {
+            ::self_encryption::datamap::DataMap::Chunks(ref chunks) => chunks.iter().map(|chunk| chunk.hash.clone()).collect(),
+            _ => ::std::collections::HashSet::new(),
+        };
+        let storage = ::std::sync::Arc::new(ExistingChunkStorage {
+            storage        : chunk_storage,
+            existing_chunks: ::std::sync::Mutex::new(existing_chunks),
+            uploaded_chunks: ::std::sync::atomic::AtomicUsize::new(0),
+            reused_chunks  : ::std::sync::atomic::AtomicUsize::new(0),
+        });
 
         Writer {
-            client          : client.clone(),
-            file            : file,
-            parent_directory: parent_directory,
-            self_encryptor  : ::self_encryption::SelfEncryptor::new(::safe_core::SelfEncryptionStorage::new(client.clone()), datamap),
+            client            : client.clone(),
+            file              : file,
+            parent_directory  : parent_directory,
+            storage           : storage.clone(),
+            self_encryptor    : ::self_encryption::SelfEncryptor::new(storage, datamap),
+            finalised         : false,
+            shared_key        : None,
+            keep_modified_time: false,
         }
     }
 
+    /// Sets the shared key used to update the parent directory when it is a Shared directory
+    pub fn set_shared_key(&mut self, shared_key: Option<::sodiumoxide::crypto::secretbox::Key>) {
+        self.shared_key = shared_key;
+    }
+
+    /// Sets whether the modified time of the file is kept as it is when the writer is closed, e.g. when the content is
+    /// only rewritten and not changed
+    pub fn set_keep_modified_time(&mut self, keep_modified_time: bool) {
+        self.keep_modified_time = keep_modified_time;
+    }
+
+    /// Returns the name of the file being written
+    pub fn get_file_name(&self) -> &String {
+        self.file.get_name()
+    }
+
     /// Data of a file/blob can be written in smaller chunks
     pub fn write(&mut self, data: &[u8], position: u64) {
         debug!("Writing file data at position {:?} ...", position);
         self.self_encryptor.write(data, position);
     }
 
+    /// Uploads the chunks written so far without ending the write session.
+    /// For long running writes this bounds the memory held by the writer, as the data written up to
+    /// this point is pushed to the network instead of being buffered until `close` is invoked.
+    /// Writing can continue after the flush and `close` then only has to upload the remaining tail.
+    pub fn flush_chunks(&mut self) -> Result<(), ::errors::NfsError> {
+        if self.finalised {
+            return Err(::errors::NfsError::WriterAlreadyClosed);
+        }
+        debug!("Flushing written chunks to the network ...");
+        let self_encryptor = ::std::mem::replace(&mut self.self_encryptor,
+                                                 ::self_encryption::SelfEncryptor::new(self.storage.clone(),
+                                                                                       ::self_encryption::datamap::DataMap::None));
+        let datamap = self_encryptor.close();
+        self.file.set_datamap(datamap.clone());
+        self.self_encryptor = ::self_encryption::SelfEncryptor::new(self.storage.clone(), datamap);
+        Ok(())
+    }
+
     /// close is invoked only after all the data is completely written
-    /// The file/blob is saved only when the close is invoked.
+    /// The file/blob is saved only when the close is invoked. Any data not yet pushed by
+    /// `flush_chunks` is uploaded here before the DataMap is finalised.
+    /// The writer is consumed by close, so a writer can be closed only once.
     /// Returns the update DirectoryListing which owns the file and also the updated DirectoryListing of the file's parent
     /// Returns (files's parent_directory, Option<file's parent_directory's parent>)
     pub fn close(mut self) -> Result<(::directory_listing::DirectoryListing, Option<::directory_listing::DirectoryListing>), ::errors::NfsError> {
-        let mut file = self.file;
-        let mut directory = self.parent_directory;
+        self.finalise()
+    }
+
+    /// Finalises the DataMap of the file and saves the file in the parent directory.
+    /// Returns NfsError::WriterAlreadyClosed if the writer has already been finalised
+    fn finalise(&mut self) -> Result<(::directory_listing::DirectoryListing, Option<::directory_listing::DirectoryListing>), ::errors::NfsError> {
+        if self.finalised {
+            return Err(::errors::NfsError::WriterAlreadyClosed);
+        }
+        self.finalised = true;
         let size = self.self_encryptor.len();
+        let self_encryptor = ::std::mem::replace(&mut self.self_encryptor,
+                                                 ::self_encryption::SelfEncryptor::new(self.storage.clone(),
+                                                                                       ::self_encryption::datamap::DataMap::None));
+
+        self.file.set_datamap(self_encryptor.close());
+        debug!("Uploaded {:?} chunks and reused {:?} existing chunks of file ...",
+               self.storage.uploaded_chunks.load(::std::sync::atomic::Ordering::SeqCst),
+               self.storage.reused_chunks.load(::std::sync::atomic::Ordering::SeqCst));
+
+        if !self.keep_modified_time {
+            self.file.get_mut_metadata().set_modified_time(::time::now_utc());
+        }
+        self.file.get_mut_metadata().set_size(size);
+
+        self.parent_directory.upsert_file(self.file.clone());
+
+        let directory_helper = ::helper::directory_helper::DirectoryHelper::from_parts(self.client.clone(), self.shared_key.clone(), false);
+        let updated_grand_parent = try!(directory_helper.update(&self.parent_directory));
+        Ok((self.parent_directory.clone(), updated_grand_parent))
+    }
+}
 
-        file.set_datamap(self.self_encryptor.close());
+/// Storage for the self-encryptor of a Writer, which skips storing the chunks already held by the network for the file.
+/// Self-encryption is convergent, so a chunk whose content is unchanged by a write is encrypted to the same chunk
+/// again and does not need to be uploaded.
+struct ExistingChunkStorage {
+    storage        : ::std::sync::Arc<::helper::chunk_storage::ChunkStorage>,
+    existing_chunks: ::std::sync::Mutex<::std::collections::HashSet<Vec<u8>>>,
+    uploaded_chunks: ::std::sync::atomic::AtomicUsize,
+    reused_chunks  : ::std::sync::atomic::AtomicUsize,
+}
 
-        file.get_mut_metadata().set_modified_time(::time::now_utc());
-        file.get_mut_metadata().set_size(size);
+impl ::self_encryption::Storage for ExistingChunkStorage {
+    fn get(&self, name: Vec<u8>) -> Vec<u8> {
+        self.storage.get(name)
+    }
 
-        directory.upsert_file(file.clone());
+    fn put(&self, name: Vec<u8>, data: Vec<u8>) {
+        if !eval_result!(self.existing_chunks.lock()).insert(name.clone()) {
+            let _ = self.reused_chunks.fetch_add(1, ::std::sync::atomic::Ordering::SeqCst);
+            return;
+        }
+        let _ = self.uploaded_chunks.fetch_add(1, ::std::sync::atomic::Ordering::SeqCst);
+        self.storage.put(name, data)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn get_client() -> ::std::sync::Arc<::std::sync::Mutex<::safe_core::client::Client>> {
+        let test_client = eval_result!(::safe_core::utility::test_utils::get_client());
+        ::std::sync::Arc::new(::std::sync::Mutex::new(test_client))
+    }
+
+    #[test]
+    fn flush_chunks_during_long_write() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let file = eval_result!(::file::File::new(::metadata::file_metadata::FileMetadata::new("large.txt".to_string(), Vec::new()),
+                                                  ::self_encryption::datamap::DataMap::None));
+        let mut writer = Writer::new(client.clone(), Mode::Overwrite, directory, file);
+        let data = vec![7u8; 1024 * 1024];
+        let data_len = data.len() as u64;
+        for i in 0..5u64 {
+            writer.write(&data[..], i * data_len);
+            eval_result!(writer.flush_chunks());
+            // Data flushed so far must already be retrievable from the network
+            assert!(writer.file.get_datamap().has_chunks());
+            let mut reader = ::helper::reader::Reader::new(client.clone(), &writer.file);
+            assert_eq!(reader.size(), (i + 1) * data_len);
+            assert_eq!(eval_result!(reader.read(i * data_len, data_len)), data);
+        }
+        writer.write(&data[0..10], 5 * data_len);
+        let (directory, _) = eval_result!(writer.close());
+        let file = eval_option!(directory.find_file(&"large.txt".to_string()), "File not found");
+        assert_eq!(file.get_metadata().get_size(), 5 * data_len + 10);
+    }
+
+    #[test]
+    fn finalise_only_once() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let file = eval_result!(::file::File::new(::metadata::file_metadata::FileMetadata::new("hello.txt".to_string(), Vec::new()),
+                                                  ::self_encryption::datamap::DataMap::None));
+        let mut writer = Writer::new(client.clone(), Mode::Overwrite, directory, file);
+        writer.write("Hello World!".as_bytes(), 0);
+        let (directory, _) = eval_result!(writer.finalise());
+        assert_eq!(eval_option!(directory.find_file(&"hello.txt".to_string()), "File not found").get_metadata().get_size(), 12);
+
+        match writer.finalise() {
+            Err(::errors::NfsError::WriterAlreadyClosed) => (),
+            _ => panic!("Expected WriterAlreadyClosed"),
+        }
+        match writer.flush_chunks() {
+            Err(::errors::NfsError::WriterAlreadyClosed) => (),
+            _ => panic!("Expected WriterAlreadyClosed"),
+        }
+        match writer.close() {
+            Err(::errors::NfsError::WriterAlreadyClosed) => (),
+            _ => panic!("Expected WriterAlreadyClosed"),
+        }
+        // The file saved by the first finalise is left untouched
+        let directory = eval_result!(dir_helper.get(directory.get_key()));
+        assert_eq!(eval_option!(directory.find_file(&"hello.txt".to_string()), "File not found").get_metadata().get_size(), 12);
+    }
+
+    #[test]
+    fn upload_only_changed_chunks() {
+        let client = get_client();
+        let dir_helper = ::helper::directory_helper::DirectoryHelper::new(client.clone());
+        let (directory, _) = eval_result!(dir_helper.create("DirName".to_string(),
+                                                            ::VERSIONED_DIRECTORY_LISTING_TAG,
+                                                            Vec::new(),
+                                                            true,
+                                                            ::AccessLevel::Private,
+                                                            None));
+        let file = eval_result!(::file::File::new(::metadata::file_metadata::FileMetadata::new("large.dat".to_string(), Vec::new()),
+                                                  ::self_encryption::datamap::DataMap::None));
+        let mut data: Vec<u8> = (0..10 * 1024 * 1024).map(|index: u64| (index % 251) as u8).collect();
+        let mut writer = Writer::new(client.clone(), Mode::Overwrite, directory, file);
+        writer.write(&data, 0);
+        let (directory, _) = eval_result!(writer.finalise());
+        let uploaded_chunks = writer.storage.uploaded_chunks.load(::std::sync::atomic::Ordering::SeqCst);
+        assert!(uploaded_chunks >= 10);
+
+        // An edit of 10 bytes in the middle of the file re-encrypts the chunk holding them and the chunks whose
+        // encryption depends on it, the rest of the chunks are reused
+        let middle = data.len() / 2;
+        for byte in &mut data[middle..middle + 10] {
+            *byte = 0;
+        }
+        for modify in vec![true, false] {
+            let file = eval_option!(directory.find_file(&"large.dat".to_string()), "File not found").clone();
+            let mut writer = if modify {
+                let mut writer = Writer::new(client.clone(), Mode::Modify, directory.clone(), file);
+                writer.write(&data[middle..middle + 10], middle as u64);
+                writer
+            } else {
+                let mut writer = Writer::new(client.clone(), Mode::Overwrite, directory.clone(), file);
+                writer.write(&data, 0);
+                writer
+            };
+            let (updated_directory, _) = eval_result!(writer.finalise());
+            let uploaded_chunks = writer.storage.uploaded_chunks.load(::std::sync::atomic::Ordering::SeqCst);
+            assert!(uploaded_chunks >= 1 && uploaded_chunks <= 3);
 
-        let directory_helper = ::helper::directory_helper::DirectoryHelper::new(self.client.clone());
-        if let Some(updated_grand_parent) = try!(directory_helper.update(&directory)) {
-            Ok((directory, Some(updated_grand_parent)))
-        } else {
-            Ok((directory, None))
+            let file = eval_option!(updated_directory.find_file(&"large.dat".to_string()), "File not found");
+            let mut reader = ::helper::reader::Reader::new(client.clone(), file);
+            let size = reader.size();
+            assert!(eval_result!(reader.read(0, size)) == data);
         }
     }
 }
@@ -57,21 +57,20 @@ pub mod helper;
 pub mod metadata;
 /// Module for directory reltaed structs - DirectoryListin, DirectoryInfo
 pub mod directory_listing;
+/// Constants for directory names and the tags of directory listings
+pub mod consts;
 
-/// Root directory name
-pub const ROOT_DIRECTORY_NAME: &'static str = "USER_ROOT";
-/// Configuration directory Name stored in the session packet
-pub const CONFIGURATION_DIRECTORY_NAME: &'static str = "CONFIGURATION_ROOT";
-/// Tag representing the Versioned Directory Listing
-pub const VERSIONED_DIRECTORY_LISTING_TAG: u64 = safe_core::CLIENT_STRUCTURED_DATA_TAG + 100;
-/// Tag representing the Versioned Directory Listing
-pub const UNVERSIONED_DIRECTORY_LISTING_TAG: u64 = VERSIONED_DIRECTORY_LISTING_TAG + 1;
+pub use consts::{ROOT_DIRECTORY_NAME, CONFIGURATION_DIRECTORY_NAME, SHARED_DIRECTORIES_CONFIGURATION_NAME,
+                 VERSIONED_DIRECTORY_LISTING_TAG, UNVERSIONED_DIRECTORY_LISTING_TAG};
 
-/// AccessLevel indicates whether the container is Private or Public shared
+/// AccessLevel indicates whether the container is Private, Public or Shared
 #[derive(RustcEncodable, RustcDecodable, PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
 pub enum AccessLevel {
     /// Private Directory where the directory is encrypted with users private keys
     Private,
     /// Public Directory where the directory is not encrypted and anyone can read the contents of it
     Public,
+    /// Shared Directory where the directory is encrypted with a symmetric key held by everyone it is shared with.
+    /// The key is never stored in the network and has to be supplied to the helpers reading or writing the directory
+    Shared,
 }
@@ -17,12 +17,13 @@
 
 /// DirectoryKey represnts the meta information about a directory
 /// A directory can be feteched with the DirectoryKey
-#[derive(Debug, RustcEncodable, RustcDecodable, PartialEq, Eq, PartialOrd, Ord, Clone)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
 pub struct DirectoryKey {
     id          : ::routing::NameType,
     type_tag    : u64,
     versioned   : bool,
     access_level: ::AccessLevel,
+    nonce       : Option<[u8; ::sodiumoxide::crypto::box_::NONCEBYTES]>,
 }
 
 impl DirectoryKey {
@@ -32,10 +33,32 @@ impl DirectoryKey {
             id          : directory_id,
             type_tag    : type_tag,
             versioned   : versioned,
-            access_level: access_level
+            access_level: access_level,
+            nonce       : None,
         }
     }
 
+    /// Returns the key with the nonce passed, which a Private directory with the key is encrypted with instead of the
+    /// nonce generated from its id, so that clients sharing the key reproduce the same encrypted form of the directory.
+    /// Reusing a nonce with the same encryption keys weakens the encryption: whoever sees two directories encrypted with
+    /// the same nonce and keys can learn how their contents differ. A nonce should therefore only be shared between
+    /// clients which are trusted with the content of the directory, and never be used with more than one directory.
+    pub fn with_nonce(mut self, nonce: ::sodiumoxide::crypto::box_::Nonce) -> DirectoryKey {
+        self.nonce = Some(nonce.0);
+        self
+    }
+
+    /// Returns the nonce set through `with_nonce`, if any
+    pub fn get_nonce(&self) -> Option<::sodiumoxide::crypto::box_::Nonce> {
+        self.nonce.map(::sodiumoxide::crypto::box_::Nonce)
+    }
+
+    /// Returns the nonce a Private directory with the key is encrypted with, which is the nonce set through `with_nonce`
+    /// if any, else the nonce generated from the id of the directory
+    pub fn get_encryption_nonce(&self) -> ::sodiumoxide::crypto::box_::Nonce {
+        self.get_nonce().unwrap_or_else(|| ::directory_listing::DirectoryListing::generate_nonce(&self.id))
+    }
+
     /// Returns the id
     pub fn get_id(&self) -> &::routing::NameType {
         &self.id
@@ -53,6 +76,122 @@ impl DirectoryKey {
         &self.access_level
     }
 
+    /// Returns a printable token through which the directory can be shared with other users.
+    /// The token only holds the key of the directory. The shared key of a Shared directory has to be passed separately
+    pub fn to_share_token(&self) -> Result<String, ::errors::NfsError> {
+        use ::rustc_serialize::hex::ToHex;
+        Ok(try!(::safe_core::utility::serialise(self)).to_hex())
+    }
+
+    /// Returns the DirectoryKey held by a token created through `to_share_token`.
+    /// The token must hold nothing but the serialised key.
+    /// Returns NfsError::InvalidKeyFormat if the token is not a valid share token
+    pub fn from_share_token(token: &str) -> Result<DirectoryKey, ::errors::NfsError> {
+        use ::rustc_serialize::hex::FromHex;
+        let serialised_key = try!(token.from_hex().map_err(|_| ::errors::NfsError::InvalidKeyFormat));
+        DirectoryKey::deserialise(&serialised_key)
+    }
+
+    /// Deserialises a DirectoryKey serialised on its own, e.g. in a share token. Keys serialised before the nonce was
+    /// introduced are also accepted. The data must hold nothing but the serialised key.
+    /// Returns NfsError::InvalidKeyFormat if the data is not a serialised DirectoryKey
+    pub fn deserialise(data: &[u8]) -> Result<DirectoryKey, ::errors::NfsError> {
+        if let Ok(directory_key) = ::safe_core::utility::deserialise::<DirectoryKey>(data) {
+            if try!(::safe_core::utility::serialise(&directory_key)) == data {
+                return Ok(directory_key);
+            }
+        }
+        debug!("Falling back to decode directory key without nonce ...");
+        if let Ok(legacy_key) = ::safe_core::utility::deserialise::<KeyWithoutNonce>(data) {
+            if try!(::safe_core::utility::serialise(&legacy_key)) == data {
+                return Ok(legacy_key.0);
+            }
+        }
+        Err(::errors::NfsError::InvalidKeyFormat)
+    }
+
+    /// Encodes the DirectoryKey in the layout of the given schema version of DirectoryListing, leaving out the nonce
+    /// before it was introduced
+    pub fn encode_with_schema<E: ::rustc_serialize::Encoder>(&self,
+                                                             e             : &mut E,
+                                                             schema_version: u16) -> Result<(), E::Error> {
+        use ::rustc_serialize::Encodable;
+        e.emit_struct("DirectoryKey", DirectoryKey::field_count(schema_version), |e| {
+            try!(e.emit_struct_field("id",           0, |e| self.id.encode(e)));
+            try!(e.emit_struct_field("type_tag",     1, |e| self.type_tag.encode(e)));
+            try!(e.emit_struct_field("versioned",    2, |e| self.versioned.encode(e)));
+            try!(e.emit_struct_field("access_level", 3, |e| self.access_level.encode(e)));
+            if schema_version >= 8 {
+                try!(e.emit_struct_field("nonce",    4, |e| self.nonce.map(|nonce| nonce.to_vec()).encode(e)));
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Decodes a DirectoryKey serialised in the layout of the given schema version of DirectoryListing.
+    /// Keys saved before the nonce was introduced have none
+    pub fn decode_with_schema<D: ::rustc_serialize::Decoder>(d             : &mut D,
+                                                             schema_version: u16) -> Result<DirectoryKey, D::Error> {
+        use ::rustc_serialize::Decodable;
+        d.read_struct("DirectoryKey", DirectoryKey::field_count(schema_version), |d| {
+            let id = try!(d.read_struct_field("id", 0, |d| Decodable::decode(d)));
+            let type_tag = try!(d.read_struct_field("type_tag", 1, |d| Decodable::decode(d)));
+            let versioned = try!(d.read_struct_field("versioned", 2, |d| Decodable::decode(d)));
+            let access_level = try!(d.read_struct_field("access_level", 3, |d| Decodable::decode(d)));
+            let nonce = if schema_version < 8 {
+                None
+            } else {
+                let nonce: Option<Vec<u8>> = try!(d.read_struct_field("nonce", 4, |d| Decodable::decode(d)));
+                match nonce {
+                    Some(nonce) => match ::sodiumoxide::crypto::box_::Nonce::from_slice(&nonce) {
+                        Some(nonce) => Some(nonce.0),
+                        None => return Err(d.error("Invalid nonce in DirectoryKey")),
+                    },
+                    None => None,
+                }
+            };
+            Ok(DirectoryKey {
+                id          : id,
+                type_tag    : type_tag,
+                versioned   : versioned,
+                access_level: access_level,
+                nonce       : nonce,
+            })
+        })
+    }
+
+    /// Number of fields serialised in the layout of the given schema version
+    fn field_count(schema_version: u16) -> usize {
+        if schema_version >= 8 { 5 } else { 4 }
+    }
+}
+
+impl ::rustc_serialize::Encodable for DirectoryKey {
+    fn encode<E: ::rustc_serialize::Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+        self.encode_with_schema(e, ::directory_listing::DIRECTORY_LISTING_SCHEMA_VERSION)
+    }
+}
+
+impl ::rustc_serialize::Decodable for DirectoryKey {
+    fn decode<D: ::rustc_serialize::Decoder>(d: &mut D) -> Result<DirectoryKey, D::Error> {
+        DirectoryKey::decode_with_schema(d, ::directory_listing::DIRECTORY_LISTING_SCHEMA_VERSION)
+    }
+}
+
+/// DirectoryKey as serialised before the nonce was introduced in schema version 8
+struct KeyWithoutNonce(DirectoryKey);
+
+impl ::rustc_serialize::Encodable for KeyWithoutNonce {
+    fn encode<E: ::rustc_serialize::Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+        self.0.encode_with_schema(e, 7)
+    }
+}
+
+impl ::rustc_serialize::Decodable for KeyWithoutNonce {
+    fn decode<D: ::rustc_serialize::Decoder>(d: &mut D) -> Result<KeyWithoutNonce, D::Error> {
+        Ok(KeyWithoutNonce(try!(DirectoryKey::decode_with_schema(d, 7))))
+    }
 }
 
 #[cfg(test)]
@@ -77,6 +216,47 @@ mod test {
         assert_eq!(*deserilaised_key.get_access_level(), access_level);
         assert_eq!(deserilaised_key.is_versioned(), versioned);
         assert_eq!(deserilaised_key.get_type_tag(), tag);
+        assert!(deserilaised_key.get_nonce().is_none());
+
+        let directory_key = directory_key.with_nonce(::sodiumoxide::crypto::box_::gen_nonce());
+        let serialised = eval_result!(::safe_core::utility::serialise(&directory_key));
+        let deserilaised_key: ::metadata::directory_key::DirectoryKey = eval_result!(::safe_core::utility::deserialise(&serialised));
+        assert_eq!(deserilaised_key, directory_key);
     }
 
+    #[test]
+    fn deserialise_directory_key_without_nonce() {
+        #[derive(RustcEncodable)]
+        struct LegacyDirectoryKey {
+            id          : ::routing::NameType,
+            type_tag    : u64,
+            versioned   : bool,
+            access_level: ::AccessLevel,
+        }
+
+        let id = ::routing::NameType(eval_result!(::safe_core::utility::generate_random_array_u8_64()));
+        let legacy_key = LegacyDirectoryKey {
+            id          : id.clone(),
+            type_tag    : 10u64,
+            versioned   : true,
+            access_level: ::AccessLevel::Private,
+        };
+        let serialised = eval_result!(::safe_core::utility::serialise(&legacy_key));
+        assert_eq!(eval_result!(::metadata::directory_key::DirectoryKey::deserialise(&serialised)),
+                   ::metadata::directory_key::DirectoryKey::new(id, 10u64, true, ::AccessLevel::Private));
+    }
+
+    #[test]
+    fn share_token() {
+        let id = ::routing::NameType(eval_result!(::safe_core::utility::generate_random_array_u8_64()));
+        let directory_key = ::metadata::directory_key::DirectoryKey::new(id, 10u64, true, ::AccessLevel::Public);
+        let token = eval_result!(directory_key.to_share_token());
+        assert_eq!(eval_result!(::metadata::directory_key::DirectoryKey::from_share_token(&token)), directory_key);
+        assert!(::metadata::directory_key::DirectoryKey::from_share_token("not a token").is_err());
+        assert!(::metadata::directory_key::DirectoryKey::from_share_token(&token[2..]).is_err());
+        match ::metadata::directory_key::DirectoryKey::from_share_token(&format!("{}00", token)) {
+            Err(::errors::NfsError::InvalidKeyFormat) => (),
+            _ => panic!("Expected InvalidKeyFormat"),
+        }
+    }
 }
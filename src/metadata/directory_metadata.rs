@@ -24,6 +24,10 @@ pub struct DirectoryMetadata {
     modified_time : ::time::Tm,
     user_metadata : Vec<u8>,
     parent_dir_key: Option<::metadata::directory_key::DirectoryKey>,
+    title         : Option<String>,
+    hidden        : bool,
+    size          : u64,
+    child_count   : u64,
 }
 
 impl DirectoryMetadata {
@@ -35,14 +39,31 @@ impl DirectoryMetadata {
                user_metadata : Vec<u8>,
                parent_dir_key: Option<::metadata::directory_key::DirectoryKey>) -> Result<DirectoryMetadata, ::errors::NfsError> {
         let id = ::routing::NameType::new(try!(::safe_core::utility::generate_random_array_u8_64()));
-        Ok(DirectoryMetadata {
-            key           : ::metadata::directory_key::DirectoryKey::new(id, type_tag, versioned, access_level),
+        Ok(DirectoryMetadata::new_with_key(::metadata::directory_key::DirectoryKey::new(id, type_tag, versioned, access_level),
+                                           name,
+                                           user_metadata,
+                                           parent_dir_key))
+    }
+
+    /// Create a new instance of Metadata for a directory with a known DirectoryKey.
+    /// The directory is hidden if its name starts with `.`, which can be overridden through `set_hidden`
+    pub fn new_with_key(key           : ::metadata::directory_key::DirectoryKey,
+                        name          : String,
+                        user_metadata : Vec<u8>,
+                        parent_dir_key: Option<::metadata::directory_key::DirectoryKey>) -> DirectoryMetadata {
+        let hidden = name.starts_with('.');
+        DirectoryMetadata {
+            key           : key,
             name          : name,
             created_time  : ::time::now_utc(),
             modified_time : ::time::now_utc(),
             user_metadata : user_metadata,
             parent_dir_key: parent_dir_key,
-        })
+            title         : None,
+            hidden        : hidden,
+            size          : 0,
+            child_count   : 0,
+        }
     }
 
     /// Return the id
@@ -81,6 +102,12 @@ impl DirectoryMetadata {
         &self.name
     }
 
+    /// Get the title of the directory to be displayed to users, which unlike the name is not used to address the
+    /// directory by path. Returns None if no title was set
+    pub fn get_title(&self) -> Option<&String> {
+        self.title.as_ref()
+    }
+
     /// Returns the DirectoryKey
     pub fn get_key(&self) -> &::metadata::directory_key::DirectoryKey {
         &self.key
@@ -102,6 +129,16 @@ impl DirectoryMetadata {
         self.name = name;
     }
 
+    /// Set the title of the directory to be displayed to users, leaving its name unchanged
+    pub fn set_title(&mut self, title: Option<String>) {
+        self.title = title;
+    }
+
+    /// Set time of creation
+    pub fn set_created_time(&mut self, created_time: ::time::Tm) {
+        self.created_time = created_time
+    }
+
     /// Set time of modification
     pub fn set_modified_time(&mut self, modified_time: ::time::Tm) {
         self.modified_time = modified_time
@@ -111,46 +148,156 @@ impl DirectoryMetadata {
     pub fn set_user_metadata(&mut self, user_metadata: Vec<u8>) {
         self.user_metadata = user_metadata;
     }
-}
 
-impl ::rustc_serialize::Encodable for DirectoryMetadata {
-    fn encode<E: ::rustc_serialize::Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+    /// Set the key of the directory, e.g. when the directory is saved under another tag
+    pub fn set_key(&mut self, key: ::metadata::directory_key::DirectoryKey) {
+        self.key = key;
+    }
+
+    /// Set the key of the parent directory
+    pub fn set_parent_dir_key(&mut self, parent_dir_key: Option<::metadata::directory_key::DirectoryKey>) {
+        self.parent_dir_key = parent_dir_key;
+    }
+
+    /// Returns true if the directory is to be hidden by frontends
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    /// Set whether the directory is to be hidden by frontends
+    pub fn set_hidden(&mut self, hidden: bool) {
+        self.hidden = hidden;
+    }
+
+    /// Returns the total size of the files held directly by the directory, not counting those of its sub directories,
+    /// as cached in the entry of the directory in its parent when the directory was last updated
+    pub fn get_size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns the number of files and sub directories in the directory, as cached in the entry of the directory in its
+    /// parent when the directory was last updated
+    pub fn get_child_count(&self) -> u64 {
+        self.child_count
+    }
+
+    /// Set the cached total size of the files held directly by the directory
+    pub fn set_size(&mut self, size: u64) {
+        self.size = size;
+    }
+
+    /// Set the cached number of files and sub directories in the directory
+    pub fn set_child_count(&mut self, child_count: u64) {
+        self.child_count = child_count;
+    }
+
+    /// Decodes DirectoryMetadata serialised in the layout of the given schema version of DirectoryListing.
+    /// Directories saved before the title was introduced get their name as title and directories saved before the hidden
+    /// flag was introduced are hidden if their name starts with `.`. Directories saved before the size and child count
+    /// were cached have both set to 0 until they are next updated
+    pub fn decode_with_schema<D: ::rustc_serialize::Decoder>(d             : &mut D,
+                                                             schema_version: u16) -> Result<DirectoryMetadata, D::Error> {
+        d.read_struct("DirectoryMetadata", DirectoryMetadata::field_count(schema_version), |d| {
+            let key = try!(d.read_struct_field("key",  0, |d| ::metadata::directory_key::DirectoryKey::decode_with_schema(d, schema_version)));
+            let name: String = try!(d.read_struct_field("name", 1, |d| ::rustc_serialize::Decodable::decode(d)));
+            let created_time = ::time::at_utc(::time::Timespec {
+                sec : try!(d.read_struct_field("created_time_sec",  2, |d| ::rustc_serialize::Decodable::decode(d))),
+                nsec: try!(d.read_struct_field("created_time_nsec", 3, |d| ::rustc_serialize::Decodable::decode(d))),
+            });
+            let modified_time = ::time::at_utc(::time::Timespec {
+                sec : try!(d.read_struct_field("modified_time_sec",  4, |d| ::rustc_serialize::Decodable::decode(d))),
+                nsec: try!(d.read_struct_field("modified_time_nsec", 5, |d| ::rustc_serialize::Decodable::decode(d))),
+            });
+            let user_metadata = try!(d.read_struct_field("user_metadata",  6, |d| ::rustc_serialize::Decodable::decode(d)));
+            let parent_dir_key = try!(d.read_struct_field("parent_dir_key", 7, |d| d.read_option(|d, is_some| {
+                if is_some {
+                    Ok(Some(try!(::metadata::directory_key::DirectoryKey::decode_with_schema(d, schema_version))))
+                } else {
+                    Ok(None)
+                }
+            })));
+            let title = if schema_version < 3 {
+                Some(name.clone())
+            } else {
+                try!(d.read_struct_field("title", 8, |d| ::rustc_serialize::Decodable::decode(d)))
+            };
+            let hidden = if schema_version < 4 {
+                name.starts_with('.')
+            } else {
+                try!(d.read_struct_field("hidden", 9, |d| ::rustc_serialize::Decodable::decode(d)))
+            };
+            let (size, child_count) = if schema_version < 6 {
+                (0, 0)
+            } else {
+                (try!(d.read_struct_field("size",        10, |d| ::rustc_serialize::Decodable::decode(d))),
+                 try!(d.read_struct_field("child_count", 11, |d| ::rustc_serialize::Decodable::decode(d))))
+            };
+            Ok(DirectoryMetadata {
+                key           : key,
+                name          : name,
+                created_time  : created_time,
+                modified_time : modified_time,
+                user_metadata : user_metadata,
+                parent_dir_key: parent_dir_key,
+                title         : title,
+                hidden        : hidden,
+                size          : size,
+                child_count   : child_count,
+            })
+        })
+    }
+
+    /// Encodes the DirectoryMetadata in the layout of the given schema version of DirectoryListing, leaving out the
+    /// fields introduced after it, e.g. to recompute the checksum of a listing saved in that layout
+    pub fn encode_with_schema<E: ::rustc_serialize::Encoder>(&self,
+                                                             e             : &mut E,
+                                                             schema_version: u16) -> Result<(), E::Error> {
+        use ::rustc_serialize::Encodable;
         let created_time = self.created_time.to_timespec();
         let modified_time = self.modified_time.to_timespec();
 
-        e.emit_struct("DirectoryMetadata", 8, |e| {
-            try!(e.emit_struct_field("key",                0, |e| self.key.encode(e)));
+        e.emit_struct("DirectoryMetadata", DirectoryMetadata::field_count(schema_version), |e| {
+            try!(e.emit_struct_field("key",                0, |e| self.key.encode_with_schema(e, schema_version)));
             try!(e.emit_struct_field("name",               1, |e| self.name.encode(e)));
             try!(e.emit_struct_field("created_time_sec",   2, |e| created_time.sec.encode(e)));
             try!(e.emit_struct_field("created_time_nsec",  3, |e| created_time.nsec.encode(e)));
             try!(e.emit_struct_field("modified_time_sec",  4, |e| modified_time.sec.encode(e)));
             try!(e.emit_struct_field("modified_time_nsec", 5, |e| modified_time.nsec.encode(e)));
             try!(e.emit_struct_field("user_metadata",      6, |e| self.user_metadata.encode(e)));
-            try!(e.emit_struct_field("parent_dir_key",     7, |e| self.parent_dir_key.encode(e)));
+            try!(e.emit_struct_field("parent_dir_key",     7, |e| e.emit_option(|e| match self.parent_dir_key {
+                Some(ref parent_dir_key) => e.emit_option_some(|e| parent_dir_key.encode_with_schema(e, schema_version)),
+                None => e.emit_option_none(),
+            })));
+            if schema_version >= 3 {
+                try!(e.emit_struct_field("title",          8, |e| self.title.encode(e)));
+            }
+            if schema_version >= 4 {
+                try!(e.emit_struct_field("hidden",         9, |e| self.hidden.encode(e)));
+            }
+            if schema_version >= 6 {
+                try!(e.emit_struct_field("size",           10, |e| self.size.encode(e)));
+                try!(e.emit_struct_field("child_count",    11, |e| self.child_count.encode(e)));
+            }
 
             Ok(())
         })
     }
+
+    /// Number of fields serialised in the layout of the given schema version
+    fn field_count(schema_version: u16) -> usize {
+        if schema_version >= 6 { 12 } else if schema_version >= 4 { 10 } else if schema_version >= 3 { 9 } else { 8 }
+    }
+}
+
+impl ::rustc_serialize::Encodable for DirectoryMetadata {
+    fn encode<E: ::rustc_serialize::Encoder>(&self, e: &mut E) -> Result<(), E::Error> {
+        self.encode_with_schema(e, ::directory_listing::DIRECTORY_LISTING_SCHEMA_VERSION)
+    }
 }
 
 impl ::rustc_serialize::Decodable for DirectoryMetadata {
     fn decode<D: ::rustc_serialize::Decoder>(d: &mut D) -> Result<DirectoryMetadata, D::Error> {
-        d.read_struct("DirectoryMetadata", 8, |d| {
-            Ok(DirectoryMetadata {
-                key           : try!(d.read_struct_field("key",  0, |d| ::rustc_serialize::Decodable::decode(d))),
-                name          : try!(d.read_struct_field("name", 1, |d| ::rustc_serialize::Decodable::decode(d))),
-                created_time  : ::time::at_utc(::time::Timespec {
-                                                   sec : try!(d.read_struct_field("created_time_sec",  2, |d| ::rustc_serialize::Decodable::decode(d))),
-                                                   nsec: try!(d.read_struct_field("created_time_nsec", 3, |d| ::rustc_serialize::Decodable::decode(d))),
-                                               }),
-                modified_time : ::time::at_utc(::time::Timespec {
-                                                   sec : try!(d.read_struct_field("modified_time_sec",  4, |d| ::rustc_serialize::Decodable::decode(d))),
-                                                   nsec: try!(d.read_struct_field("modified_time_nsec", 5, |d| ::rustc_serialize::Decodable::decode(d))),
-                                               }),
-                user_metadata : try!(d.read_struct_field("user_metadata",  6, |d| ::rustc_serialize::Decodable::decode(d))),
-                parent_dir_key: try!(d.read_struct_field("parent_dir_key", 7, |d| ::rustc_serialize::Decodable::decode(d))),
-            })
-        })
+        DirectoryMetadata::decode_with_schema(d, ::directory_listing::DIRECTORY_LISTING_SCHEMA_VERSION)
     }
 }
 
@@ -206,4 +353,20 @@ mod test {
         assert_eq!(modified_time, *obj_after.get_modified_time());
         assert_eq!("index.txt".to_string(), *obj_after.get_name());
     }
+
+    #[test]
+    fn title() {
+        let mut obj_before = eval_result!(DirectoryMetadata::new("hello".to_string(),
+                                                                 99u64,
+                                                                 true,
+                                                                 ::AccessLevel::Private,
+                                                                 Vec::new(),
+                                                                 None));
+        assert!(obj_before.get_title().is_none());
+        obj_before.set_title(Some("Hello World!".to_string()));
+        let serialised_data = eval_result!(::safe_core::utility::serialise(&obj_before));
+        let obj_after: DirectoryMetadata = eval_result!(::safe_core::utility::deserialise(&serialised_data));
+        assert_eq!(obj_after.get_title(), Some(&"Hello World!".to_string()));
+        assert_eq!(*obj_after.get_name(), "hello".to_string());
+    }
 }
@@ -0,0 +1,56 @@
+// Copyright 2015 MaidSafe.net limited.
+//
+// This SAFE Network Software is licensed to you under (1) the MaidSafe.net Commercial License,
+// version 1.0 or later, or (2) The General Public License (GPL), version 3, depending on which
+// licence you accepted on initial access to the Software (the "Licences").
+//
+// By contributing code to the SAFE Network Software, or to this project generally, you agree to be
+// bound by the terms of the MaidSafe Contributor Agreement, version 1.0.  This, along with the
+// Licenses can be found in the root directory of this project at LICENSE, COPYING and CONTRIBUTOR.
+//
+// Unless required by applicable law or agreed to in writing, the SAFE Network Software distributed
+// under the GPL Licence is distributed on an "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+// KIND, either express or implied.
+//
+// Please review the Licences for the specific language governing permissions and limitations
+// relating to use of the SAFE Network Software.
+
+/// Advisory lock held on a File by an owner until it is released or it expires
+#[derive(RustcEncodable, RustcDecodable, Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub struct FileLock {
+    owner          : String,
+    locked_time_sec: i64,
+    expiry_time_sec: i64,
+}
+
+impl FileLock {
+    /// Create a new FileLock held by the owner from now on for the given duration
+    pub fn new(owner: String, duration: ::time::Duration) -> FileLock {
+        let now = ::time::now_utc().to_timespec().sec;
+        FileLock {
+            owner          : owner,
+            locked_time_sec: now,
+            expiry_time_sec: now + duration.num_seconds(),
+        }
+    }
+
+    /// Get the owner of the lock
+    pub fn get_owner(&self) -> &String {
+        &self.owner
+    }
+
+    /// Get the time at which the lock was taken
+    pub fn get_locked_time(&self) -> ::time::Tm {
+        ::time::at_utc(::time::Timespec::new(self.locked_time_sec, 0))
+    }
+
+    /// Get the time at which the lock expires
+    pub fn get_expiry_time(&self) -> ::time::Tm {
+        ::time::at_utc(::time::Timespec::new(self.expiry_time_sec, 0))
+    }
+
+    /// Returns true once the lock has expired, after which it no longer has any effect
+    pub fn is_expired(&self) -> bool {
+        ::time::now_utc().to_timespec().sec >= self.expiry_time_sec
+    }
+}
@@ -23,17 +23,23 @@ pub struct FileMetadata {
     created_time : ::time::Tm,
     modified_time: ::time::Tm,
     user_metadata: Vec<u8>,
+    lock         : Option<::metadata::file_lock::FileLock>,
+    hidden       : bool,
 }
 
 impl FileMetadata {
-    /// Create a new instance of FileMetadata
+    /// Create a new instance of FileMetadata.
+    /// The file is hidden if its name starts with `.`, which can be overridden through `set_hidden`
     pub fn new(name: String, user_metadata: Vec<u8>) -> FileMetadata {
+        let hidden = name.starts_with('.');
         FileMetadata {
             name         : name,
             size         : 0,
             created_time : ::time::now_utc(),
             modified_time: ::time::now_utc(),
             user_metadata: user_metadata,
+            lock         : None,
+            hidden       : hidden,
         }
     }
 
@@ -83,6 +89,94 @@ impl FileMetadata {
     pub fn set_user_metadata(&mut self, user_metadata: Vec<u8>) {
         self.user_metadata = user_metadata;
     }
+
+    /// Get the lock held on the file, if any. An expired lock is still returned, see `get_active_lock`
+    pub fn get_lock(&self) -> Option<&::metadata::file_lock::FileLock> {
+        self.lock.iter().next()
+    }
+
+    /// Get the lock held on the file, if any, ignoring an expired lock
+    pub fn get_active_lock(&self) -> Option<&::metadata::file_lock::FileLock> {
+        self.lock.iter().find(|lock| !lock.is_expired())
+    }
+
+    /// Set or clear the lock held on the file
+    pub fn set_lock(&mut self, lock: Option<::metadata::file_lock::FileLock>) {
+        self.lock = lock;
+    }
+
+    /// Returns true if the file is to be hidden by frontends
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    /// Set whether the file is to be hidden by frontends
+    pub fn set_hidden(&mut self, hidden: bool) {
+        self.hidden = hidden;
+    }
+
+    /// Decodes FileMetadata serialised in the layout of the given schema version of DirectoryListing.
+    /// Fields introduced after that version are filled in with their defaults. Files saved before the hidden flag was
+    /// introduced are hidden if their name starts with `.`
+    pub fn decode_with_schema<D: ::rustc_serialize::Decoder>(d: &mut D, schema_version: u16) -> Result<FileMetadata, D::Error> {
+        let field_count = if schema_version >= 4 { 9 } else if schema_version >= 2 { 8 } else { 7 };
+        d.read_struct("FileMetadata", field_count, |d| {
+            let mut metadata = FileMetadata {
+                name         : try!(d.read_struct_field("name", 0, |d| ::rustc_serialize::Decodable::decode(d))),
+                size         : try!(d.read_struct_field("size", 1, |d| ::rustc_serialize::Decodable::decode(d))),
+                created_time : ::time::at_utc(::time::Timespec {
+                                                  sec : try!(d.read_struct_field("created_time_sec",  2, |d| ::rustc_serialize::Decodable::decode(d))),
+                                                  nsec: try!(d.read_struct_field("created_time_nsec", 3, |d| ::rustc_serialize::Decodable::decode(d))),
+                                              }),
+                modified_time: ::time::at_utc(::time::Timespec {
+                                                  sec : try!(d.read_struct_field("modified_time_sec",  4, |d| ::rustc_serialize::Decodable::decode(d))),
+                                                  nsec: try!(d.read_struct_field("modified_time_nsec", 5, |d| ::rustc_serialize::Decodable::decode(d))),
+                                              }),
+                user_metadata: try!(d.read_struct_field("user_metadata",  6, |d| ::rustc_serialize::Decodable::decode(d))),
+                lock         : if schema_version >= 2 {
+                                   try!(d.read_struct_field("lock", 7, |d| ::rustc_serialize::Decodable::decode(d)))
+                               } else {
+                                   None
+                               },
+                hidden       : if schema_version >= 4 {
+                                   try!(d.read_struct_field("hidden", 8, |d| ::rustc_serialize::Decodable::decode(d)))
+                               } else {
+                                   false
+                               },
+            };
+            if schema_version < 4 {
+                metadata.hidden = metadata.name.starts_with('.');
+            }
+            Ok(metadata)
+        })
+    }
+
+    /// Get the value of an attribute.
+    /// Attributes are key-value pairs kept as a serialised map in the user metadata. None is returned if the attribute
+    /// is not set or if the user metadata holds custom data rather than attributes.
+    pub fn get_attribute(&self, key: &str) -> Option<String> {
+        self.get_attributes().and_then(|mut attributes| attributes.remove(key))
+    }
+
+    /// Get all the attributes.
+    /// Returns None if the user metadata holds custom data rather than attributes
+    pub fn get_attributes(&self) -> Option<::std::collections::BTreeMap<String, String>> {
+        if self.user_metadata.is_empty() {
+            Some(::std::collections::BTreeMap::new())
+        } else {
+            ::safe_core::utility::deserialise(&self.user_metadata).ok()
+        }
+    }
+
+    /// Set the value of an attribute, replacing any earlier value of it.
+    /// Returns NfsError::ParameterIsNotValid if the user metadata holds custom data rather than attributes, as that data
+    /// would otherwise be lost.
+    pub fn set_attribute(&mut self, key: &str, value: &str) -> Result<(), ::errors::NfsError> {
+        let mut attributes = try!(self.get_attributes().ok_or(::errors::NfsError::ParameterIsNotValid));
+        let _ = attributes.insert(key.to_string(), value.to_string());
+        self.user_metadata = try!(::safe_core::utility::serialise(&attributes));
+        Ok(())
+    }
 }
 
 impl ::rustc_serialize::Encodable for FileMetadata {
@@ -90,7 +184,7 @@ impl ::rustc_serialize::Encodable for FileMetadata {
         let created_time = self.created_time.to_timespec();
         let modified_time = self.modified_time.to_timespec();
 
-        e.emit_struct("FileMetadata", 7, |e| {
+        e.emit_struct("FileMetadata", 9, |e| {
             try!(e.emit_struct_field("name",               0, |e| self.name.encode(e)));
             try!(e.emit_struct_field("size",               1, |e| self.size.encode(e)));
             try!(e.emit_struct_field("created_time_sec",   2, |e| created_time.sec.encode(e)));
@@ -98,6 +192,8 @@ impl ::rustc_serialize::Encodable for FileMetadata {
             try!(e.emit_struct_field("modified_time_sec",  4, |e| modified_time.sec.encode(e)));
             try!(e.emit_struct_field("modified_time_nsec", 5, |e| modified_time.nsec.encode(e)));
             try!(e.emit_struct_field("user_metadata",      6, |e| self.user_metadata.encode(e)));
+            try!(e.emit_struct_field("lock",               7, |e| self.lock.encode(e)));
+            try!(e.emit_struct_field("hidden",             8, |e| self.hidden.encode(e)));
 
             Ok(())
         })
@@ -106,21 +202,7 @@ impl ::rustc_serialize::Encodable for FileMetadata {
 
 impl ::rustc_serialize::Decodable for FileMetadata {
     fn decode<D: ::rustc_serialize::Decoder>(d: &mut D) -> Result<FileMetadata, D::Error> {
-        d.read_struct("FileMetadata", 7, |d| {
-            Ok(FileMetadata {
-                name         : try!(d.read_struct_field("name", 0, |d| ::rustc_serialize::Decodable::decode(d))),
-                size         : try!(d.read_struct_field("size", 1, |d| ::rustc_serialize::Decodable::decode(d))),
-                created_time : ::time::at_utc(::time::Timespec {
-                                                  sec : try!(d.read_struct_field("created_time_sec",  2, |d| ::rustc_serialize::Decodable::decode(d))),
-                                                  nsec: try!(d.read_struct_field("created_time_nsec", 3, |d| ::rustc_serialize::Decodable::decode(d))),
-                                              }),
-                modified_time: ::time::at_utc(::time::Timespec {
-                                                  sec : try!(d.read_struct_field("modified_time_sec",  4, |d| ::rustc_serialize::Decodable::decode(d))),
-                                                  nsec: try!(d.read_struct_field("modified_time_nsec", 5, |d| ::rustc_serialize::Decodable::decode(d))),
-                                              }),
-                user_metadata: try!(d.read_struct_field("user_metadata",  6, |d| ::rustc_serialize::Decodable::decode(d))),
-            })
-        })
+        FileMetadata::decode_with_schema(d, ::directory_listing::DIRECTORY_LISTING_SCHEMA_VERSION)
     }
 }
 
@@ -136,4 +218,29 @@ mod test {
         let obj_after = eval_result!(::safe_core::utility::deserialise(&serialised_data));
         assert_eq!(obj_before, obj_after);
     }
+
+    #[test]
+    fn attributes() {
+        let mut metadata = FileMetadata::new("hello.txt".to_string(), Vec::new());
+        assert!(metadata.get_attribute("author").is_none());
+        eval_result!(metadata.set_attribute("author", "maidsafe"));
+        eval_result!(metadata.set_attribute("tag", "greeting"));
+        eval_result!(metadata.set_attribute("tag", "example"));
+        assert_eq!(metadata.get_attribute("author"), Some("maidsafe".to_string()));
+        assert_eq!(metadata.get_attribute("tag"), Some("example".to_string()));
+        assert_eq!(eval_option!(metadata.get_attributes(), "Attributes not found").len(), 2);
+
+        let serialised_data = eval_result!(::safe_core::utility::serialise(&metadata));
+        let deserialised: FileMetadata = eval_result!(::safe_core::utility::deserialise(&serialised_data));
+        assert_eq!(deserialised.get_attribute("author"), Some("maidsafe".to_string()));
+
+        // Custom user metadata is never overwritten by attributes
+        let mut metadata = FileMetadata::new("hello.txt".to_string(), "{mime: \"application/json\"}".to_string().into_bytes());
+        assert!(metadata.get_attributes().is_none());
+        match metadata.set_attribute("author", "maidsafe") {
+            Err(::errors::NfsError::ParameterIsNotValid) => (),
+            _ => panic!("Expected ParameterIsNotValid"),
+        }
+        assert_eq!(*metadata.get_user_metadata(), "{mime: \"application/json\"}".to_string().into_bytes());
+    }
 }
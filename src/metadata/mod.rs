@@ -17,6 +17,8 @@
 
 /// FileMetadata
 pub mod file_metadata;
+/// FileLock
+pub mod file_lock;
 /// DirectoryKey
 pub mod directory_key;
 /// DirectoryMetadata
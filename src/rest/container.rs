@@ -176,9 +176,7 @@ impl Container {
         let dir_listing = match version {
             Some(version_id) => {
                     debug!("Retrieving using version id ...");
-                    try!(directory_helper.get_by_version(directory_metadata.get_id(),
-                                                         directory_metadata.get_access_level(),
-                                                         ::routing::NameType(version_id)))
+                    try!(directory_helper.get_by_version(directory_metadata.get_key(), ::routing::NameType(version_id)))
             },
             None =>  {
                     debug!("Retrieving the latest version ...");
@@ -27,6 +27,11 @@ impl ContainerInfo {
         self.metadata.get_name()
     }
 
+    /// Get the title of the Container to be displayed to users. Returns None if no title was set
+    pub fn get_title(&self) -> Option<&String> {
+        self.metadata.get_title()
+    }
+
     /// Get the creation time for this Container
     pub fn get_created_time(&self) -> &::time::Tm {
         self.metadata.get_created_time()